@@ -0,0 +1,110 @@
+//! Compares the CPU time of the two display renderers on SDL's software renderer:
+//! 2048 `fill_rect` calls per refresh, as the display driver used to draw, against a
+//! single upload into a streaming texture scaled in one copy, as it draws now.
+//!
+//! Run with `cargo bench --bench render`.
+
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::surface::Surface;
+use std::time::{Duration, Instant};
+
+const SCREEN_WIDTH: usize = 64;
+const SCREEN_HEIGHT: usize = 32;
+const SCALE_FACTOR: u32 = 20;
+const FOREGROUND_COLOR: [u8; 3] = [0x41, 0xEC, 0x9D];
+const BACKGROUND_COLOR: [u8; 3] = [0x0F, 0x0F, 0x0F];
+
+// Instructions per 60Hz frame, the old renderer could refresh after each of them
+const FRAME_SIZE: u32 = 15;
+const ITERATIONS: u32 = 2000;
+
+fn main() {
+    let mut canvas = Surface::new(
+        SCREEN_WIDTH as u32 * SCALE_FACTOR,
+        SCREEN_HEIGHT as u32 * SCALE_FACTOR,
+        PixelFormatEnum::RGB888,
+    )
+    .unwrap()
+    .into_canvas()
+    .unwrap();
+
+    // Checkerboard, half of the pixels lit
+    let buffer: Vec<bool> = (0..SCREEN_WIDTH * SCREEN_HEIGHT)
+        .map(|i| (i % SCREEN_WIDTH + i / SCREEN_WIDTH).is_multiple_of(2))
+        .collect();
+
+    let rects = measure(|| draw_rects(&mut canvas, &buffer));
+    let texture = measure_texture(&mut canvas, &buffer);
+
+    println!("fill_rect per draw:       {:>10.1?}", rects);
+    println!("texture per draw:         {:>10.1?}", texture);
+    println!(
+        "fill_rect per second:     {:>10.1?}  (up to {} draws per frame)",
+        rects * 60 * FRAME_SIZE,
+        FRAME_SIZE
+    );
+    println!(
+        "texture per second:       {:>10.1?}  (at most 1 draw per frame)",
+        texture * 60
+    );
+}
+
+fn measure(mut draw: impl FnMut()) -> Duration {
+    draw();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        draw();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn draw_rects(canvas: &mut Canvas<Surface>, buffer: &[bool]) {
+    let [fg_r, fg_g, fg_b] = FOREGROUND_COLOR;
+    let [bg_r, bg_g, bg_b] = BACKGROUND_COLOR;
+    canvas.set_draw_color(Color::RGB(bg_r, bg_g, bg_b));
+    canvas.clear();
+    for (i, pixel) in buffer.iter().enumerate() {
+        if *pixel {
+            canvas.set_draw_color(Color::RGB(fg_r, fg_g, fg_b));
+        } else {
+            canvas.set_draw_color(Color::RGB(bg_r, bg_g, bg_b));
+        }
+        let x = (i % SCREEN_WIDTH) as u32;
+        let y = (i / SCREEN_WIDTH) as u32;
+        let rect = Rect::new(
+            (x * SCALE_FACTOR) as i32,
+            (y * SCALE_FACTOR) as i32,
+            SCALE_FACTOR,
+            SCALE_FACTOR,
+        );
+        canvas.fill_rect(rect).unwrap();
+    }
+    canvas.present();
+}
+
+fn measure_texture(canvas: &mut Canvas<Surface>, buffer: &[bool]) -> Duration {
+    let texture_creator = canvas.texture_creator();
+    let mut texture = texture_creator
+        .create_texture_streaming(
+            PixelFormatEnum::RGB24,
+            SCREEN_WIDTH as u32,
+            SCREEN_HEIGHT as u32,
+        )
+        .unwrap();
+    let mut frame = vec![0u8; SCREEN_WIDTH * SCREEN_HEIGHT * 3];
+
+    measure(|| {
+        for (pixel, rgb) in buffer.iter().zip(frame.chunks_exact_mut(3)) {
+            rgb.copy_from_slice(if *pixel {
+                &FOREGROUND_COLOR
+            } else {
+                &BACKGROUND_COLOR
+            });
+        }
+        texture.update(None, &frame, SCREEN_WIDTH * 3).unwrap();
+        canvas.copy(&texture, None, None).unwrap();
+        canvas.present();
+    })
+}
@@ -0,0 +1,445 @@
+use clap::{Args, Parser, Subcommand};
+use log::LevelFilter;
+
+use crate::constants::{MEMORY_SIZE, OPCODE_SIZE};
+use crate::motherboard::layout::MemoryLayout;
+use crate::motherboard::quirks::{KeyWait, QuirkPreset};
+use crate::peripherals::display_filter::DisplayFilter;
+use crate::peripherals::keymap::KeymapPreset;
+use crate::peripherals::palette::PalettePreset;
+use crate::peripherals::post_processing::PostEffect;
+use crate::peripherals::terminal_driver::TerminalCharset;
+use crate::peripherals::tone::Waveform;
+use crate::toolchain::recorder::RecordingFormat;
+use crate::toolchain::screenshot::ScreenshotSize;
+
+/// A CHIP-8 virtual machine
+#[derive(Parser)]
+#[command(name = "chip8", version, about)]
+pub(crate) struct Cli {
+    /// Path to the configuration file [default: <XDG config directory>/chip8/config.toml]
+    #[arg(long, global = true, value_name = "PATH")]
+    pub(crate) config: Option<String>,
+
+    #[command(subcommand)]
+    pub(crate) command: Command,
+}
+
+#[derive(Subcommand)]
+pub(crate) enum Command {
+    /// Run a ROM in a window
+    Run {
+        /// ROM file, archive.zip[:entry], hex or base64 text, Octo cartridge GIF, or - for stdin
+        rom: String,
+
+        #[command(flatten)]
+        emulator: EmulatorOptions,
+
+        #[command(flatten)]
+        display: DisplayOptions,
+
+        #[command(flatten)]
+        input: InputOptions,
+
+        #[command(flatten)]
+        capture: CaptureOptions,
+    },
+
+    /// Run a ROM in the terminal, e.g. over SSH
+    Tui {
+        /// ROM file, archive.zip[:entry], hex or base64 text, Octo cartridge GIF, or - for stdin
+        rom: String,
+
+        /// Characters the display is drawn with
+        #[arg(long, value_enum, default_value_t = TerminalCharset::HalfBlock)]
+        charset: TerminalCharset,
+
+        #[command(flatten)]
+        emulator: EmulatorOptions,
+
+        #[command(flatten)]
+        input: InputOptions,
+
+        #[command(flatten)]
+        capture: CaptureOptions,
+    },
+
+    /// Print the disassembled ROM
+    Disasm {
+        /// ROM file, archive.zip[:entry], hex or base64 text, Octo cartridge GIF, or - for stdin
+        rom: String,
+
+        #[command(flatten)]
+        layout: LayoutOptions,
+    },
+
+    /// Print the likely bugs found in a ROM by static analysis
+    Lint {
+        /// ROM file, archive.zip[:entry], hex or base64 text, Octo cartridge GIF, or - for stdin
+        rom: String,
+
+        #[command(flatten)]
+        layout: LayoutOptions,
+    },
+
+    /// Run a ROM without a window, printing every executed instruction
+    Trace {
+        /// ROM file, archive.zip[:entry], hex or base64 text, Octo cartridge GIF, or - for stdin
+        rom: String,
+
+        /// Number of instructions to execute
+        #[arg(long, default_value_t = 1000)]
+        cycles: usize,
+
+        #[command(flatten)]
+        emulator: EmulatorOptions,
+    },
+
+    /// Print the disassembly of a ROM annotated with the coverage merged from lcov tracefiles
+    Coverage {
+        /// ROM file, archive.zip[:entry], hex or base64 text, Octo cartridge GIF, or - for stdin
+        rom: String,
+
+        /// Tracefiles written by --coverage
+        #[arg(required = true)]
+        tracefiles: Vec<String>,
+
+        #[command(flatten)]
+        layout: LayoutOptions,
+    },
+
+    /// Print information about a ROM
+    Info {
+        /// ROM file, archive.zip[:entry], hex or base64 text, Octo cartridge GIF, or - for stdin
+        rom: String,
+
+        #[command(flatten)]
+        emulator: EmulatorOptions,
+    },
+
+    /// Run a ROM without a window and print the final screen
+    Headless {
+        /// ROM file, archive.zip[:entry], hex or base64 text, Octo cartridge GIF, or - for stdin
+        rom: String,
+
+        /// Number of 60Hz frames to execute
+        #[arg(long, default_value_t = 600)]
+        frames: usize,
+
+        /// Key presses and releases to feed, one '<frame> press|release <key>' per line
+        #[arg(long, value_name = "PATH")]
+        input_script: Option<String>,
+
+        #[command(flatten)]
+        emulator: EmulatorOptions,
+
+        #[command(flatten)]
+        capture: CaptureOptions,
+    },
+
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub(crate) enum ConfigCommand {
+    /// Print the effective settings, including the overrides for a ROM and the given options
+    Dump {
+        /// Path to a ROM file whose overrides are applied
+        rom: Option<String>,
+
+        #[command(flatten)]
+        emulator: EmulatorOptions,
+
+        #[command(flatten)]
+        display: DisplayOptions,
+
+        #[command(flatten)]
+        input: InputOptions,
+    },
+}
+
+/// Options shared by every command that executes a ROM
+#[derive(Args, Default)]
+pub(crate) struct EmulatorOptions {
+    /// Instructions executed per second
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub(crate) ips: Option<u32>,
+
+    /// Quirk preset used for opcodes whose behaviour differs between interpreters
+    #[arg(long, value_enum)]
+    pub(crate) quirks: Option<QuirkPreset>,
+
+    /// Key event completing LD Vx, K (FX0A), replacing the one of the quirk preset
+    #[arg(long, value_enum)]
+    pub(crate) key_wait: Option<KeyWait>,
+
+    /// Pitch of the beeper in Hz
+    #[arg(long, value_name = "HZ", value_parser = parse_frequency)]
+    pub(crate) beep_frequency: Option<f32>,
+
+    /// Volume of the beeper, from 0 to 1
+    #[arg(long, value_name = "VOLUME", value_parser = parse_volume)]
+    pub(crate) beep_volume: Option<f32>,
+
+    /// Shape of the beeper sound
+    #[arg(long, value_enum)]
+    pub(crate) waveform: Option<Waveform>,
+
+    /// Seed for the random number generator used by RND
+    #[arg(long)]
+    pub(crate) seed: Option<u64>,
+
+    /// Path to the log file
+    #[arg(long, value_name = "PATH")]
+    pub(crate) log_file: Option<String>,
+
+    /// Log level (off, error, warn, info, debug, trace)
+    #[arg(long, value_name = "LEVEL", value_parser = parse_log_level)]
+    pub(crate) log_level: Option<LevelFilter>,
+
+    #[command(flatten)]
+    pub(crate) layout: LayoutOptions,
+}
+
+/// Options for commands that load a ROM into memory or disassemble it
+#[derive(Args, Clone, Default)]
+pub(crate) struct LayoutOptions {
+    /// Memory layout giving the addresses the ROM is loaded at and started from
+    #[arg(long, value_enum)]
+    pub(crate) layout: Option<MemoryLayout>,
+
+    /// Address the ROM is loaded at, as 0x hex or decimal, replacing the one of the layout
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_address)]
+    pub(crate) load_address: Option<usize>,
+
+    /// Address execution starts at, as 0x hex or decimal [default: the load address when
+    /// one is given, else the one of the layout]
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_address)]
+    pub(crate) entry_address: Option<usize>,
+}
+
+/// Options for commands that open a window
+#[derive(Args)]
+pub(crate) struct DisplayOptions {
+    /// Scaling factor for the 64x32 display
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub(crate) scale: Option<u32>,
+
+    /// Color palette
+    #[arg(long, value_enum)]
+    pub(crate) palette: Option<PalettePreset>,
+
+    /// Colors replacing the first ones of the palette, as RRGGBB hex:
+    /// background, foreground, plane 2, both planes
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_color, value_delimiter = ',', num_args = 1..=4)]
+    pub(crate) colors: Vec<[u8; 3]>,
+
+    /// Foreground color as RRGGBB hex
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_color)]
+    pub(crate) foreground: Option<[u8; 3]>,
+
+    /// Background color as RRGGBB hex
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_color)]
+    pub(crate) background: Option<[u8; 3]>,
+
+    /// Do not play the beeper, a WAV capture still records it
+    #[arg(long)]
+    pub(crate) no_sound: bool,
+
+    /// Show the emulated frames and instructions per second
+    #[arg(long)]
+    pub(crate) show_fps: bool,
+
+    /// Open a second window with the registers, stack, keypad, disassembly and memory,
+    /// where clicking an instruction sets a breakpoint
+    #[arg(long)]
+    pub(crate) debugger: bool,
+
+    /// Start in fullscreen mode
+    #[arg(long)]
+    pub(crate) fullscreen: bool,
+
+    /// Scale the display by whole numbers only, keeping every pixel the same size
+    #[arg(long)]
+    pub(crate) integer_scale: bool,
+
+    /// Flicker reduction filter
+    #[arg(long, value_enum)]
+    pub(crate) filter: Option<DisplayFilter>,
+
+    /// Brightness kept by a turned off pixel after each frame, from 0 to 1 (phosphor and blend filters)
+    #[arg(long, value_name = "DECAY", value_parser = parse_decay)]
+    pub(crate) filter_decay: Option<f32>,
+
+    /// Number of frames combined by the blend filter
+    #[arg(long, value_name = "FRAMES", value_parser = clap::value_parser!(u32).range(1..))]
+    pub(crate) blend_frames: Option<u32>,
+
+    /// Post-processing effects, comma separated or repeated (replaces the configured ones)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub(crate) effect: Vec<PostEffect>,
+}
+
+pub(crate) fn parse_color(value: &str) -> Result<[u8; 3], String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("invalid color '{}', expected RRGGBB", value));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    Ok([channel(0), channel(2), channel(4)])
+}
+
+pub(crate) fn parse_frequency(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(frequency) if (20.0..=20000.0).contains(&frequency) => Ok(frequency),
+        _ => Err(format!(
+            "invalid frequency '{}', expected 20 to 20000",
+            value
+        )),
+    }
+}
+
+pub(crate) fn parse_volume(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(volume) if (0.0..=1.0).contains(&volume) => Ok(volume),
+        _ => Err(format!("invalid volume '{}', expected 0 to 1", value)),
+    }
+}
+
+/// Parses an address of an instruction, as hex with a 0x prefix or decimal
+pub(crate) fn parse_address(value: &str) -> Result<usize, String> {
+    let address = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    address
+        .ok()
+        .and_then(|address| check_address(address).ok())
+        .ok_or_else(|| address_error(value))
+}
+
+/// Checks that an address of an instruction, as read from the config file, is in the memory
+pub(crate) fn check_address(address: usize) -> Result<usize, String> {
+    if address <= MEMORY_SIZE - OPCODE_SIZE {
+        Ok(address)
+    } else {
+        Err(address_error(&format!("{:#05X}", address)))
+    }
+}
+
+fn address_error(value: &str) -> String {
+    format!(
+        "invalid address '{}', expected 0x000 to {:#05X}",
+        value,
+        MEMORY_SIZE - OPCODE_SIZE
+    )
+}
+
+pub(crate) fn parse_decay(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(decay) if (0.0..=1.0).contains(&decay) => Ok(decay),
+        _ => Err(format!("invalid decay '{}', expected 0 to 1", value)),
+    }
+}
+
+/// Options for commands that can save what the emulator shows
+#[derive(Args)]
+pub(crate) struct CaptureOptions {
+    /// Write a screenshot when the emulator exits
+    #[arg(long)]
+    pub(crate) screenshot: bool,
+
+    /// Directory the screenshots and recordings are written to
+    #[arg(long, value_name = "DIR")]
+    pub(crate) capture_dir: Option<String>,
+
+    /// Resolution of the screenshots
+    #[arg(long, value_enum)]
+    pub(crate) screenshot_size: Option<ScreenshotSize>,
+
+    /// Record the display from the start, to PATH or to a timestamped file in the capture directory
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = None)]
+    pub(crate) record: Option<Option<String>>,
+
+    /// Format of the recordings, unless given by the extension of the recording path
+    #[arg(long, value_enum)]
+    pub(crate) record_format: Option<RecordingFormat>,
+
+    /// Frame the recording starts at
+    #[arg(long, value_name = "FRAME", requires = "record")]
+    pub(crate) record_start: Option<usize>,
+
+    /// Frame the recording stops at
+    #[arg(long, value_name = "FRAME", requires = "record")]
+    pub(crate) record_stop: Option<usize>,
+
+    /// Size of a display pixel in the recordings
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=64))]
+    pub(crate) record_scale: Option<u32>,
+
+    /// Write the beeper output to a WAV file (mono, 16-bit, 44100Hz)
+    #[arg(long, value_name = "PATH")]
+    pub(crate) wav: Option<String>,
+
+    /// Write the memory reads, writes and fetches per address at exit, as a PNG heat map
+    /// or a CSV file, following the extension
+    #[arg(long, value_name = "PATH")]
+    pub(crate) heat_map: Option<String>,
+
+    /// Profile the executed instructions, printing the report at exit, or writing it to PATH,
+    /// as JSON if PATH ends with .json
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = None)]
+    pub(crate) profile: Option<Option<String>>,
+
+    /// Merge the executed instructions and skip outcomes into an lcov tracefile at exit,
+    /// created if needed
+    #[arg(long, value_name = "PATH")]
+    pub(crate) coverage: Option<String>,
+}
+
+/// Options for commands that read the keyboard
+#[derive(Args)]
+pub(crate) struct InputOptions {
+    /// Keyboard layout
+    #[arg(long, value_enum)]
+    pub(crate) keymap: Option<KeymapPreset>,
+
+    /// Bind a Chip8 key to host keys, replacing the layout ones (e.g. 5=W,Space), can be repeated
+    #[arg(long, value_name = "KEY=HOST[,HOST...]", value_parser = parse_binding)]
+    pub(crate) bind: Vec<(String, Vec<String>)>,
+
+    /// Bind a game controller button to a Chip8 key, or to none (e.g. a=5, dpup=2, back=none), can be repeated
+    #[arg(long, value_name = "BUTTON=KEY", value_parser = parse_button_binding)]
+    pub(crate) button: Vec<(String, String)>,
+}
+
+fn parse_button_binding(value: &str) -> Result<(String, String), String> {
+    let (button, key) = value
+        .split_once('=')
+        .ok_or_else(|| format!("invalid binding '{}', expected BUTTON=KEY", value))?;
+    Ok((button.trim().to_string(), key.trim().to_string()))
+}
+
+fn parse_binding(value: &str) -> Result<(String, Vec<String>), String> {
+    let (key, host_keys) = value
+        .split_once('=')
+        .ok_or_else(|| format!("invalid binding '{}', expected KEY=HOST[,HOST...]", value))?;
+    let host_keys = host_keys
+        .split(',')
+        .map(|name| name.trim().to_string())
+        .collect();
+    Ok((key.trim().to_string(), host_keys))
+}
+
+fn parse_log_level(value: &str) -> Result<LevelFilter, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid log level '{}'", value))
+}
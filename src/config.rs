@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::constants::{CONFIG_DIRECTORY_NAME, CONFIG_FILE_NAME};
+use crate::motherboard::layout::MemoryLayout;
+use crate::motherboard::quirks::{KeyWait, QuirkPreset};
+use crate::peripherals::display_filter::DisplayFilter;
+use crate::peripherals::keymap::KeymapPreset;
+use crate::peripherals::palette::PalettePreset;
+use crate::peripherals::post_processing::PostEffect;
+use crate::peripherals::tone::Waveform;
+use crate::toolchain::recorder::RecordingFormat;
+use crate::toolchain::screenshot::ScreenshotSize;
+
+//  Configuration file (config.toml in the XDG config directory):
+//
+//  [defaults]                  Settings for every ROM
+//  scale_factor = 20
+//  palette = "amber"
+//  foreground_color = "41EC9D"
+//
+//  [defaults.keys]             Custom key bindings, Chip8 key => host keys
+//  5 = ["W", "Space"]
+//
+//  [defaults.controller]       Game controller bindings, button => Chip8 key
+//  a = "5"
+//
+//  [roms.<SHA-1 of the ROM>]   Overrides for a single ROM
+//  quirks = "cosmac"
+//  frame_size = 10
+//  layout = "eti660"           Or load_address = 0x600, entry_address = 0x600
+
+/// Contents of the configuration file
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    /// Settings applied to every ROM
+    pub(crate) defaults: ConfigSection,
+
+    /// Settings applied to a single ROM, keyed by the lowercase SHA-1 of the ROM
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) roms: BTreeMap<String, ConfigSection>,
+}
+
+/// A set of optional settings, every missing entry keeps its previous value
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct ConfigSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) scale_factor: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) foreground_color: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) background_color: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) palette: Option<PalettePreset>,
+
+    /// Colors replacing the first ones of the palette: background, foreground, plane 2, both planes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) colors: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) frame_size: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) quirks: Option<QuirkPreset>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) key_wait: Option<KeyWait>,
+
+    /// Memory layout giving the load and entry addresses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) layout: Option<MemoryLayout>,
+
+    /// Address the ROM is loaded at, replacing the one of the layout (e.g. 0x600)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) load_address: Option<usize>,
+
+    /// Address execution starts at, the load address by default when one is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) entry_address: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) seed: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) log_file: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) log_level: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) sound: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) beep_frequency: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) beep_volume: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) waveform: Option<Waveform>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) fullscreen: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) show_fps: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) debugger: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) integer_scale: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) filter: Option<DisplayFilter>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) filter_decay: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) blend_frames: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) effects: Option<Vec<PostEffect>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) capture_dir: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) screenshot_size: Option<ScreenshotSize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) record_format: Option<RecordingFormat>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) record_scale: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) keymap: Option<KeymapPreset>,
+
+    /// Chip8 key (0-F) => host key names
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) keys: Option<BTreeMap<String, Vec<String>>>,
+
+    /// Game controller button => Chip8 key (0-F), or "none"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) controller: Option<BTreeMap<String, String>>,
+}
+
+impl Config {
+    /// Loads the configuration from the given path, or from the XDG config directory.
+    /// A missing file at the default location is not an error, other read errors are.
+    pub(crate) fn load(path: Option<&str>) -> Result<Self, String> {
+        let (path, required) = match path {
+            Some(path) => (PathBuf::from(path), true),
+            None => match Self::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        Self::read(&path, required)
+    }
+
+    /// Reads the configuration, a missing file giving the default one unless it is required.
+    /// Any other error, like a file that is not readable or not UTF-8, is reported.
+    fn read(path: &Path, required: bool) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text)
+                .map_err(|e| format!("Error: config file {} invalid: {}", path.display(), e)),
+            Err(e) if e.kind() == ErrorKind::NotFound && !required => Ok(Config::default()),
+            Err(e) => Err(format!(
+                "Error: config file {} not readable: {}",
+                path.display(),
+                e
+            )),
+        }
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub(crate) fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_DIRECTORY_NAME).join(CONFIG_FILE_NAME))
+    }
+
+    /// Returns the overrides for the ROM with the given SHA-1, if any
+    pub(crate) fn rom_section(&self, rom_sha1: &str) -> Option<&ConfigSection> {
+        self.roms.get(&rom_sha1.to_lowercase())
+    }
+
+    pub(crate) fn to_toml(&self) -> String {
+        toml::to_string(self).expect("Error: failed to serialize config")
+    }
+}
+
+#[cfg(test)]
+#[path = "./config_test.rs"]
+mod config_test;
@@ -0,0 +1,189 @@
+use super::*;
+use crate::constants::{BACKGROUND_COLOR, FRAME_SIZE, SCALE_FACTOR};
+use crate::peripherals::palette::PalettePreset;
+use crate::settings::Settings;
+
+const ROM_SHA1: &str = "0123456789abcdef0123456789abcdef01234567";
+
+const CONFIG: &str = r##"
+[defaults]
+scale_factor = 10
+foreground_color = "#FFB000"
+frame_size = 20
+
+[roms.0123456789abcdef0123456789abcdef01234567]
+quirks = "cosmac"
+frame_size = 8
+"##;
+
+#[test]
+fn test_empty_config_keeps_constants() {
+    let config = Config::parse("").unwrap();
+    let settings = Settings::from_config(&config, Some(ROM_SHA1), None).unwrap();
+    assert_eq!(settings.scale_factor, SCALE_FACTOR);
+    assert_eq!(settings.frame_size, FRAME_SIZE);
+    assert_eq!(settings.quirk_preset, QuirkPreset::Modern);
+    assert!(settings.detect_quirks);
+}
+
+#[test]
+fn test_defaults_section() {
+    let config = Config::parse(CONFIG).unwrap();
+    let settings = Settings::from_config(&config, None, None).unwrap();
+    assert_eq!(settings.scale_factor, 10);
+    assert_eq!(settings.palette.foreground(), [0xFF, 0xB0, 0x00]);
+    assert_eq!(settings.palette.background(), BACKGROUND_COLOR);
+    assert_eq!(settings.frame_size, 20);
+    assert_eq!(settings.quirk_preset, QuirkPreset::Modern);
+}
+
+#[test]
+fn test_rom_section_overrides_defaults() {
+    let config = Config::parse(CONFIG).unwrap();
+    let settings = Settings::from_config(&config, Some(ROM_SHA1), None).unwrap();
+    assert_eq!(settings.scale_factor, 10);
+    assert_eq!(settings.frame_size, 8);
+    assert_eq!(settings.quirk_preset, QuirkPreset::Cosmac);
+    assert_eq!(settings.quirks, QuirkPreset::Cosmac.quirks());
+    assert!(!settings.detect_quirks);
+}
+
+#[test]
+fn test_database_between_defaults_and_rom_section() {
+    let config = Config::parse(CONFIG).unwrap();
+    let database = ConfigSection {
+        scale_factor: Some(5),
+        frame_size: Some(30),
+        quirks: Some(QuirkPreset::Schip),
+        ..ConfigSection::default()
+    };
+    let settings = Settings::from_config(&config, Some(ROM_SHA1), Some(&database)).unwrap();
+    assert_eq!(settings.scale_factor, 5);
+    assert_eq!(settings.frame_size, 8);
+    assert_eq!(settings.quirk_preset, QuirkPreset::Cosmac);
+
+    let settings = Settings::from_config(&config, None, Some(&database)).unwrap();
+    assert_eq!(settings.frame_size, 30);
+    assert_eq!(settings.quirk_preset, QuirkPreset::Schip);
+    assert!(!settings.detect_quirks);
+}
+
+#[test]
+fn test_rom_section_is_case_insensitive() {
+    let config = Config::parse(CONFIG).unwrap();
+    let settings = Settings::from_config(&config, Some(&ROM_SHA1.to_uppercase()), None).unwrap();
+    assert_eq!(settings.frame_size, 8);
+}
+
+#[test]
+fn test_key_wait_overrides_rom_preset() {
+    let text = CONFIG.replace("[defaults]\n", "[defaults]\nkey_wait = \"press\"\n");
+    let config = Config::parse(&text).unwrap();
+    let settings = Settings::from_config(&config, Some(ROM_SHA1), None).unwrap();
+    assert_eq!(settings.quirk_preset, QuirkPreset::Cosmac);
+    assert_eq!(settings.quirks.key_wait, KeyWait::Press);
+}
+
+#[test]
+fn test_memory_layout_and_addresses() {
+    let config = Config::parse("[defaults]\nlayout = \"eti660\"").unwrap();
+    let settings = Settings::from_config(&config, None, None).unwrap();
+    assert_eq!(settings.program_layout.load_address, 0x600);
+    assert_eq!(settings.program_layout.entry_address, 0x600);
+
+    // A load address is the entry address too, unless one is given
+    let config = Config::parse("[defaults]\nlayout = \"image\"\nload_address = 0x300").unwrap();
+    let settings = Settings::from_config(&config, None, None).unwrap();
+    assert_eq!(settings.program_layout.load_address, 0x300);
+    assert_eq!(settings.program_layout.entry_address, 0x300);
+
+    let config = Config::parse("[defaults]\nlayout = \"image\"\nentry_address = 0x400").unwrap();
+    let settings = Settings::from_config(&config, None, None).unwrap();
+    assert_eq!(settings.program_layout.load_address, 0);
+    assert_eq!(settings.program_layout.entry_address, 0x400);
+    assert!(settings.check_rom_size(0x1000).is_ok());
+
+    let config = Config::parse("[defaults]\nload_address = 0xF00").unwrap();
+    let settings = Settings::from_config(&config, None, None).unwrap();
+    assert_eq!(
+        settings.check_rom_size(0x101),
+        Err(
+            "Error: ROM of 257 bytes larger than the 256 bytes of memory from its load address 0xF00"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_custom_colors_override_rom_palette() {
+    // The ROM section is the last one of CONFIG
+    let text = format!("{}palette = \"gameboy\"\n", CONFIG);
+    let config = Config::parse(&text).unwrap();
+    let settings = Settings::from_config(&config, Some(ROM_SHA1), None).unwrap();
+    let gameboy = PalettePreset::Gameboy.palette();
+    assert_eq!(settings.palette_preset, PalettePreset::Gameboy);
+    assert_eq!(settings.palette.background(), gameboy.background());
+    assert_eq!(settings.palette.foreground(), [0xFF, 0xB0, 0x00]);
+    assert_eq!(settings.palette.colors[2..], gameboy.colors[2..]);
+}
+
+#[test]
+fn test_invalid_config() {
+    assert!(Config::parse("[defaults]\nunknown = 1").is_err());
+    assert!(Config::parse("[defaults]\nquirks = \"unknown\"").is_err());
+
+    let config = Config::parse("[defaults]\nforeground_color = \"green\"").unwrap();
+    assert!(Settings::from_config(&config, None, None).is_err());
+
+    let config = Config::parse("[defaults]\nframe_size = 0").unwrap();
+    assert!(Settings::from_config(&config, None, None).is_err());
+
+    let config = Config::parse("[defaults]\nload_address = 0x1000").unwrap();
+    assert_eq!(
+        Settings::from_config(&config, None, None).err(),
+        Some("invalid address '0x1000', expected 0x000 to 0xFFE".to_string())
+    );
+
+    let colors = r#"["000000", "111111", "222222", "333333", "444444"]"#;
+    let config = Config::parse(&format!("[defaults]\ncolors = {}", colors)).unwrap();
+    assert!(Settings::from_config(&config, None, None).is_err());
+}
+
+#[test]
+fn test_unreadable_default_config() {
+    let missing = std::env::temp_dir().join(format!("chip8-{}-missing.toml", std::process::id()));
+    assert!(Config::read(&missing, false).is_ok());
+    assert!(Config::read(&missing, true).is_err());
+
+    // A directory in place of the file is reported, even at the default location
+    let error = Config::read(&std::env::temp_dir(), false).err().unwrap();
+    assert!(error.contains("not readable"), "{}", error);
+
+    let path = std::env::temp_dir().join(format!("chip8-{}-latin1.toml", std::process::id()));
+    fs::write(&path, b"[defaults]\nlog_file = \"caf\xE9.log\"\n").unwrap();
+    let error = Config::read(&path, false).err().unwrap();
+    assert!(error.contains("not readable"), "{}", error);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_dump_round_trip() {
+    let config = Config::parse(CONFIG).unwrap();
+    let settings = Settings::from_config(&config, Some(ROM_SHA1), None).unwrap();
+    let dumped = Config {
+        defaults: settings.to_config_section(),
+        ..Config::default()
+    };
+
+    let reloaded = Config::parse(&dumped.to_toml()).unwrap();
+    let reloaded_settings = Settings::from_config(&reloaded, None, None).unwrap();
+    assert_eq!(reloaded_settings.scale_factor, settings.scale_factor);
+    assert_eq!(
+        reloaded_settings.palette.foreground(),
+        settings.palette.foreground()
+    );
+    assert_eq!(reloaded_settings.frame_size, settings.frame_size);
+    assert_eq!(reloaded_settings.quirk_preset, settings.quirk_preset);
+    assert_eq!(reloaded_settings.program_layout, settings.program_layout);
+    assert_eq!(reloaded_settings.log_level, settings.log_level);
+}
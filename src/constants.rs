@@ -6,9 +6,17 @@ pub const SCALE_FACTOR: u32 = 20; // The scaling factor for rendering the CHIP-8
 pub const FOREGROUND_COLOR: [u8; 3] = [65, 236, 157]; // RGB color used for the foreground (active pixels) on the display
 pub const BACKGROUND_COLOR: [u8; 3] = [15, 15, 15]; // RGB color used for the background (inactive pixels) on the display
 
+pub const FILTER_DECAY: f32 = 0.6; // Brightness kept by a turned off pixel after each frame with the phosphor and blend filters
+pub const BLEND_FRAMES: usize = 3; // Number of frames combined by the blend filter
+
 pub const MEMORY_SIZE: usize = 4096; // Total memory size for the CHIP-8 system (4KB), typical of the CHIP-8 architecture
 pub const RESERVED_MEMORY_SIZE: usize = 512; // Reserved memory space (0x000 to 0x1FF) for interpreter, font data, and other purposes
-pub const MAX_ROM_SIZE: usize = MEMORY_SIZE - RESERVED_MEMORY_SIZE;
+
+pub const AUDIO_SAMPLE_RATE: i32 = 44100; // Samples per second of the beeper, played and captured
+pub const BEEP_FREQUENCY: f32 = 240.0; // Pitch of the beeper in Hz
+pub const BEEP_VOLUME: f32 = 0.25; // Amplitude of the beeper, from 0 to 1
+pub const BEEP_ATTACK: f32 = 0.005; // Seconds for the beeper to fade in, avoiding clicks
+pub const BEEP_RELEASE: f32 = 0.005; // Seconds for the beeper to fade out, avoiding clicks
 
 pub const FRAME_FREQUENCY: f64 = 60.0; // Target frame rate for the CHIP-8 system (60 frames per second)
 pub const FRAME_SIZE: usize = 15; // Number of CPU cycles (instructions) to execute per frame
@@ -18,6 +26,13 @@ pub const OPCODE_SIZE: usize = 2; // Size of each opcode in bytes (2 bytes per i
 pub const V_REGISTERS_SIZE: usize = 16; // Size of general-purpose registers in the CHIP-8 system (V0 to VF)
 pub const STACK_SIZE: usize = 16; // Size of the stack used for subroutine calls and returns
 
+pub const CONFIG_DIRECTORY_NAME: &str = "chip8"; // Name of the directory holding the configuration inside the XDG config directory
+pub const CONFIG_FILE_NAME: &str = "config.toml"; // Name of the configuration file
+pub const DATABASE_DIRECTORY_NAME: &str = "database"; // Directory next to the configuration file replacing the bundled ROM database
+
+pub const CAPTURE_DIRECTORY: &str = "."; // Directory the screenshots and recordings are written to
+pub const RECORD_SCALE: usize = 4; // Size of a display pixel in the recordings
+
 pub const LOG_FILE_PATH: &str = "debug.log"; // Path to the log file for storing debug information
 pub const LOG_LEVEL: log::LevelFilter = log::LevelFilter::Debug; // Default log level for the CHIP-8 emulator
 
@@ -2,15 +2,13 @@ use log::LevelFilter;
 use simplelog::{format_description, CombinedLogger, ConfigBuilder, LevelPadding, WriteLogger};
 use std::fs::{remove_file, OpenOptions};
 
-use crate::constants::{LOG_FILE_PATH, LOG_LEVEL};
-
-pub(crate) fn init() {
-    let _ = remove_file(LOG_FILE_PATH);
+pub(crate) fn init(log_file_path: &str, log_level: LevelFilter) {
+    let _ = remove_file(log_file_path);
     let log_file = OpenOptions::new()
         .write(true)
         .create(true)
         .truncate(true)
-        .open(LOG_FILE_PATH)
+        .open(log_file_path)
         .expect("Error: failed to open log file");
 
     // Configure the logger to show only the timestamp
@@ -26,7 +24,7 @@ pub(crate) fn init() {
         .build();
 
     CombinedLogger::init(vec![WriteLogger::new(
-        LOG_LEVEL, config, // Use the custom configuration
+        log_level, config, // Use the custom configuration
         log_file,
     )])
     .expect("Error: failed to initialize logger");
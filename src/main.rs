@@ -1,47 +1,647 @@
 extern crate rand;
 extern crate sdl2;
 
-use std::{env, process};
+use std::path::{Path, PathBuf};
+use std::{io, process};
 
+use clap::Parser;
+
+use cli::{
+    CaptureOptions, Cli, Command, ConfigCommand, DisplayOptions, EmulatorOptions, InputOptions,
+    LayoutOptions,
+};
+use config::Config;
+use motherboard::layout::ProgramLayout;
 use motherboard::processor::Processor;
 use peripherals::audio_driver::AudioDriver;
 use peripherals::cartridge_driver::CartridgeDriver;
+use peripherals::debugger_window::DebuggerWindow;
 use peripherals::display_driver::DisplayDriver;
+use peripherals::frontend::DisplayFrontend;
 use peripherals::input_driver::InputDriver;
+use peripherals::terminal_driver::{TerminalBell, TerminalCharset, TerminalDisplay, TerminalInput};
+use rom_database::{KnownRom, RomDatabase};
+use settings::Settings;
+use toolchain::audio_capture::AudioCapture;
+use toolchain::coverage::{self, Coverage, Tracefile};
+use toolchain::debugger::Debugger;
+use toolchain::heat_map::{self, HeatMapFormat};
+use toolchain::input_script::InputScript;
+use toolchain::linter;
+use toolchain::profiler::Profiler;
+use toolchain::quirk_detector;
+use toolchain::recorder::Recorder;
+use toolchain::screenshot::Screenshot;
 
+mod cli;
+mod config;
 mod constants;
 mod logger;
 mod motherboard;
 mod peripherals;
+mod rom_database;
+mod settings;
 mod toolchain;
 
 fn main() {
-    logger::init();
+    let cli = Cli::parse();
+
+    let config_path = cli.config.as_deref();
+
+    let result = match &cli.command {
+        Command::Run {
+            rom,
+            emulator,
+            display,
+            input,
+            capture,
+        } => run(config_path, rom, emulator, display, input, capture),
+        Command::Tui {
+            rom,
+            charset,
+            emulator,
+            input,
+            capture,
+        } => tui(config_path, rom, *charset, emulator, input, capture),
+        Command::Disasm { rom, layout } => disasm(config_path, rom, layout),
+        Command::Lint { rom, layout } => lint(config_path, rom, layout),
+        Command::Trace {
+            rom,
+            cycles,
+            emulator,
+        } => trace(config_path, rom, *cycles, emulator),
+        Command::Coverage {
+            rom,
+            tracefiles,
+            layout,
+        } => coverage(config_path, rom, tracefiles, layout),
+        Command::Info { rom, emulator } => info(config_path, rom, emulator),
+        Command::Headless {
+            rom,
+            frames,
+            input_script,
+            emulator,
+            capture,
+        } => headless(
+            config_path,
+            rom,
+            *frames,
+            input_script.as_deref(),
+            emulator,
+            capture,
+        ),
+        Command::Config {
+            command:
+                ConfigCommand::Dump {
+                    rom,
+                    emulator,
+                    display,
+                    input,
+                },
+        } => config_dump(config_path, rom.as_deref(), emulator, display, input),
+    };
 
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        log::error!("Error: <ROM file> missing");
+    if let Err(message) = result {
+        log::error!("{}", message);
+        eprintln!("{}", message);
         process::exit(1);
     }
-    let rom_filename = &args[1];
+}
+
+/// Resolves the settings: constants, then the configuration file and the ROM database,
+/// then the command-line options. Returns the ROM as found in the database too.
+fn load_settings(
+    config_path: Option<&str>,
+    cartridge_driver: Option<&CartridgeDriver>,
+    emulator: &EmulatorOptions,
+    display: Option<&DisplayOptions>,
+    input: Option<&InputOptions>,
+    capture: Option<&CaptureOptions>,
+) -> Result<(Settings, Option<KnownRom>), String> {
+    let config = Config::load(config_path)?;
+    let rom_sha1 = cartridge_driver.map(CartridgeDriver::sha1);
+    let known_rom = match &rom_sha1 {
+        Some(sha1) => RomDatabase::load()?.lookup(sha1),
+        None => None,
+    };
+    let database = known_rom.as_ref().map(KnownRom::to_config_section);
 
-    let sdl_context = sdl2::init().unwrap();
+    let mut settings = Settings::from_config(&config, rom_sha1.as_deref(), database.as_ref())?;
+    settings.apply_emulator_options(emulator);
+    if let Some(cartridge_driver) = cartridge_driver {
+        settings.check_rom_size(cartridge_driver.rom_size)?;
+    }
+    if let Some(display) = display {
+        settings.apply_display_options(display);
+    }
+    if let Some(input) = input {
+        settings.apply_input_options(input)?;
+    }
+    if let Some(capture) = capture {
+        settings.apply_capture_options(capture);
+    }
+    Ok((settings, known_rom))
+}
 
-    let mut display_driver = DisplayDriver::new(&sdl_context);
-    let mut input_driver = InputDriver::new(&sdl_context);
-    let audio_driver = AudioDriver::new(&sdl_context);
-    let cartridge_driver = CartridgeDriver::new(rom_filename);
+/// Resolves the memory layout of a ROM for the commands that do not run it,
+/// from the configuration, the ROM database and the layout options
+fn load_layout(
+    config_path: Option<&str>,
+    cartridge_driver: &CartridgeDriver,
+    layout: &LayoutOptions,
+) -> Result<(ProgramLayout, Option<KnownRom>), String> {
+    let emulator = EmulatorOptions {
+        layout: layout.clone(),
+        ..EmulatorOptions::default()
+    };
+    let (settings, known_rom) = load_settings(
+        config_path,
+        Some(cartridge_driver),
+        &emulator,
+        None,
+        None,
+        None,
+    )?;
+    Ok((settings.program_layout, known_rom))
+}
 
-    if cartridge_driver.rom_size == 0 {
-        log::error!("Error: <ROM file> {} empty", rom_filename);
-        process::exit(1);
+fn run(
+    config_path: Option<&str>,
+    rom: &str,
+    emulator: &EmulatorOptions,
+    display: &DisplayOptions,
+    input: &InputOptions,
+    capture: &CaptureOptions,
+) -> Result<(), String> {
+    let cartridge_driver = CartridgeDriver::new(rom)?;
+    let (mut settings, known_rom) = load_settings(
+        config_path,
+        Some(&cartridge_driver),
+        emulator,
+        Some(display),
+        Some(input),
+        Some(capture),
+    )?;
+    let keymap = settings.keymap()?;
+    let controller_map = settings.controller_map()?;
+    let messages = rom_messages(&mut settings, &cartridge_driver, known_rom.as_ref());
+    logger::init(&settings.log_file_path, settings.log_level);
+
+    let sdl_context = sdl2::init()?;
+
+    let mut display_driver = DisplayDriver::new(&sdl_context, &settings);
+    for message in messages {
+        log::info!("{}", message);
+        display_driver.show_message(message);
     }
+    let mut input_driver = InputDriver::new(&sdl_context, keymap, controller_map);
+    let mut audio_driver = AudioDriver::new(&sdl_context, &settings);
+    let mut debugger_window = if settings.debugger {
+        let debugger_window = DebuggerWindow::new(&sdl_context)?;
+        input_driver.set_debugger_window(debugger_window.id());
+        Some(debugger_window)
+    } else {
+        None
+    };
+
+    let heat_map_path = heat_map_path(capture)?;
+    let mut recorder = Recorder::new(&settings, Some(capture))?;
+    let mut audio_capture = AudioCapture::new(capture.wav.as_ref().map(PathBuf::from), &settings);
 
-    let mut processor = Processor::new();
+    let mut processor = Processor::with_settings(&settings);
+    start_analysis(&mut processor, capture);
     processor.run(
         &mut display_driver,
         &mut input_driver,
-        &audio_driver,
+        &mut audio_driver,
+        &cartridge_driver,
+        &mut recorder,
+        &mut audio_capture,
+        debugger_window.as_mut(),
+    );
+
+    recorder.finish()?;
+    audio_capture.finish()?;
+    if capture.screenshot {
+        display_driver.save_screenshot(processor.screen.get_all_pixels())?;
+    }
+    if let Some(path) = heat_map_path {
+        heat_map::save(&path, &processor.memory)?;
+    }
+    save_profile(&processor, capture)?;
+    save_coverage(&processor, rom, &cartridge_driver, capture)?;
+    Ok(())
+}
+
+fn tui(
+    config_path: Option<&str>,
+    rom: &str,
+    charset: TerminalCharset,
+    emulator: &EmulatorOptions,
+    input: &InputOptions,
+    capture: &CaptureOptions,
+) -> Result<(), String> {
+    let cartridge_driver = CartridgeDriver::new(rom)?;
+    let (mut settings, known_rom) = load_settings(
+        config_path,
+        Some(&cartridge_driver),
+        emulator,
+        None,
+        Some(input),
+        Some(capture),
+    )?;
+    let keymap = settings.keymap()?;
+    let messages = rom_messages(&mut settings, &cartridge_driver, known_rom.as_ref());
+    logger::init(&settings.log_file_path, settings.log_level);
+
+    // The terminal is restored when the display and input are dropped, before errors are printed
+    let mut display = TerminalDisplay::new(&settings, charset)?;
+    for message in messages {
+        log::info!("{}", message);
+        display.show_message(message);
+    }
+    let mut input = TerminalInput::new(keymap)?;
+    let mut bell = TerminalBell::new(&settings);
+
+    let heat_map_path = heat_map_path(capture)?;
+    let mut recorder = Recorder::new(&settings, Some(capture))?;
+    let mut audio_capture = AudioCapture::new(capture.wav.as_ref().map(PathBuf::from), &settings);
+
+    let mut processor = Processor::with_settings(&settings);
+    start_analysis(&mut processor, capture);
+    processor.run(
+        &mut display,
+        &mut input,
+        &mut bell,
         &cartridge_driver,
+        &mut recorder,
+        &mut audio_capture,
+        None,
     );
+
+    recorder.finish()?;
+    audio_capture.finish()?;
+    if capture.screenshot {
+        display.save_screenshot(processor.screen.get_all_pixels())?;
+    }
+    if let Some(path) = heat_map_path {
+        heat_map::save(&path, &processor.memory)?;
+    }
+    // The report is printed on the main screen, once the terminal is restored
+    drop(input);
+    drop(display);
+    save_profile(&processor, capture)?;
+    save_coverage(&processor, rom, &cartridge_driver, capture)?;
+    Ok(())
+}
+
+fn disasm(config_path: Option<&str>, rom: &str, layout: &LayoutOptions) -> Result<(), String> {
+    let cartridge_driver = CartridgeDriver::new(rom)?;
+    let (layout, _) = load_layout(config_path, &cartridge_driver, layout)?;
+
+    let mut debugger = Debugger::new();
+    debugger.reset(&cartridge_driver.rom, cartridge_driver.rom_size, layout);
+    println!("{}", debugger.get_disassembled_rom());
+    Ok(())
+}
+
+fn lint(config_path: Option<&str>, rom: &str, layout: &LayoutOptions) -> Result<(), String> {
+    let cartridge_driver = CartridgeDriver::new(rom)?;
+    let (layout, _) = load_layout(config_path, &cartridge_driver, layout)?;
+
+    let findings = linter::lint(&cartridge_driver.rom, cartridge_driver.rom_size, layout);
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    match findings.len() {
+        0 => println!("No findings"),
+        1 => println!("1 finding"),
+        count => println!("{} findings", count),
+    }
+    Ok(())
+}
+
+fn trace(
+    config_path: Option<&str>,
+    rom: &str,
+    cycles: usize,
+    emulator: &EmulatorOptions,
+) -> Result<(), String> {
+    let cartridge_driver = CartridgeDriver::new(rom)?;
+    let (mut settings, known_rom) = load_settings(
+        config_path,
+        Some(&cartridge_driver),
+        emulator,
+        None,
+        None,
+        None,
+    )?;
+    let messages = rom_messages(&mut settings, &cartridge_driver, known_rom.as_ref());
+    logger::init(&settings.log_file_path, settings.log_level);
+    for message in messages {
+        log::info!("{}", message);
+        eprintln!("{}", message);
+    }
+
+    let mut processor = Processor::with_settings(&settings);
+    processor
+        .run_trace(&cartridge_driver, cycles, &mut io::stdout().lock())
+        .map_err(|e| format!("Error: failed to write trace: {}", e))
+}
+
+fn coverage(
+    config_path: Option<&str>,
+    rom: &str,
+    tracefiles: &[String],
+    layout: &LayoutOptions,
+) -> Result<(), String> {
+    let cartridge_driver = CartridgeDriver::new(rom)?;
+    let (layout, _) = load_layout(config_path, &cartridge_driver, layout)?;
+    let source = coverage::source_name(rom);
+
+    let mut merged = Coverage::new();
+    let mut found = false;
+    for path in tracefiles {
+        let path = Path::new(path);
+        if !path.exists() {
+            return Err(format!("Error: coverage file {} not found", path.display()));
+        }
+        if let Some(coverage) = Tracefile::load(path)?.coverage(&source) {
+            merged.merge(coverage);
+            found = true;
+        }
+    }
+    if !found {
+        return Err(format!(
+            "Error: no coverage of {} in the tracefiles",
+            source
+        ));
+    }
+
+    merged.add_rom_instructions(&cartridge_driver.rom, cartridge_driver.rom_size, layout);
+    print!("{}", merged.annotate(&cartridge_driver.rom, layout));
+    println!();
+    print!("{}", merged.summary(&cartridge_driver.rom, layout));
+    Ok(())
+}
+
+fn info(config_path: Option<&str>, rom: &str, emulator: &EmulatorOptions) -> Result<(), String> {
+    let cartridge_driver = CartridgeDriver::new(rom)?;
+    let (settings, known_rom) = load_settings(
+        config_path,
+        Some(&cartridge_driver),
+        emulator,
+        None,
+        None,
+        None,
+    )?;
+    let layout = settings.program_layout;
+
+    let mut debugger = Debugger::new();
+    debugger.reset(&cartridge_driver.rom, cartridge_driver.rom_size, layout);
+    println!("ROM:          {}", rom);
+    println!("SHA-1:        {}", cartridge_driver.sha1());
+    println!("Size:         {} bytes", cartridge_driver.rom_size);
+    println!(
+        "Addresses:    loaded at {:#05X}, started at {:#05X}",
+        layout.load_address, layout.entry_address
+    );
+    println!("Instructions: {}", debugger.instruction_count());
+    println!("Labels:       {}", debugger.label_count());
+
+    match &known_rom {
+        Some(known_rom) => {
+            for line in known_rom.describe() {
+                println!("{}", line);
+            }
+        }
+        None => println!("Title:        unknown, not in the ROM database"),
+    }
+
+    // A preset given by the ROM database, the config or --quirks is used at startup,
+    // the detected one being shown for comparison
+    let detection = quirk_detector::detect(
+        &cartridge_driver,
+        settings.frame_size,
+        settings.program_layout,
+    );
+    if settings.detect_quirks {
+        println!("Quirks:       {}", detection.summary());
+    } else {
+        let source = if emulator.quirks.is_some() {
+            "the command line"
+        } else if known_rom.as_ref().and_then(KnownRom::quirk_preset) == Some(settings.quirk_preset)
+        {
+            "the ROM database"
+        } else {
+            "the config file"
+        };
+        println!(
+            "Quirks:       {}, from {}",
+            settings.quirk_preset.name(),
+            source
+        );
+        println!("Detected:     {}", detection.summary());
+    }
+    for clue in &detection.clues {
+        println!(
+            "  {:+.1} {:7} {}",
+            clue.weight,
+            clue.preset.name(),
+            clue.reason
+        );
+    }
+    Ok(())
+}
+
+fn headless(
+    config_path: Option<&str>,
+    rom: &str,
+    frames: usize,
+    input_script: Option<&str>,
+    emulator: &EmulatorOptions,
+    capture: &CaptureOptions,
+) -> Result<(), String> {
+    let cartridge_driver = CartridgeDriver::new(rom)?;
+    let input_script = input_script
+        .map(InputScript::load)
+        .transpose()?
+        .unwrap_or_default();
+    let (mut settings, known_rom) = load_settings(
+        config_path,
+        Some(&cartridge_driver),
+        emulator,
+        None,
+        None,
+        Some(capture),
+    )?;
+    let messages = rom_messages(&mut settings, &cartridge_driver, known_rom.as_ref());
+    logger::init(&settings.log_file_path, settings.log_level);
+    for message in messages {
+        log::info!("{}", message);
+        eprintln!("{}", message);
+    }
+
+    let heat_map_path = heat_map_path(capture)?;
+    let mut recorder = Recorder::new(&settings, Some(capture))?;
+    let mut audio_capture = AudioCapture::new(capture.wav.as_ref().map(PathBuf::from), &settings);
+
+    let mut processor = Processor::with_settings(&settings);
+    start_analysis(&mut processor, capture);
+    processor.run_headless(
+        &cartridge_driver,
+        frames,
+        &input_script,
+        &mut recorder,
+        &mut audio_capture,
+    );
+    println!(
+        "{}",
+        Debugger::get_screen_status(processor.screen.get_all_pixels())
+    );
+
+    if let Some(path) = recorder.finish()? {
+        println!("Recording: {}", path.display());
+    }
+    if let Some(path) = audio_capture.finish()? {
+        println!("Audio: {}", path.display());
+    }
+    if capture.screenshot {
+        let path = Screenshot::new(&settings)
+            .save(processor.screen.get_all_pixels(), &settings.palette)?;
+        println!("Screenshot: {}", path.display());
+    }
+    if let Some(path) = heat_map_path {
+        heat_map::save(&path, &processor.memory)?;
+        println!("Heat map: {}", path.display());
+    }
+    save_profile(&processor, capture)?;
+    if let Some(Some(path)) = &capture.profile {
+        println!("Profile: {}", path);
+    }
+    save_coverage(&processor, rom, &cartridge_driver, capture)?;
+    if let Some(path) = &capture.coverage {
+        println!("Coverage: {}", path);
+    }
+    Ok(())
+}
+
+/// Applies the quirk preset detected from the ROM when none was given,
+/// returning the messages naming the ROM and the detected preset
+fn rom_messages(
+    settings: &mut Settings,
+    cartridge_driver: &CartridgeDriver,
+    known_rom: Option<&KnownRom>,
+) -> Vec<String> {
+    let mut messages = Vec::new();
+    if let Some(known_rom) = known_rom {
+        messages.push(format!("Known ROM: {}", known_rom.program.title));
+    }
+    if settings.detect_quirks {
+        let detection = quirk_detector::detect(
+            cartridge_driver,
+            settings.frame_size,
+            settings.program_layout,
+        );
+        settings.apply_detected_quirks(detection.preset);
+        messages.push(format!("Detected quirks: {}", detection.summary()));
+    }
+    messages
+}
+
+/// Returns the heat map file of the capture options, checking its format before running the ROM
+fn heat_map_path(capture: &CaptureOptions) -> Result<Option<PathBuf>, String> {
+    capture
+        .heat_map
+        .as_ref()
+        .map(|path| {
+            let path = PathBuf::from(path);
+            HeatMapFormat::from_path(&path)?;
+            Ok(path)
+        })
+        .transpose()
+}
+
+/// Profiles the processor and measures the coverage if the capture options ask for it
+fn start_analysis(processor: &mut Processor, capture: &CaptureOptions) {
+    if capture.profile.is_some() {
+        processor.profiler = Some(Profiler::new(processor.program_counter));
+    }
+    if capture.coverage.is_some() {
+        processor.coverage = Some(Coverage::new());
+    }
+}
+
+/// Merges the coverage of the run into the tracefile of the capture options
+fn save_coverage(
+    processor: &Processor,
+    rom: &str,
+    cartridge_driver: &CartridgeDriver,
+    capture: &CaptureOptions,
+) -> Result<(), String> {
+    let (Some(coverage), Some(path)) = (&processor.coverage, &capture.coverage) else {
+        return Ok(());
+    };
+    let mut coverage = coverage.clone();
+    coverage.add_rom_instructions(
+        &cartridge_driver.rom,
+        cartridge_driver.rom_size,
+        processor.program_layout,
+    );
+    Tracefile::update(Path::new(path), &coverage::source_name(rom), &coverage)
+}
+
+/// Prints the profile report, or writes it to the file of the capture options
+fn save_profile(processor: &Processor, capture: &CaptureOptions) -> Result<(), String> {
+    let (Some(profiler), Some(path)) = (&processor.profiler, &capture.profile) else {
+        return Ok(());
+    };
+    let report = profiler.report(&processor.memory);
+    match path {
+        Some(path) => report.save(Path::new(path)),
+        None => {
+            print!("{}", report.to_text());
+            Ok(())
+        }
+    }
+}
+
+fn config_dump(
+    config_path: Option<&str>,
+    rom: Option<&str>,
+    emulator: &EmulatorOptions,
+    display: &DisplayOptions,
+    input: &InputOptions,
+) -> Result<(), String> {
+    let cartridge_driver = rom.map(CartridgeDriver::new).transpose()?;
+    let (settings, known_rom) = load_settings(
+        config_path,
+        cartridge_driver.as_ref(),
+        emulator,
+        Some(display),
+        Some(input),
+        None,
+    )?;
+    settings.keymap()?;
+    settings.controller_map()?;
+
+    let source = match config_path {
+        Some(path) => path.to_string(),
+        None => Config::default_path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "<none>".to_string()),
+    };
+    println!("# Config file: {}", source);
+    if let Some(cartridge_driver) = &cartridge_driver {
+        println!("# ROM SHA-1: {}", cartridge_driver.sha1());
+    }
+    if let Some(known_rom) = &known_rom {
+        println!("# ROM database: {}", known_rom.program.title);
+    }
+    let effective = Config {
+        defaults: settings.to_config_section(),
+        ..Config::default()
+    };
+    print!("{}", effective.to_toml());
+    Ok(())
 }
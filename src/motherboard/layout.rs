@@ -0,0 +1,72 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{MEMORY_SIZE, RESERVED_MEMORY_SIZE};
+
+/// Addresses the ROM is loaded at and executed from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ProgramLayout {
+    /// Address of the first byte of the ROM
+    pub(crate) load_address: usize,
+
+    /// Address of the first instruction executed
+    pub(crate) entry_address: usize,
+}
+
+/// Named layouts matching well-known interpreters and memory images
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MemoryLayout {
+    /// Programs loaded and started at 0x200, as on the COSMAC VIP (default)
+    Chip8,
+
+    /// Programs loaded and started at 0x600, as on the ETI 660
+    Eti660,
+
+    /// Raw memory images holding the interpreter area, loaded at 0x000 and started at 0x200
+    Image,
+}
+
+impl MemoryLayout {
+    pub(crate) fn program_layout(self) -> ProgramLayout {
+        match self {
+            MemoryLayout::Chip8 => ProgramLayout {
+                load_address: RESERVED_MEMORY_SIZE,
+                entry_address: RESERVED_MEMORY_SIZE,
+            },
+            MemoryLayout::Eti660 => ProgramLayout {
+                load_address: 0x600,
+                entry_address: 0x600,
+            },
+            MemoryLayout::Image => ProgramLayout {
+                load_address: 0,
+                entry_address: RESERVED_MEMORY_SIZE,
+            },
+        }
+    }
+}
+
+impl ProgramLayout {
+    /// Returns the address past the last byte of a ROM
+    pub(crate) fn end(&self, rom_size: usize) -> usize {
+        self.load_address + rom_size
+    }
+
+    /// Returns the number of bytes of memory from the load address
+    pub(crate) fn capacity(&self) -> usize {
+        MEMORY_SIZE - self.load_address
+    }
+
+    /// Returns the offset in the ROM of an address, None outside of a ROM of the given size
+    pub(crate) fn offset(&self, address: usize, rom_size: usize) -> Option<usize> {
+        (self.load_address..self.end(rom_size))
+            .contains(&address)
+            .then(|| address - self.load_address)
+    }
+}
+
+impl Default for ProgramLayout {
+    fn default() -> Self {
+        MemoryLayout::Chip8.program_layout()
+    }
+}
@@ -1,6 +1,6 @@
 use log::error;
 
-use crate::constants::{MAX_ROM_SIZE, MEMORY_SIZE, RESERVED_MEMORY_SIZE};
+use crate::constants::MEMORY_SIZE;
 
 //  Memory Map:
 //  +---------------+= 0xFFF (4095) End of Chip-8 RAM
@@ -24,43 +24,107 @@ use crate::constants::{MAX_ROM_SIZE, MEMORY_SIZE, RESERVED_MEMORY_SIZE};
 //  | 0x000 to 0x1FF|
 //  | Reserved for  |
 //  |  interpreter  |
-//  +---------------+= 0x000 (0) Start of Chip-8 RAM
+//  +---------------+= 0x000 (0) Start of Chip-8 RAM, and of memory images
+//
+//  The load address of the ROM is set by the memory layout, see `layout.rs`.
+
+/// Number of accesses to every address by the executed instructions
+pub(crate) struct AccessCounts {
+    pub(crate) reads: Vec<u32>,
+    pub(crate) writes: Vec<u32>,
+    pub(crate) fetches: Vec<u32>,
+}
+
+impl AccessCounts {
+    fn new() -> Self {
+        AccessCounts {
+            reads: vec![0; MEMORY_SIZE],
+            writes: vec![0; MEMORY_SIZE],
+            fetches: vec![0; MEMORY_SIZE],
+        }
+    }
+
+    /// Returns the number of reads, writes and fetches of an address
+    pub(crate) fn total(&self, address: usize) -> u32 {
+        self.reads[address]
+            .saturating_add(self.writes[address])
+            .saturating_add(self.fetches[address])
+    }
+}
 
 pub(crate) struct Memory {
     bytes: [u8; MEMORY_SIZE],
     rom_size: usize,
+    load_address: usize,
+    access_counts: AccessCounts,
 }
 
 impl Memory {
     pub(crate) fn new() -> Self {
         let mut bytes = [0u8; MEMORY_SIZE];
-        for i in 0..FONT_SPRITES.len() {
-            bytes[i] = FONT_SPRITES[i];
+        bytes[..FONT_SPRITES.len()].copy_from_slice(&FONT_SPRITES);
+        Memory {
+            bytes,
+            rom_size: 0,
+            load_address: 0,
+            access_counts: AccessCounts::new(),
         }
-        Memory { bytes, rom_size: 0 }
     }
 
-    pub(crate) fn reset(&mut self, rom_bytes: &[u8], rom_size: usize) {
-        if rom_size > MAX_ROM_SIZE {
+    /// Copies the ROM at the load address. A memory image loaded at 0 replaces the font too,
+    /// which is installed again when the image leaves it empty.
+    pub(crate) fn reset(&mut self, rom_bytes: &[u8], rom_size: usize, load_address: usize) {
+        if load_address + rom_size > MEMORY_SIZE {
             error!("ROM size exceeds memory capacity");
         }
-        self.rom_size = rom_size;
-        for (i, &byte) in rom_bytes.iter().enumerate() {
-            let address = RESERVED_MEMORY_SIZE + i;
-            if address < RESERVED_MEMORY_SIZE + rom_size {
-                self.bytes[RESERVED_MEMORY_SIZE + i] = byte;
-            } else {
-                break;
-            }
+        self.rom_size = rom_size.min(MEMORY_SIZE - load_address);
+        self.load_address = load_address;
+        self.access_counts = AccessCounts::new();
+        self.bytes[load_address..load_address + self.rom_size]
+            .copy_from_slice(&rom_bytes[..self.rom_size]);
+
+        let font = &mut self.bytes[..FONT_SPRITES.len()];
+        if font.iter().all(|byte| *byte == 0) {
+            font.copy_from_slice(&FONT_SPRITES);
         }
     }
 
+    /// Returns a byte without counting the access, for the debugger and the tests
     pub(crate) fn load(&self, address: usize) -> u8 {
         self.bytes[address]
     }
 
+    /// Returns a byte read by an instruction
+    pub(crate) fn read(&mut self, address: usize) -> u8 {
+        let value = self.bytes[address];
+        self.access_counts.reads[address] = self.access_counts.reads[address].saturating_add(1);
+        value
+    }
+
+    /// Returns the opcode at an address, both of its bytes counted as fetched
+    pub(crate) fn fetch(&mut self, address: usize) -> u16 {
+        let opcode = (self.bytes[address] as u16) << 8 | (self.bytes[address + 1] as u16);
+        for byte in address..address + 2 {
+            self.access_counts.fetches[byte] = self.access_counts.fetches[byte].saturating_add(1);
+        }
+        opcode
+    }
+
     pub(crate) fn store(&mut self, address: usize, value: u8) {
         self.bytes[address] = value;
+        self.access_counts.writes[address] = self.access_counts.writes[address].saturating_add(1);
+    }
+
+    pub(crate) fn rom_size(&self) -> usize {
+        self.rom_size
+    }
+
+    pub(crate) fn load_address(&self) -> usize {
+        self.load_address
+    }
+
+    pub(crate) fn access_counts(&self) -> &AccessCounts {
+        &self.access_counts
     }
 }
 
@@ -1,3 +1,5 @@
+pub(crate) mod layout;
 pub(crate) mod memory;
 pub(crate) mod processor;
+pub(crate) mod quirks;
 pub(crate) mod screen;
@@ -1,19 +1,31 @@
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io::{self, Write};
 use std::thread;
 use std::time::Duration;
 
 use crate::constants::{
-    FRAME_FREQUENCY, FRAME_SIZE, OPCODE_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, STACK_SIZE,
+    FRAME_FREQUENCY, FRAME_SIZE, MEMORY_SIZE, OPCODE_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, STACK_SIZE,
     V_REGISTERS_SIZE,
 };
-use crate::peripherals::audio_driver::AudioDriver;
 use crate::peripherals::cartridge_driver::CartridgeDriver;
-use crate::peripherals::display_driver::DisplayDriver;
-use crate::peripherals::input_driver::InputDriver;
+use crate::peripherals::debugger_window::DebuggerWindow;
+use crate::peripherals::display_driver::DisplayEvent;
+use crate::peripherals::frontend::{AudioFrontend, DisplayFrontend, InputFrontend};
+use crate::peripherals::input_driver::KeyEvent;
+use crate::settings::Settings;
+use crate::toolchain::audio_capture::AudioCapture;
+use crate::toolchain::coverage::Coverage;
 use crate::toolchain::debugger::Debugger;
 use crate::toolchain::decoder::DecodedOpcode;
+use crate::toolchain::input_script::InputScript;
+use crate::toolchain::profiler::Profiler;
+use crate::toolchain::quirk_detector::Trial;
+use crate::toolchain::recorder::Recorder;
 
+use super::layout::ProgramLayout;
 use super::memory::Memory;
+use super::quirks::{KeyWait, Quirks};
 use super::screen::Screen;
 
 /// Represents the CHIP-8 processor, handling memory, registers, stack, and timers
@@ -54,8 +66,32 @@ pub(crate) struct Processor {
     /// Keypad index for keypress result (0-15)
     keypad_wait_index: usize,
 
+    /// Key pressed while waiting, whose release completes the wait (release quirk only)
+    keypad_wait_key: Option<usize>,
+
+    /// Interpreter behaviours for ambiguous opcodes
+    pub(crate) quirks: Quirks,
+
+    /// Number of instructions executed per 60Hz frame
+    frame_size: usize,
+
+    /// Addresses the ROM is loaded at and started from
+    pub(crate) program_layout: ProgramLayout,
+
+    /// Random number generator used by RND
+    rng: StdRng,
+
     /// Debugger for debugging the processor
     debugger: Debugger,
+
+    /// Errors met since the run loop last showed them
+    messages: Vec<String>,
+
+    /// Profiler counting the executed instructions, when profiling
+    pub(crate) profiler: Option<Profiler>,
+
+    /// Executed instructions and skip outcomes, when measuring the coverage
+    pub(crate) coverage: Option<Coverage>,
 }
 
 impl Processor {
@@ -67,81 +103,370 @@ impl Processor {
             stack_pointer: 0,
             v_registers: [0; V_REGISTERS_SIZE],
             i_register: 0,
-            program_counter: 0x200,
+            program_counter: ProgramLayout::default().entry_address,
             delay_timer: 0,
             sound_timer: 0,
             keypad: 0,
             keypad_wait: false,
             keypad_wait_index: 0,
+            keypad_wait_key: None,
+            quirks: Quirks::default(),
+            frame_size: FRAME_SIZE,
+            program_layout: ProgramLayout::default(),
+            rng: StdRng::from_entropy(),
             debugger: Debugger::new(),
+            messages: Vec::new(),
+            profiler: None,
+            coverage: None,
+        }
+    }
+
+    pub(crate) fn with_settings(settings: &Settings) -> Self {
+        let mut processor = Self::new();
+        processor.quirks = settings.quirks;
+        processor.frame_size = settings.frame_size;
+        processor.program_layout = settings.program_layout;
+        processor.program_counter = settings.program_layout.entry_address;
+        if let Some(seed) = settings.seed {
+            processor.rng = StdRng::seed_from_u64(seed);
         }
+        processor
+    }
+
+    fn load(&mut self, cartridge_driver: &CartridgeDriver) {
+        self.memory.reset(
+            &cartridge_driver.rom,
+            cartridge_driver.rom_size,
+            self.program_layout.load_address,
+        );
+        self.debugger.reset(
+            &cartridge_driver.rom,
+            cartridge_driver.rom_size,
+            self.program_layout,
+        );
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn run(
         &mut self,
-        display_driver: &mut DisplayDriver,
-        input_driver: &mut InputDriver,
-        audio_driver: &AudioDriver,
+        display_driver: &mut dyn DisplayFrontend,
+        input_driver: &mut dyn InputFrontend,
+        audio_driver: &mut dyn AudioFrontend,
         cartridge_driver: &CartridgeDriver,
+        recorder: &mut Recorder,
+        audio_capture: &mut AudioCapture,
+        mut debugger_window: Option<&mut DebuggerWindow>,
     ) {
-        self.memory
-            .reset(&cartridge_driver.rom, cartridge_driver.rom_size);
-        self.debugger
-            .reset(&cartridge_driver.rom, cartridge_driver.rom_size);
+        self.load(cartridge_driver);
 
         // Debug the rom only if the DEBUG environment variable is set
         self.debug_rom();
 
         let sleep_duration = Duration::from_nanos((1f64 / FRAME_FREQUENCY * 1e9) as u64);
         let mut opcode_count = 0;
+        let mut paused = false;
+
+        while let Ok(key_events) = input_driver.poll() {
+            for display_event in input_driver.take_display_events() {
+                match display_event {
+                    DisplayEvent::Screenshot => {
+                        if let Err(e) = display_driver.save_screenshot(self.screen.get_all_pixels())
+                        {
+                            log::error!("{}", e);
+                            display_driver.show_message(e);
+                        }
+                    }
+                    DisplayEvent::ToggleRecording => {
+                        recorder.toggle(display_driver.palette());
+                        display_driver.show_message(if recorder.is_recording() {
+                            "Recording started".to_string()
+                        } else {
+                            "Recording stopped".to_string()
+                        });
+                    }
+                    DisplayEvent::TogglePause => {
+                        paused = !paused;
+                        display_driver.set_paused(paused);
+                        self.screen.invalidate();
+                    }
+                    _ => {
+                        display_driver.handle_event(display_event);
+                        self.screen.invalidate();
+                    }
+                }
+            }
 
-        while let Ok(keypad) = input_driver.poll() {
-            self.tick(keypad);
-            opcode_count += 1;
+            for audio_event in input_driver.take_audio_events() {
+                display_driver.show_message(audio_driver.handle_event(audio_event));
+            }
+
+            for debugger_event in input_driver.take_debugger_events() {
+                if let Some(message) = debugger_window
+                    .as_mut()
+                    .and_then(|debugger_window| debugger_window.handle_event(debugger_event))
+                {
+                    display_driver.show_message(message);
+                }
+            }
+
+            // The game is paused while the rebinding screen is open, the keys held when it
+            // opened being released meanwhile
+            if let Some(key) = input_driver.rebinding_key() {
+                for key_event in key_events {
+                    self.handle_key_event(key_event);
+                }
+                audio_driver.set_beeping(false);
+                display_driver.draw_keypad(key);
+                self.screen.invalidate();
+                thread::sleep(sleep_duration);
+                continue;
+            }
 
-            if self.sound_timer > 0 {
-                audio_driver.start_beep();
-            } else {
-                audio_driver.stop_beep();
+            // Keys still reach the keypad while paused, so that none stays pressed afterwards
+            if paused {
+                for key_event in key_events {
+                    self.handle_key_event(key_event);
+                }
+                audio_driver.set_beeping(false);
+                self.screen.refresh(display_driver);
+                if let Some(debugger_window) = debugger_window.as_mut() {
+                    debugger_window.draw(self);
+                }
+                thread::sleep(sleep_duration);
+                continue;
             }
 
-            self.screen.refresh(display_driver);
+            // The game pauses before executing an instruction with a breakpoint, F3 resumes it
+            if debugger_window
+                .as_mut()
+                .is_some_and(|debugger_window| debugger_window.should_break(self.program_counter))
+            {
+                for key_event in key_events {
+                    self.handle_key_event(key_event);
+                }
+                paused = true;
+                display_driver.set_paused(true);
+                display_driver.show_message(format!("Breakpoint at {:#06X}", self.program_counter));
+                self.screen.invalidate();
+                continue;
+            }
+
+            self.tick(&key_events);
+            opcode_count += 1;
 
             // Opcode buffer for 60Hz, ideal around 10-15: 60 * 15 = 900 cycles/second
-            if opcode_count >= FRAME_SIZE {
+            if opcode_count >= self.frame_size {
                 opcode_count = 0;
-                if self.sound_timer > 0 {
-                    self.sound_timer -= 1;
+                for message in self.messages.drain(..) {
+                    display_driver.show_message(message);
                 }
-                if self.delay_timer > 0 {
-                    self.delay_timer -= 1;
+                display_driver.count_frame(self.frame_size);
+                self.screen.advance_frame(display_driver);
+                // The display is refreshed at most once per frame, with every change of the frame
+                self.screen.refresh(display_driver);
+                recorder.capture_frame(self.screen.get_all_pixels());
+                if let Some(debugger_window) = debugger_window.as_mut() {
+                    debugger_window.draw(self);
                 }
+                // The beeper only starts and stops between frames, as the sound timer counts frames
+                audio_driver.set_beeping(self.sound_timer > 0);
+                audio_capture.capture_frame(self.sound_timer > 0);
+                self.update_timers();
                 thread::sleep(sleep_duration);
             }
         }
     }
 
-    fn tick(&mut self, keypad: u16) {
-        self.keypad = keypad;
+    /// Runs the given number of 60Hz frames as fast as possible, with the keys of the
+    /// input script, every frame given to the recorder and the beeper to the audio capture
+    pub(crate) fn run_headless(
+        &mut self,
+        cartridge_driver: &CartridgeDriver,
+        frames: usize,
+        input_script: &InputScript,
+        recorder: &mut Recorder,
+        audio_capture: &mut AudioCapture,
+    ) {
+        self.load(cartridge_driver);
+
+        for frame in 0..frames {
+            self.tick(input_script.events_at(frame));
+            for _ in 1..self.frame_size {
+                self.tick(&[]);
+            }
+            recorder.capture_frame(self.screen.get_all_pixels());
+            audio_capture.capture_frame(self.sound_timer > 0);
+            for message in self.messages.drain(..) {
+                eprintln!("{}", message);
+            }
+            self.update_timers();
+        }
+    }
+
+    /// Runs the given number of frames with no keys pressed, counting the frames run
+    /// and the errors met instead of showing them, to compare the quirk presets
+    pub(crate) fn run_trial(
+        &mut self,
+        cartridge_driver: &CartridgeDriver,
+        frames: usize,
+        trial: &mut Trial,
+    ) {
+        self.load(cartridge_driver);
+
+        for _ in 0..frames {
+            for _ in 0..self.frame_size {
+                if let Some(crash) = self.crash() {
+                    log::error!("{} at {:#05X}", crash, self.program_counter);
+                    trial.crashed = true;
+                    break;
+                }
+                self.tick(&[]);
+            }
+            trial.errors += self.messages.drain(..).count();
+            // A crash ends the run, the frame it happened in not being counted
+            if trial.crashed {
+                return;
+            }
+            trial.frames += 1;
+            self.update_timers();
+        }
+    }
 
+    /// Returns why the next instruction would crash the interpreter: a stack overflow or
+    /// underflow, or an access to an address outside the memory
+    fn crash(&self) -> Option<&'static str> {
         if self.keypad_wait {
-            if self.keypad > 0 {
-                self.keypad_wait = false;
-                self.v_registers[self.keypad_wait_index] = self.keypad.trailing_zeros() as u8;
+            return None;
+        }
+        if self.program_counter + OPCODE_SIZE > MEMORY_SIZE {
+            return Some("Program counter outside the memory");
+        }
+        // Last address accessed by the instruction from I, if any
+        let last_address = match self.decode_opcode(self.fetch_opcode(self.program_counter)) {
+            DecodedOpcode::Ret if self.stack_pointer == 0 => return Some("Stack underflow"),
+            DecodedOpcode::CallNnn { .. } if self.stack_pointer == STACK_SIZE => {
+                return Some("Stack overflow")
             }
-        } else {
-            // Do the fetch-decode-execute cycle
+            DecodedOpcode::DrwVxVyN { n, .. } if n > 0 => self.i_register + n - 1,
+            DecodedOpcode::LdAtIVx { vx } | DecodedOpcode::LdVxAtI { vx } => self.i_register + vx,
+            DecodedOpcode::BcdVx { .. } => self.i_register + 2,
+            _ => return None,
+        };
+        (last_address >= MEMORY_SIZE).then_some("Memory access outside the memory")
+    }
+
+    /// Executes the given number of instructions with no keys pressed,
+    /// writing one line per executed instruction
+    pub(crate) fn run_trace(
+        &mut self,
+        cartridge_driver: &CartridgeDriver,
+        cycles: usize,
+        output: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.load(cartridge_driver);
+
+        for cycle in 0..cycles {
             let address = self.program_counter;
             let opcode = self.fetch_opcode(address);
             let decoded = self.decode_opcode(opcode);
+            let registers: Vec<String> = self
+                .v_registers
+                .iter()
+                .map(|register| format!("{:02X}", register))
+                .collect();
+            writeln!(
+                output,
+                "{:#06X}  {:04X}  {:20}  I: {:#06X}  SP: {:02}  DT: {:02}  ST: {:02}  V: {}",
+                address,
+                opcode,
+                decoded,
+                self.i_register,
+                self.stack_pointer,
+                self.delay_timer,
+                self.sound_timer,
+                registers.join(" ")
+            )?;
+
+            self.tick(&[]);
+            for message in self.messages.drain(..) {
+                eprintln!("{}", message);
+            }
+            if (cycle + 1) % self.frame_size == 0 {
+                self.update_timers();
+            }
+        }
+        Ok(())
+    }
+
+    fn update_timers(&mut self) {
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        // The timers are updated once per frame, which the profiler counts too
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.count_frame();
+        }
+    }
+
+    fn tick(&mut self, key_events: &[KeyEvent]) {
+        for key_event in key_events {
+            self.handle_key_event(*key_event);
+        }
+
+        if !self.keypad_wait {
+            // Do the fetch-decode-execute cycle
+            let address = self.program_counter;
+            let opcode = self.memory.fetch(address);
+            let decoded = self.decode_opcode(opcode);
 
             // Debug the processor status only if the DEBUG environment variable is set
             self.debug_status(address, opcode, &decoded);
 
+            if let Some(profiler) = self.profiler.as_mut() {
+                profiler.record(address, &decoded);
+            }
+
+            let skip = decoded.is_skip();
             self.execute_opcode(decoded);
+
+            if let Some(coverage) = self.coverage.as_mut() {
+                coverage.record(address, skip, self.program_counter);
+            }
         }
     }
 
+    // Keys already held when FX0A starts never complete the wait,
+    // only a key pressed while waiting does, on press or on release depending on the quirk
+    fn handle_key_event(&mut self, key_event: KeyEvent) {
+        match key_event {
+            KeyEvent::Pressed(key) => {
+                self.keypad |= 1 << key;
+                if self.keypad_wait && self.keypad_wait_key.is_none() {
+                    match self.quirks.key_wait {
+                        KeyWait::Press => self.end_keypad_wait(key),
+                        KeyWait::Release => self.keypad_wait_key = Some(key),
+                    }
+                }
+            }
+            KeyEvent::Released(key) => {
+                self.keypad &= !(1 << key);
+                if self.keypad_wait && self.keypad_wait_key == Some(key) {
+                    self.end_keypad_wait(key);
+                }
+            }
+        }
+    }
+
+    fn end_keypad_wait(&mut self, key: usize) {
+        self.keypad_wait = false;
+        self.keypad_wait_key = None;
+        self.v_registers[self.keypad_wait_index] = key as u8;
+    }
+
+    /// Returns the opcode at an address, without counting it as fetched
     pub(crate) fn fetch_opcode(&self, address: usize) -> u16 {
         (self.memory.load(address) as u16) << 8 | (self.memory.load(address + 1) as u16)
     }
@@ -154,7 +479,7 @@ impl Processor {
         let processor_cycle = match decoded {
             DecodedOpcode::Cls => self.execute_cls(),
             DecodedOpcode::Ret => self.execute_ret(),
-            DecodedOpcode::SysNnn { nnn } => self.execute_sys_nnn(nnn),
+            DecodedOpcode::SysNnn { .. } => self.execute_sys_nnn(),
             DecodedOpcode::JpNnn { nnn } => self.execute_jp_nnn(nnn),
             DecodedOpcode::CallNnn { nnn } => self.execute_call_nnn(nnn),
             DecodedOpcode::SeVxNn { vx, nn } => self.execute_se_vx_nn(vx, nn),
@@ -163,14 +488,14 @@ impl Processor {
             DecodedOpcode::LdVxNn { vx, nn } => self.execute_ld_vx_nn(vx, nn),
             DecodedOpcode::AddVxNn { vx, nn } => self.execute_add_vx_nn(vx, nn),
             DecodedOpcode::LdVxVy { vx, vy } => self.execute_ld_vx_vy(vx, vy),
-            DecodedOpcode::OrVxVy { vx, vy } => self.execute_ox_vx_vy(vx, vy),
+            DecodedOpcode::OrVxVy { vx, vy } => self.execute_or_vx_vy(vx, vy),
             DecodedOpcode::AndVxVy { vx, vy } => self.execute_and_vx_vy(vx, vy),
             DecodedOpcode::XorVxVy { vx, vy } => self.execute_xor_vx_vy(vx, vy),
             DecodedOpcode::AddVxVy { vx, vy } => self.execute_add_vx_vy(vx, vy),
             DecodedOpcode::SubVxVy { vx, vy } => self.execute_sub_vx_vy(vx, vy),
-            DecodedOpcode::ShrVx { vx } => self.execute_shr_vx(vx),
+            DecodedOpcode::ShrVx { vx, vy } => self.execute_shr_vx(vx, vy),
             DecodedOpcode::SubnVxVy { vx, vy } => self.execute_subn_vx_vy(vx, vy),
-            DecodedOpcode::ShlVx { vx } => self.execute_shl_vx(vx),
+            DecodedOpcode::ShlVx { vx, vy } => self.execute_shl_vx(vx, vy),
             DecodedOpcode::SneVxVy { vx, vy } => self.execute_sne_vx_vy(vx, vy),
             DecodedOpcode::LdINnn { nnn } => self.execute_ld_i_nnn(nnn),
             DecodedOpcode::JpV0Nnn { nnn } => self.execute_jp_v0_nnn(nnn),
@@ -187,12 +512,19 @@ impl Processor {
             DecodedOpcode::LdAtIVx { vx } => self.execute_ld_at_i_vx(vx),
             DecodedOpcode::LdVxAtI { vx } => self.execute_ld_vx_at_i(vx),
             DecodedOpcode::BcdVx { vx } => self.execute_bcd_vx(vx),
-            DecodedOpcode::Unknown { opcode } => self.execute_unknown(opcode),
+            DecodedOpcode::Unknown { .. } => self.execute_unknown(),
         };
 
         match processor_cycle {
-            ProcessorCycle::Error(decoded_opcode, message) => {
-                eprintln!("ERROR {}: {}", message, decoded_opcode.to_string());
+            ProcessorCycle::Error(message) => {
+                let message = format!(
+                    "{} {:#06X} at {:#05X}",
+                    message,
+                    self.fetch_opcode(self.program_counter),
+                    self.program_counter
+                );
+                log::error!("{}", message);
+                self.messages.push(message);
                 self.program_counter += OPCODE_SIZE;
             }
             ProcessorCycle::Next => self.program_counter += OPCODE_SIZE,
@@ -219,11 +551,8 @@ impl Processor {
 
     // SYS nnn
     // Jump to a machine code routine at nnn.
-    fn execute_sys_nnn(&mut self, nnn: usize) -> ProcessorCycle {
-        ProcessorCycle::Error(
-            DecodedOpcode::SysNnn { nnn },
-            "not implemented opcode".to_string(),
-        )
+    fn execute_sys_nnn(&mut self) -> ProcessorCycle {
+        ProcessorCycle::Error("Unsupported opcode")
     }
 
     // JP nnn
@@ -285,25 +614,37 @@ impl Processor {
 
     // OR Vx, Vy
     // Set Vx = Vx OR Vy.
-    fn execute_ox_vx_vy(&mut self, x: usize, y: usize) -> ProcessorCycle {
+    // With the logic quirk, VF is reset to 0.
+    fn execute_or_vx_vy(&mut self, x: usize, y: usize) -> ProcessorCycle {
         self.v_registers[x] |= self.v_registers[y];
+        self.apply_logic_quirk();
         ProcessorCycle::Next
     }
 
     // AND Vx, Vy
     // Set Vx = Vx AND Vy.
+    // With the logic quirk, VF is reset to 0.
     fn execute_and_vx_vy(&mut self, x: usize, y: usize) -> ProcessorCycle {
         self.v_registers[x] &= self.v_registers[y];
+        self.apply_logic_quirk();
         ProcessorCycle::Next
     }
 
     // XOR Vx, Vy
     // Set Vx = Vx XOR Vy.
+    // With the logic quirk, VF is reset to 0.
     fn execute_xor_vx_vy(&mut self, x: usize, y: usize) -> ProcessorCycle {
         self.v_registers[x] ^= self.v_registers[y];
+        self.apply_logic_quirk();
         ProcessorCycle::Next
     }
 
+    fn apply_logic_quirk(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.v_registers[0x0f] = 0;
+        }
+    }
+
     // ADD Vx, Vy
     // The values of Vx and Vy are added together. If the result is
     // greater than 8 bits (i.e. > 255) VF is set to 1, otherwise 0.
@@ -334,9 +675,11 @@ impl Processor {
     // SHR Vx
     // If the least-significant bit of Vx is 1, then VF is set to 1,
     // otherwise 0. Then Vx is divided by 2.
-    fn execute_shr_vx(&mut self, x: usize) -> ProcessorCycle {
-        let temp = self.v_registers[x] & 1;
-        self.v_registers[x] >>= 1;
+    // With the shift quirk, Vy is shifted and the result stored in Vx.
+    fn execute_shr_vx(&mut self, x: usize, y: usize) -> ProcessorCycle {
+        let source = self.shift_source(x, y);
+        let temp = source & 1;
+        self.v_registers[x] = source >> 1;
         self.v_registers[0xf] = temp;
         ProcessorCycle::Next
     }
@@ -358,13 +701,23 @@ impl Processor {
     // SHL Vx
     // If the most-significant bit of Vx is 1, then VF is set to 1,
     // otherwise to 0. Then Vx is multiplied by 2.
-    fn execute_shl_vx(&mut self, x: usize) -> ProcessorCycle {
-        let temp = self.v_registers[x] >> 7;
-        self.v_registers[x] <<= 1;
+    // With the shift quirk, Vy is shifted and the result stored in Vx.
+    fn execute_shl_vx(&mut self, x: usize, y: usize) -> ProcessorCycle {
+        let source = self.shift_source(x, y);
+        let temp = source >> 7;
+        self.v_registers[x] = source << 1;
         self.v_registers[0xf] = temp;
         ProcessorCycle::Next
     }
 
+    fn shift_source(&self, x: usize, y: usize) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.v_registers[y]
+        } else {
+            self.v_registers[x]
+        }
+    }
+
     // SNE Vx, Vy
     // Skip next instruction if Vx != Vy.
     fn execute_sne_vx_vy(&mut self, x: usize, y: usize) -> ProcessorCycle {
@@ -380,16 +733,21 @@ impl Processor {
 
     // JP V0, nnn
     // The program counter is set to nnn plus the value of V0.
+    // With the jump quirk, the highest nibble of nnn selects Vx instead of V0.
     fn execute_jp_v0_nnn(&mut self, nnn: usize) -> ProcessorCycle {
-        ProcessorCycle::Jump((self.v_registers[0] as usize) + nnn)
+        let x = if self.quirks.jump_uses_vx {
+            nnn >> 8
+        } else {
+            0
+        };
+        ProcessorCycle::Jump((self.v_registers[x] as usize) + nnn)
     }
 
     // RND Vx, nn
     // The interpreter generates a random number from 0 to 255,
     // which is then ANDed with the value nn. The results are stored in Vx.
     fn execute_rnd_vx_nn(&mut self, x: usize, nn: u8) -> ProcessorCycle {
-        let mut rng = rand::thread_rng();
-        self.v_registers[x] = rng.gen::<u8>() & nn;
+        self.v_registers[x] = self.rng.gen::<u8>() & nn;
         ProcessorCycle::Next
     }
 
@@ -400,11 +758,12 @@ impl Processor {
     // If this causes any pixels to be erased, VF is set to 1, otherwise
     // it is set to 0. If the sprite is positioned so part of it is outside
     // the coordinates of the display, it wraps around to the opposite side
-    // of the screen.
+    // of the screen. With the clipping quirk, the parts outside the display
+    // are not drawn instead.
     fn execute_drw_vx_vy_n(&mut self, x: usize, y: usize, n: usize) -> ProcessorCycle {
-        // Get the (x, y) coords for our sprite
-        let x_coord = self.v_registers[x] as usize;
-        let y_coord = self.v_registers[y] as usize;
+        // Get the (x, y) coords for our sprite, the origin always wraps
+        let x_coord = self.v_registers[x] as usize % SCREEN_WIDTH;
+        let y_coord = self.v_registers[y] as usize % SCREEN_HEIGHT;
         // The last digit determines how many rows high our sprite is
         let num_rows = n;
 
@@ -414,12 +773,18 @@ impl Processor {
         for y_line in 0..num_rows {
             // Determine which memory address our row's data is stored
             let addr = self.i_register + y_line;
-            let row_pixels = self.memory.load(addr); // 8 pixels wide
+            let row_pixels = self.memory.read(addr); // 8 pixels wide
                                                      // Iterate over each column in our row
                                                      // The rows in sprite are always 8 pixels wide, 1 byte
             for x_column in 0..8 {
                 // Use a mask to fetch current pixel's bit. Only flip if a 1
                 if (row_pixels & (0b1000_0000 >> x_column)) != 0 {
+                    let clipped =
+                        x_coord + x_column >= SCREEN_WIDTH || y_coord + y_line >= SCREEN_HEIGHT;
+                    if clipped && self.quirks.clip_sprites {
+                        continue;
+                    }
+
                     // Sprites should wrap around screen, so apply modulo
                     let x = (x_coord + x_column) % SCREEN_WIDTH;
                     let y = (y_coord + y_line) % SCREEN_HEIGHT;
@@ -455,6 +820,7 @@ impl Processor {
     fn execute_ld_vx_k(&mut self, x: usize) -> ProcessorCycle {
         self.keypad_wait = true;
         self.keypad_wait_index = x;
+        self.keypad_wait_key = None;
         ProcessorCycle::Next
     }
 
@@ -497,23 +863,33 @@ impl Processor {
     // LD [I], Vx
     // The interpreter copies the values of registers V0 through Vx
     // into memory, starting at the address in register I.
+    // With the load/store quirk, I is incremented by x + 1.
     fn execute_ld_at_i_vx(&mut self, x: usize) -> ProcessorCycle {
         for i in 0..x + 1 {
             self.memory.store(self.i_register + i, self.v_registers[i]);
         }
+        self.apply_load_store_quirk(x);
         ProcessorCycle::Next
     }
 
     // LD Vx, [I]
     // The interpreter reads values from memory starting at location
     // I into registers V0 through Vx.
+    // With the load/store quirk, I is incremented by x + 1.
     fn execute_ld_vx_at_i(&mut self, x: usize) -> ProcessorCycle {
         for i in 0..x + 1 {
-            self.v_registers[i] = self.memory.load(self.i_register + i);
+            self.v_registers[i] = self.memory.read(self.i_register + i);
         }
+        self.apply_load_store_quirk(x);
         ProcessorCycle::Next
     }
 
+    fn apply_load_store_quirk(&mut self, x: usize) {
+        if self.quirks.load_store_increments_i {
+            self.i_register += x + 1;
+        }
+    }
+
     // LD B, Vx
     // The interpreter takes the decimal value of Vx, and places
     // the hundreds digit in memory at location in I, the tens digit
@@ -528,11 +904,8 @@ impl Processor {
         ProcessorCycle::Next
     }
 
-    fn execute_unknown(&self, opcode: u16) -> ProcessorCycle {
-        ProcessorCycle::Error(
-            DecodedOpcode::Unknown { opcode },
-            "unknown opcode".to_string(),
-        )
+    fn execute_unknown(&self) -> ProcessorCycle {
+        ProcessorCycle::Error("Unknown opcode")
     }
 
     fn debug_rom(&mut self) {
@@ -553,13 +926,13 @@ impl Processor {
             self.keypad,
             address,
             opcode,
-            &decoded,
+            decoded,
         );
     }
 }
 
 enum ProcessorCycle {
-    Error(DecodedOpcode, String),
+    Error(&'static str),
     Next,
     Skip,
     Jump(usize),
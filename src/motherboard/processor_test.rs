@@ -1,5 +1,11 @@
+use super::super::layout::MemoryLayout;
 use super::super::memory::FONT_SPRITES;
+use super::super::quirks::QuirkPreset;
 use super::*;
+use crate::constants::{MEMORY_SIZE, RESERVED_MEMORY_SIZE, SCREEN_SIZE};
+use crate::peripherals::audio_driver::AudioEvent;
+use crate::peripherals::palette::{Palette, PalettePreset};
+use std::collections::VecDeque;
 
 const PROGRAM_COUNTER_START: usize = 0xF00;
 const PROGRAM_COUNTER_NEXT: usize = PROGRAM_COUNTER_START + OPCODE_SIZE;
@@ -35,7 +41,7 @@ fn test_initial_state() {
 #[test]
 fn test_load_data() {
     let mut processor = Processor::new();
-    processor.memory.reset(&[1, 2, 3], 3);
+    processor.memory.reset(&[1, 2, 3], 3, RESERVED_MEMORY_SIZE);
     assert_eq!(processor.memory.load(0x200), 1);
     assert_eq!(processor.memory.load(0x201), 2);
     assert_eq!(processor.memory.load(0x202), 3);
@@ -51,7 +57,7 @@ fn test_execute_opcode_00e0() {
     processor.execute_opcode(processor.decode_opcode(0x00e0));
 
     for index in 0..SCREEN_WIDTH * SCREEN_HEIGHT {
-        assert_eq!(processor.screen.get_pixel(index), false);
+        assert!(!processor.screen.get_pixel(index));
     }
 
     assert_eq!(processor.program_counter, PROGRAM_COUNTER_NEXT);
@@ -266,10 +272,10 @@ fn test_execute_opcode_dxyn() {
     processor.screen.set_pixel(SCREEN_WIDTH, true);
     processor.v_registers[0] = 0;
     processor.execute_opcode(processor.decode_opcode(0xd002));
-    assert_eq!(processor.screen.get_pixel(0), false);
-    assert_eq!(processor.screen.get_pixel(1), true);
-    assert_eq!(processor.screen.get_pixel(SCREEN_WIDTH), true);
-    assert_eq!(processor.screen.get_pixel(SCREEN_WIDTH + 1), false);
+    assert!(!processor.screen.get_pixel(0));
+    assert!(processor.screen.get_pixel(1));
+    assert!(processor.screen.get_pixel(SCREEN_WIDTH));
+    assert!(!processor.screen.get_pixel(SCREEN_WIDTH + 1));
     assert_eq!(processor.v_registers[0x0f], 1);
     assert_eq!(processor.program_counter, PROGRAM_COUNTER_NEXT);
 }
@@ -283,16 +289,16 @@ fn test_execute_opcode_dxyn_wrap_horizontal() {
     processor.v_registers[0] = x as u8;
     processor.v_registers[1] = 0;
     processor.execute_opcode(processor.decode_opcode(0xd011));
-    assert_eq!(processor.screen.get_pixel(x - 1), false);
-    assert_eq!(processor.screen.get_pixel(x), true);
-    assert_eq!(processor.screen.get_pixel(x + 1), true);
-    assert_eq!(processor.screen.get_pixel(x + 2), true);
-    assert_eq!(processor.screen.get_pixel(x + 3), true);
-    assert_eq!(processor.screen.get_pixel(0), true);
-    assert_eq!(processor.screen.get_pixel(1), true);
-    assert_eq!(processor.screen.get_pixel(2), true);
-    assert_eq!(processor.screen.get_pixel(3), true);
-    assert_eq!(processor.screen.get_pixel(4), false);
+    assert!(!processor.screen.get_pixel(x - 1));
+    assert!(processor.screen.get_pixel(x));
+    assert!(processor.screen.get_pixel(x + 1));
+    assert!(processor.screen.get_pixel(x + 2));
+    assert!(processor.screen.get_pixel(x + 3));
+    assert!(processor.screen.get_pixel(0));
+    assert!(processor.screen.get_pixel(1));
+    assert!(processor.screen.get_pixel(2));
+    assert!(processor.screen.get_pixel(3));
+    assert!(!processor.screen.get_pixel(4));
     assert_eq!(processor.v_registers[0x0f], 0);
 }
 
@@ -307,8 +313,8 @@ fn test_execute_opcode_dxyn_wrap_vertical() {
     processor.v_registers[0] = 0;
     processor.v_registers[1] = y as u8;
     processor.execute_opcode(processor.decode_opcode(0xd012));
-    assert_eq!(processor.screen.get_pixel(y * SCREEN_WIDTH), true);
-    assert_eq!(processor.screen.get_pixel(0), true);
+    assert!(processor.screen.get_pixel(y * SCREEN_WIDTH));
+    assert!(processor.screen.get_pixel(0));
     assert_eq!(processor.v_registers[0x0f], 0);
 }
 
@@ -357,22 +363,64 @@ fn test_execute_opcode_fx07() {
 fn test_execute_opcode_fx0a() {
     let mut processor = build_processor();
     processor.execute_opcode(processor.decode_opcode(0xf50a));
-    assert_eq!(processor.keypad_wait, true);
+    assert!(processor.keypad_wait);
     assert_eq!(processor.keypad_wait_index, 5);
     assert_eq!(processor.program_counter, PROGRAM_COUNTER_NEXT);
 
     // Tick with no keypress doesn't do anything
-    processor.tick(0x0);
-    assert_eq!(processor.keypad_wait, true);
+    processor.tick(&[]);
+    assert!(processor.keypad_wait);
     assert_eq!(processor.keypad_wait_index, 5);
     assert_eq!(processor.program_counter, PROGRAM_COUNTER_NEXT);
 
-    // Tick with a keypress finishes wait and loads
-    // first pressed key into vx
-    processor.tick(0xffff);
-    assert_eq!(processor.keypad_wait, false);
-    assert_eq!(processor.v_registers[5], 0);
-    assert_eq!(processor.program_counter, PROGRAM_COUNTER_NEXT);
+    // A press alone doesn't finish the wait
+    processor.tick(&[KeyEvent::Pressed(0xA)]);
+    assert!(processor.keypad_wait);
+    assert_eq!(processor.keypad, 1 << 0xA);
+
+    // Releasing another key doesn't either, releasing the pressed key
+    // finishes the wait and loads it into vx
+    processor.tick(&[KeyEvent::Pressed(0x3), KeyEvent::Released(0x3)]);
+    assert!(processor.keypad_wait);
+    processor.tick(&[KeyEvent::Released(0xA)]);
+    assert!(!processor.keypad_wait);
+    assert_eq!(processor.v_registers[5], 0xA);
+    assert_eq!(processor.keypad, 0);
+}
+
+#[test]
+fn test_execute_opcode_fx0a_ignores_held_keys() {
+    let mut processor = build_processor();
+    processor.tick(&[KeyEvent::Pressed(0x7)]);
+    processor.program_counter = PROGRAM_COUNTER_START;
+    processor.execute_opcode(processor.decode_opcode(0xf50a));
+
+    // A key held before the wait started is not accepted when released
+    processor.tick(&[]);
+    processor.tick(&[KeyEvent::Released(0x7)]);
+    assert!(processor.keypad_wait);
+
+    processor.tick(&[KeyEvent::Pressed(0x7)]);
+    processor.tick(&[KeyEvent::Released(0x7)]);
+    assert!(!processor.keypad_wait);
+    assert_eq!(processor.v_registers[5], 0x7);
+}
+
+#[test]
+fn test_execute_opcode_fx0a_press_quirk() {
+    let mut processor = build_processor();
+    processor.quirks = QuirkPreset::Schip.quirks();
+    processor.tick(&[KeyEvent::Pressed(0x7)]);
+    processor.program_counter = PROGRAM_COUNTER_START;
+    processor.execute_opcode(processor.decode_opcode(0xf50a));
+
+    // Held keys are still ignored, a new press finishes the wait at once
+    processor.tick(&[]);
+    assert!(processor.keypad_wait);
+    processor.tick(&[KeyEvent::Pressed(0xB)]);
+    assert!(!processor.keypad_wait);
+    assert_eq!(processor.v_registers[5], 0xB);
+    assert_eq!(processor.keypad, 1 << 0x7 | 1 << 0xB);
 }
 
 // LD DT, vX
@@ -462,9 +510,292 @@ fn test_timers() {
     let mut processor = build_processor();
     processor.delay_timer = 200;
     processor.sound_timer = 100;
-    processor.tick(0x0000);
+    processor.tick(&[]);
     processor.delay_timer -= 1;
     processor.sound_timer -= 1;
     assert_eq!(processor.delay_timer, 199);
     assert_eq!(processor.sound_timer, 99);
 }
+
+fn build_processor_with_quirks(preset: QuirkPreset) -> Processor {
+    let mut processor = build_processor();
+    processor.quirks = preset.quirks();
+    processor
+}
+
+// SHR Vx, Vy with shift quirk
+#[test]
+fn test_quirk_shift_uses_vy() {
+    let mut processor = build_processor_with_quirks(QuirkPreset::Cosmac);
+    processor.v_registers[0] = 0x04;
+    processor.v_registers[1] = 0x05;
+    processor.execute_opcode(processor.decode_opcode(0x8016));
+    assert_eq!(processor.v_registers[0], 0x02);
+    assert_eq!(processor.v_registers[0x0f], 1);
+
+    processor.v_registers[1] = 0b11000000;
+    processor.execute_opcode(processor.decode_opcode(0x801e));
+    assert_eq!(processor.v_registers[0], 0b10000000);
+    assert_eq!(processor.v_registers[0x0f], 1);
+}
+
+// LD [I], Vx and LD Vx, [I] with load/store quirk
+#[test]
+fn test_quirk_load_store_increments_i() {
+    let mut processor = build_processor_with_quirks(QuirkPreset::Cosmac);
+    processor.i_register = 1000;
+    processor.execute_opcode(processor.decode_opcode(0xf355));
+    assert_eq!(processor.i_register, 1004);
+    processor.execute_opcode(processor.decode_opcode(0xf165));
+    assert_eq!(processor.i_register, 1006);
+}
+
+// JP V0, addr with jump quirk
+#[test]
+fn test_quirk_jump_uses_vx() {
+    let mut processor = build_processor_with_quirks(QuirkPreset::Schip);
+    processor.v_registers[0] = 3;
+    processor.v_registers[1] = 5;
+    processor.execute_opcode(processor.decode_opcode(0xb123));
+    assert_eq!(processor.program_counter, 0x128);
+}
+
+// OR Vx, Vy with logic quirk
+#[test]
+fn test_quirk_logic_resets_vf() {
+    let mut processor = build_processor_with_quirks(QuirkPreset::Cosmac);
+    processor.v_registers[0x0f] = 1;
+    processor.execute_opcode(processor.decode_opcode(0x8011));
+    assert_eq!(processor.v_registers[0x0f], 0);
+}
+
+// DRW Vx, Vy, nibble with clipping quirk
+#[test]
+fn test_quirk_clip_sprites() {
+    let mut processor = build_processor_with_quirks(QuirkPreset::Schip);
+    let x = SCREEN_WIDTH - 4;
+    processor.i_register = 0;
+    processor.memory.store(0, 0b11111111);
+    processor.v_registers[0] = x as u8;
+    processor.v_registers[1] = 0;
+    processor.execute_opcode(processor.decode_opcode(0xd011));
+    assert!(processor.screen.get_pixel(x + 3));
+    assert!(!processor.screen.get_pixel(0));
+}
+
+// LD V0, 5; LD ST, V0; JP 0x204: a 5 frames beep
+#[test]
+fn test_headless_audio_capture() {
+    let mut cartridge_driver = CartridgeDriver {
+        rom: [0; MEMORY_SIZE],
+        rom_size: 6,
+    };
+    cartridge_driver.rom[..6].copy_from_slice(&[0x60, 0x05, 0xF0, 0x18, 0x12, 0x04]);
+
+    let mut processor = Processor::new();
+    let mut audio_capture = AudioCapture::new(Some("unused.wav".into()), &Settings::default());
+    processor.run_headless(
+        &cartridge_driver,
+        10,
+        &InputScript::default(),
+        &mut Recorder::new(&Settings::default(), None).unwrap(),
+        &mut audio_capture,
+    );
+
+    // 5 frames of beep from the frame setting the timer, then its release
+    let samples = audio_capture.samples();
+    assert_eq!(samples.len(), 10 * 735);
+    assert!(samples[..5 * 735].iter().all(|sample| *sample != 0));
+    assert_ne!(samples[5 * 735], 0);
+    assert!(samples[6 * 735..].iter().all(|sample| *sample == 0));
+}
+
+// LD V0, 5; JP 0x602, loaded and started at 0x600 on the ETI 660
+#[test]
+fn test_program_layout() {
+    let mut cartridge_driver = CartridgeDriver {
+        rom: [0; MEMORY_SIZE],
+        rom_size: 4,
+    };
+    cartridge_driver.rom[..4].copy_from_slice(&[0x60, 0x05, 0x16, 0x02]);
+    let settings = Settings {
+        program_layout: MemoryLayout::Eti660.program_layout(),
+        ..Settings::default()
+    };
+
+    let mut processor = Processor::with_settings(&settings);
+    let mut trace = Vec::new();
+    processor
+        .run_trace(&cartridge_driver, 3, &mut trace)
+        .unwrap();
+    let trace = String::from_utf8(trace).unwrap();
+    let addresses: Vec<&str> = trace.lines().map(|line| &line[..6]).collect();
+    assert_eq!(addresses, ["0x0600", "0x0602", "0x0602"]);
+    assert_eq!(processor.memory.load(0x200), 0);
+    assert_eq!(processor.v_registers[0], 5);
+
+    // A memory image replaces the interpreter area, the font installed again if left empty
+    let mut image = [0; MEMORY_SIZE];
+    image[0x200..0x206].copy_from_slice(&[0x60, 0x0A, 0xF0, 0x29, 0x12, 0x04]);
+    let mut cartridge_driver = CartridgeDriver {
+        rom: image,
+        rom_size: MEMORY_SIZE,
+    };
+    let settings = Settings {
+        program_layout: MemoryLayout::Image.program_layout(),
+        ..Settings::default()
+    };
+    let mut processor = Processor::with_settings(&settings);
+    processor
+        .run_trace(&cartridge_driver, 3, &mut Vec::new())
+        .unwrap();
+    assert_eq!(processor.program_counter, 0x204);
+    // LD F, V0 points at the sprite of A
+    assert_eq!(processor.i_register, 0xA * 5);
+    let sprite: Vec<u8> = (50..55)
+        .map(|address| processor.memory.load(address))
+        .collect();
+    assert_eq!(sprite, FONT_SPRITES[50..55]);
+
+    // The font of an image is kept
+    cartridge_driver.rom[0] = 0xFF;
+    let mut processor = Processor::with_settings(&settings);
+    processor
+        .run_trace(&cartridge_driver, 1, &mut Vec::new())
+        .unwrap();
+    assert_eq!(processor.memory.load(0), 0xFF);
+    assert_eq!(processor.memory.load(1), 0);
+}
+
+// LD I, 0x20A; DRW V0, V0, 1; LD B, V0; JP 0x206
+#[test]
+fn test_memory_access_counts() {
+    let mut processor = Processor::new();
+    let rom = [
+        0xA2, 0x0A, 0xD0, 0x01, 0xF0, 0x33, 0x12, 0x06, 0x00, 0x00, 0xFF,
+    ];
+    processor
+        .memory
+        .reset(&rom, rom.len(), RESERVED_MEMORY_SIZE);
+    for _ in 0..10 {
+        processor.tick(&[]);
+    }
+
+    let counts = processor.memory.access_counts();
+    assert_eq!(counts.fetches[0x200..0x20A], [1, 1, 1, 1, 1, 1, 7, 7, 0, 0]);
+    assert_eq!(counts.reads[0x20A], 1);
+    assert_eq!(counts.writes[0x20A..0x20D], [1, 1, 1]);
+    assert_eq!(counts.total(0x208), 0);
+}
+
+// CALL 0x206; JP 0x202; (padding); RET
+#[test]
+fn test_profiler_counts_instructions_and_frames() {
+    let mut processor = Processor::new();
+    processor.memory.reset(
+        &[0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x00, 0xEE],
+        8,
+        RESERVED_MEMORY_SIZE,
+    );
+    processor.profiler = Some(Profiler::new(processor.program_counter));
+    for _ in 0..4 {
+        processor.tick(&[]);
+    }
+    processor.update_timers();
+
+    let report = processor
+        .profiler
+        .as_ref()
+        .unwrap()
+        .report(&processor.memory);
+    assert_eq!((report.instructions, report.frames), (4, 1));
+    assert_eq!(
+        (report.hot_spots[0].address, report.hot_spots[0].count),
+        (0x202, 2)
+    );
+    assert_eq!(report.subroutines[1].entry, 0x206);
+    assert_eq!(report.subroutines[1].total_cycles, 1);
+}
+
+/// Frontend doing nothing, the polls of the input replaying a script until it runs out
+struct FakeFrontend {
+    palette: Palette,
+
+    /// Key events of every poll, with the key being rebound during it
+    polls: VecDeque<(Option<usize>, Vec<KeyEvent>)>,
+    rebinding_key: Option<usize>,
+}
+
+impl DisplayFrontend for FakeFrontend {
+    fn advance_frame(&mut self, _buffer: &[bool; SCREEN_SIZE]) {}
+    fn draw(&mut self, _buffer: &[bool; SCREEN_SIZE]) {}
+    fn needs_redraw(&self) -> bool {
+        false
+    }
+    fn handle_event(&mut self, _event: DisplayEvent) {}
+    fn draw_keypad(&mut self, _highlighted_key: usize) {}
+    fn save_screenshot(&mut self, _pixels: &[bool]) -> Result<(), String> {
+        Ok(())
+    }
+    fn show_message(&mut self, _message: String) {}
+    fn set_paused(&mut self, _paused: bool) {}
+    fn count_frame(&mut self, _instructions: usize) {}
+    fn palette(&self) -> &Palette {
+        &self.palette
+    }
+}
+
+impl InputFrontend for FakeFrontend {
+    fn poll(&mut self) -> Result<Vec<KeyEvent>, ()> {
+        let (rebinding_key, key_events) = self.polls.pop_front().ok_or(())?;
+        self.rebinding_key = rebinding_key;
+        Ok(key_events)
+    }
+    fn take_display_events(&mut self) -> Vec<DisplayEvent> {
+        Vec::new()
+    }
+    fn take_audio_events(&mut self) -> Vec<AudioEvent> {
+        Vec::new()
+    }
+    fn rebinding_key(&self) -> Option<usize> {
+        self.rebinding_key
+    }
+}
+
+impl AudioFrontend for FakeFrontend {
+    fn set_beeping(&mut self, _beeping: bool) {}
+    fn handle_event(&mut self, _event: AudioEvent) -> String {
+        String::new()
+    }
+}
+
+#[test]
+fn test_keys_released_while_rebinding() {
+    let mut cartridge_driver = CartridgeDriver {
+        rom: [0; MEMORY_SIZE],
+        rom_size: 2,
+    };
+    cartridge_driver.rom[..2].copy_from_slice(&[0x12, 0x00]);
+    let frontend = || FakeFrontend {
+        palette: PalettePreset::Green.palette(),
+        polls: VecDeque::from([
+            (None, vec![KeyEvent::Pressed(5)]),
+            (Some(0), vec![KeyEvent::Released(5)]),
+            (None, vec![]),
+        ]),
+        rebinding_key: None,
+    };
+    let (mut display, mut input, mut audio) = (frontend(), frontend(), frontend());
+    let settings = Settings::default();
+    let mut processor = Processor::with_settings(&settings);
+    processor.run(
+        &mut display,
+        &mut input,
+        &mut audio,
+        &cartridge_driver,
+        &mut Recorder::new(&settings, None).unwrap(),
+        &mut AudioCapture::new(None, &settings),
+        None,
+    );
+    assert_eq!(processor.keypad, 0);
+}
@@ -0,0 +1,95 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Behaviours that differ between CHIP-8 interpreters for the same opcode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Quirks {
+    /// 8XY6 and 8XYE shift VY into VX instead of shifting VX in place
+    pub(crate) shift_uses_vy: bool,
+
+    /// FX55 and FX65 leave I pointing past the last register transferred
+    pub(crate) load_store_increments_i: bool,
+
+    /// BNNN jumps to XNN + VX instead of NNN + V0
+    pub(crate) jump_uses_vx: bool,
+
+    /// 8XY1, 8XY2 and 8XY3 reset VF to 0
+    pub(crate) logic_resets_vf: bool,
+
+    /// DXYN clips sprites at the screen edges instead of wrapping them around
+    pub(crate) clip_sprites: bool,
+
+    /// Key event completing FX0A
+    pub(crate) key_wait: KeyWait,
+}
+
+/// Key event completing FX0A, the key must be pressed while waiting in both cases
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum KeyWait {
+    /// As soon as a key is pressed
+    Press,
+
+    /// When the pressed key is released, as on the COSMAC VIP
+    Release,
+}
+
+/// Named quirk sets matching well-known interpreters
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum QuirkPreset {
+    /// Behaviour of most modern interpreters (default)
+    Modern,
+
+    /// Original COSMAC VIP interpreter
+    Cosmac,
+
+    /// SUPER-CHIP 1.1 interpreter on the HP 48
+    Schip,
+}
+
+impl QuirkPreset {
+    /// Returns the name of the preset, as given on the command line and in the configuration
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            QuirkPreset::Modern => "modern",
+            QuirkPreset::Cosmac => "cosmac",
+            QuirkPreset::Schip => "schip",
+        }
+    }
+
+    pub(crate) fn quirks(self) -> Quirks {
+        match self {
+            QuirkPreset::Modern => Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                jump_uses_vx: false,
+                logic_resets_vf: false,
+                clip_sprites: false,
+                key_wait: KeyWait::Release,
+            },
+            QuirkPreset::Cosmac => Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: true,
+                clip_sprites: true,
+                key_wait: KeyWait::Release,
+            },
+            QuirkPreset::Schip => Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                jump_uses_vx: true,
+                logic_resets_vf: false,
+                clip_sprites: true,
+                key_wait: KeyWait::Press,
+            },
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        QuirkPreset::Modern.quirks()
+    }
+}
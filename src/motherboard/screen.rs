@@ -1,5 +1,5 @@
 use crate::constants::SCREEN_SIZE;
-use crate::DisplayDriver;
+use crate::peripherals::frontend::DisplayFrontend;
 
 pub(crate) struct Screen {
     pixels: [bool; SCREEN_SIZE],
@@ -19,20 +19,32 @@ impl Screen {
         self.needs_refresh = true;
     }
 
-    pub(crate) fn refresh(&mut self, display_driver: &mut DisplayDriver) {
-        if self.needs_refresh {
+    pub(crate) fn refresh(&mut self, display_driver: &mut dyn DisplayFrontend) {
+        if self.needs_refresh || display_driver.needs_redraw() {
             display_driver.draw(&self.pixels);
             self.needs_refresh = false;
         }
     }
 
+    /// Moves the display effects one emulated frame on, however often the display is drawn
+    pub(crate) fn advance_frame(&self, display_driver: &mut dyn DisplayFrontend) {
+        display_driver.advance_frame(&self.pixels);
+    }
+
+    /// Forces the next refresh to redraw the display
+    pub(crate) fn invalidate(&mut self) {
+        self.needs_refresh = true;
+    }
+
     pub(crate) fn get_pixel(&self, index: usize) -> bool {
         self.pixels[index]
     }
 
     pub(crate) fn set_pixel(&mut self, index: usize, value: bool) {
-        self.pixels[index] = value;
-        self.needs_refresh = true;
+        if self.pixels[index] != value {
+            self.pixels[index] = value;
+            self.needs_refresh = true;
+        }
     }
 
     pub(crate) fn get_all_pixels(&self) -> &[bool] {
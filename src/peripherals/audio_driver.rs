@@ -1,57 +1,113 @@
-use sdl2;
 use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
 
+use crate::constants::AUDIO_SAMPLE_RATE;
+use crate::settings::Settings;
+
+use super::frontend::AudioFrontend;
+use super::tone::Tone;
+
+const VOLUME_STEP: f32 = 0.05; // Volume change of the volume hotkeys
+
+/// Sound changes requested through the input driver
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum AudioEvent {
+    /// The mute hotkey was pressed
+    ToggleMute,
+
+    /// The volume down hotkey was pressed
+    VolumeDown,
+
+    /// The volume up hotkey was pressed
+    VolumeUp,
+}
+
+/// Plays the beeper through SDL, or stays silent when sound is disabled.
+/// The device plays all along, the beeps being started and stopped by the gate of the tone.
 pub(crate) struct AudioDriver {
-    device: AudioDevice<SquareWave>,
+    device: Option<AudioDevice<Beeper>>,
+    beeping: bool,
+    volume: f32,
+    muted: bool,
 }
 
 impl AudioDriver {
-    pub(crate) fn new(sdl_context: &sdl2::Sdl) -> Self {
+    pub(crate) fn new(sdl_context: &sdl2::Sdl, settings: &Settings) -> Self {
+        let mut audio_driver = AudioDriver {
+            device: None,
+            beeping: false,
+            volume: settings.beep_volume,
+            muted: false,
+        };
+        if !settings.sound {
+            return audio_driver;
+        }
+
         let audio_subsystem = sdl_context.audio().unwrap();
 
         let desired_spec = AudioSpecDesired {
-            freq: Some(44100),
+            freq: Some(AUDIO_SAMPLE_RATE),
             channels: Some(1), // mono
             samples: None,     // default sample size
         };
 
         let device = audio_subsystem
             .open_playback(None, &desired_spec, |spec| {
-                // initialize the audio callback
-                SquareWave {
-                    phase_inc: 240.0 / spec.freq as f32,
-                    phase: 0.0,
-                    volume: 0.25,
-                }
+                Beeper(Tone::new(settings, spec.freq))
             })
             .unwrap();
+        device.resume();
 
-        AudioDriver { device }
+        audio_driver.device = Some(device);
+        audio_driver
     }
+}
 
-    pub(crate) fn start_beep(&self) {
-        self.device.resume();
+impl AudioFrontend for AudioDriver {
+    fn set_beeping(&mut self, beeping: bool) {
+        if beeping == self.beeping {
+            return;
+        }
+        self.beeping = beeping;
+        if let Some(device) = &mut self.device {
+            device.lock().0.set_gate(beeping);
+        }
     }
 
-    pub(crate) fn stop_beep(&self) {
-        self.device.pause();
+    fn handle_event(&mut self, event: AudioEvent) -> String {
+        match event {
+            AudioEvent::ToggleMute => self.muted = !self.muted,
+            AudioEvent::VolumeDown => {
+                self.volume = (self.volume - VOLUME_STEP).max(0.0);
+                self.muted = false;
+            }
+            AudioEvent::VolumeUp => {
+                self.volume = (self.volume + VOLUME_STEP).min(1.0);
+                self.muted = false;
+            }
+        }
+        let volume = if self.muted { 0.0 } else { self.volume };
+        if let Some(device) = &mut self.device {
+            device.lock().0.set_volume(volume);
+        }
+
+        let message = format!(
+            "Volume: {:.0}%{}",
+            self.volume * 100.0,
+            if self.muted { " (muted)" } else { "" }
+        );
+        log::info!("{}", message);
+        message
     }
 }
 
-struct SquareWave {
-    phase_inc: f32,
-    phase: f32,
-    volume: f32,
-}
+struct Beeper(Tone);
 
-impl AudioCallback for SquareWave {
+impl AudioCallback for Beeper {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut [f32]) {
-        // Generate a square wave
         for x in out.iter_mut() {
-            *x = self.volume * if self.phase < 0.5 { 1.0 } else { -1.0 };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
+            *x = self.0.next_sample();
         }
     }
 }
@@ -1,27 +1,32 @@
-use std::fs::File;
-use std::io::prelude::*;
+use sha1::{Digest, Sha1};
 
-use crate::constants::MAX_ROM_SIZE;
+use crate::constants::MEMORY_SIZE;
+
+use super::rom_loader;
 
 pub(crate) struct CartridgeDriver {
-    pub rom: [u8; MAX_ROM_SIZE],
+    pub rom: [u8; MEMORY_SIZE], // Up to a whole memory image
     pub rom_size: usize,
 }
 
 impl CartridgeDriver {
-    pub(crate) fn new(filename: &str) -> Self {
-        let mut f = File::open(filename).expect("ERROR: file not found");
-        let mut buffer = [0u8; MAX_ROM_SIZE];
+    /// Loads a ROM from any source of the ROM loader: file, archive, text, cartridge or stdin
+    pub(crate) fn new(source: &str) -> Result<Self, String> {
+        let bytes = rom_loader::load(source)?;
+        let mut rom = [0u8; MEMORY_SIZE];
+        rom[..bytes.len()].copy_from_slice(&bytes);
 
-        let bytes_read = if let Ok(bytes_read) = f.read(&mut buffer) {
-            bytes_read
-        } else {
-            0
-        };
+        Ok(CartridgeDriver {
+            rom,
+            rom_size: bytes.len(),
+        })
+    }
 
-        CartridgeDriver {
-            rom: buffer,
-            rom_size: bytes_read,
-        }
+    /// Lowercase hex SHA-1 of the ROM contents, used to identify a ROM
+    pub(crate) fn sha1(&self) -> String {
+        Sha1::digest(&self.rom[..self.rom_size])
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
     }
 }
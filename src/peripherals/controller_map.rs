@@ -0,0 +1,70 @@
+use sdl2::controller::Button;
+use std::collections::BTreeMap;
+
+use super::keymap::parse_keypad_key;
+
+//  Controller            Chip8
+//  D-pad up/down    >    2 / 8
+//  D-pad left/right >    4 / 6
+//  A / B / X / Y    >    5 / 0 / 7 / 9
+//  Shoulders L / R  >    1 / 3
+//  Back / Start     >    E / F
+const DEFAULT_BUTTONS: [(Button, usize); 12] = [
+    (Button::DPadUp, 0x2),
+    (Button::DPadDown, 0x8),
+    (Button::DPadLeft, 0x4),
+    (Button::DPadRight, 0x6),
+    (Button::A, 0x5),
+    (Button::B, 0x0),
+    (Button::X, 0x7),
+    (Button::Y, 0x9),
+    (Button::LeftShoulder, 0x1),
+    (Button::RightShoulder, 0x3),
+    (Button::Back, 0xE),
+    (Button::Start, 0xF),
+];
+
+// Value unbinding a button in the overrides
+pub(crate) const UNBOUND: &str = "none";
+
+/// Maps game controller buttons to Chip8 keys, each button triggers at most one key
+#[derive(Clone)]
+pub(crate) struct ControllerMap {
+    bindings: BTreeMap<i32, (Button, usize)>,
+}
+
+impl ControllerMap {
+    /// Builds the default mapping, replacing the buttons found in `overrides`
+    /// (SDL button name => Chip8 key as a hex digit, or "none")
+    pub(crate) fn new(overrides: &BTreeMap<String, String>) -> Result<Self, String> {
+        let mut bindings: BTreeMap<i32, (Button, usize)> = DEFAULT_BUTTONS
+            .iter()
+            .map(|&(button, key)| (button as i32, (button, key)))
+            .collect();
+
+        for (name, key) in overrides {
+            let button = parse_button(name)?;
+            if key.eq_ignore_ascii_case(UNBOUND) {
+                bindings.remove(&(button as i32));
+            } else {
+                bindings.insert(button as i32, (button, parse_keypad_key(key)?));
+            }
+        }
+
+        Ok(ControllerMap { bindings })
+    }
+
+    /// Returns the keypad bits of the Chip8 keys whose buttons are pressed
+    pub(crate) fn keypad_bits(&self, is_pressed: impl Fn(Button) -> bool) -> u16 {
+        self.bindings
+            .values()
+            .filter(|(button, _)| is_pressed(*button))
+            .fold(0, |bits, (_, key)| bits | (1 << key))
+    }
+}
+
+/// Parses a controller button by its SDL name (e.g. "a", "dpup", "leftshoulder")
+pub(crate) fn parse_button(name: &str) -> Result<Button, String> {
+    Button::from_string(&name.to_lowercase())
+        .ok_or_else(|| format!("unknown controller button '{}'", name))
+}
@@ -0,0 +1,171 @@
+use sdl2::pixels::{self, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+
+use crate::constants::MEMORY_SIZE;
+use crate::motherboard::processor::Processor;
+use crate::peripherals::osd_font::{text_pixels, text_width, CHAR_WIDTH, GLYPH_HEIGHT};
+use crate::toolchain::debugger_view::{
+    DebuggerView, TextStyle, COLUMNS, MEMORY_MAP_COLUMN, MEMORY_MAP_ROW, ROWS,
+};
+use crate::toolchain::heat_map::{self, MAP_WIDTH};
+
+const DEBUGGER_SCALE: u32 = 2; // Size of a font pixel in window pixels
+const MARGIN: usize = 4; // Distance of the panels from the edges of the window, in font pixels
+const LINE_HEIGHT: usize = GLYPH_HEIGHT + 2;
+
+const BACKGROUND_COLOR: pixels::Color = pixels::Color::RGB(24, 24, 24);
+const TEXT_COLOR: pixels::Color = pixels::Color::RGB(200, 200, 200);
+const HEADING_COLOR: pixels::Color = pixels::Color::RGB(120, 160, 255);
+const CURRENT_COLOR: pixels::Color = pixels::Color::RGB(65, 236, 157); // Also behind the text
+const BREAKPOINT_COLOR: pixels::Color = pixels::Color::RGB(255, 90, 90);
+const HIGHLIGHT_TEXT_COLOR: pixels::Color = pixels::Color::RGB(0, 0, 0);
+
+/// Debugger window changes received through the input driver
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DebuggerEvent {
+    /// The left mouse button was pressed at a position of the window
+    Click { x: i32, y: i32 },
+
+    /// The window was closed
+    Close,
+}
+
+/// Second window showing the debugger panels, redrawn every frame
+pub(crate) struct DebuggerWindow {
+    view: DebuggerView,
+
+    /// Heat map of the memory accesses, one pixel per address
+    memory_map: Texture,
+
+    canvas: Canvas<Window>,
+
+    /// False once the window was closed, it is hidden until the emulator exits
+    open: bool,
+}
+
+impl DebuggerWindow {
+    pub(crate) fn new(sdl_context: &sdl2::Sdl) -> Result<Self, String> {
+        let video_subsystem = sdl_context.video()?;
+        let window = video_subsystem
+            .window(
+                "Chip8 debugger",
+                (COLUMNS * CHAR_WIDTH + 2 * MARGIN) as u32 * DEBUGGER_SCALE,
+                (ROWS * LINE_HEIGHT + 2 * MARGIN) as u32 * DEBUGGER_SCALE,
+            )
+            .build()
+            .map_err(|e| format!("Error: failed to open the debugger window: {}", e))?;
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+        let memory_map = canvas
+            .texture_creator()
+            .create_texture_streaming(
+                PixelFormatEnum::RGB24,
+                MAP_WIDTH as u32,
+                (MEMORY_SIZE / MAP_WIDTH) as u32,
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(DebuggerWindow {
+            view: DebuggerView::new(),
+            memory_map,
+            canvas,
+            open: true,
+        })
+    }
+
+    /// Identifier of the window in SDL events
+    pub(crate) fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    /// Toggles a breakpoint on click and hides the window on close,
+    /// returning a message about the change, if any
+    pub(crate) fn handle_event(&mut self, event: DebuggerEvent) -> Option<String> {
+        match event {
+            DebuggerEvent::Click { x, y } => {
+                let font_pixel = |position: i32| {
+                    (position.max(0) as usize / DEBUGGER_SCALE as usize).checked_sub(MARGIN)
+                };
+                let column = font_pixel(x)? / CHAR_WIDTH;
+                let row = font_pixel(y)? / LINE_HEIGHT;
+                let (address, set) = self.view.click(column, row)?;
+                Some(if set {
+                    format!("Breakpoint set at {:#06X}", address)
+                } else {
+                    format!("Breakpoint cleared at {:#06X}", address)
+                })
+            }
+            DebuggerEvent::Close => {
+                self.canvas.window_mut().hide();
+                self.open = false;
+                Some("Debugger closed".to_string())
+            }
+        }
+    }
+
+    /// Returns true if the game should stop before the instruction at the program counter,
+    /// breakpoints being ignored once the window is closed
+    pub(crate) fn should_break(&mut self, program_counter: usize) -> bool {
+        self.open && self.view.should_break(program_counter)
+    }
+
+    pub(crate) fn draw(&mut self, processor: &Processor) {
+        if !self.open {
+            return;
+        }
+
+        self.canvas.set_draw_color(BACKGROUND_COLOR);
+        self.canvas.clear();
+
+        let font_rect = |x: usize, y: usize, width: usize, height: usize| {
+            Rect::new(
+                ((MARGIN + x) as u32 * DEBUGGER_SCALE) as i32,
+                ((MARGIN + y) as u32 * DEBUGGER_SCALE) as i32,
+                width as u32 * DEBUGGER_SCALE,
+                height as u32 * DEBUGGER_SCALE,
+            )
+        };
+        for text in self.view.layout(processor) {
+            let (x, y) = (text.column * CHAR_WIDTH, text.row * LINE_HEIGHT);
+
+            // Current and pressed text is drawn in black on a box, like a selection
+            let color = match text.style {
+                TextStyle::Normal => TEXT_COLOR,
+                TextStyle::Heading => HEADING_COLOR,
+                TextStyle::Breakpoint => BREAKPOINT_COLOR,
+                TextStyle::Current | TextStyle::Pressed => {
+                    self.canvas.set_draw_color(CURRENT_COLOR);
+                    self.canvas
+                        .fill_rect(
+                            font_rect(x, y, text_width(&text.text) + 2, GLYPH_HEIGHT + 2)
+                                .left_shifted(DEBUGGER_SCALE as i32)
+                                .top_shifted(DEBUGGER_SCALE as i32),
+                        )
+                        .unwrap();
+                    HIGHLIGHT_TEXT_COLOR
+                }
+            };
+
+            let pixels: Vec<Rect> = text_pixels(&text.text)
+                .map(|(pixel_x, pixel_y)| font_rect(x + pixel_x, y + pixel_y, 1, 1))
+                .collect();
+            self.canvas.set_draw_color(color);
+            self.canvas.fill_rects(&pixels).unwrap();
+        }
+
+        let colors: Vec<u8> = heat_map::colors(&processor.memory).concat();
+        self.memory_map
+            .update(None, &colors, MAP_WIDTH * 3)
+            .unwrap();
+        let memory_map_rect = font_rect(
+            MEMORY_MAP_COLUMN * CHAR_WIDTH,
+            (MEMORY_MAP_ROW + 1) * LINE_HEIGHT,
+            MAP_WIDTH,
+            MEMORY_SIZE / MAP_WIDTH,
+        );
+        self.canvas
+            .copy(&self.memory_map, None, memory_map_rect)
+            .unwrap();
+        self.canvas.present();
+    }
+}
@@ -1,75 +1,344 @@
-use sdl2;
-use sdl2::pixels;
+use sdl2::pixels::{self, PixelFormatEnum};
 use sdl2::rect::Rect;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
+use sdl2::render::{BlendMode, Canvas, Texture};
+use sdl2::video::{FullscreenType, Window};
+use std::time::Instant;
 
-use crate::constants::{
-    BACKGROUND_COLOR as BG, FOREGROUND_COLOR as FG, SCALE_FACTOR, SCREEN_HEIGHT, SCREEN_WIDTH,
-};
+use crate::constants::{SCREEN_HEIGHT, SCREEN_SIZE, SCREEN_WIDTH};
+use crate::motherboard::memory::FONT_SPRITES;
+use crate::peripherals::display_filter::FrameFilter;
+use crate::peripherals::frontend::DisplayFrontend;
+use crate::peripherals::keymap::KEYPAD_LAYOUT;
+use crate::peripherals::osd::Osd;
+use crate::peripherals::osd_font::{text_pixels, text_width, GLYPH_HEIGHT};
+use crate::peripherals::palette::{Palette, PalettePreset};
+use crate::peripherals::post_processing::PostProcessor;
+use crate::settings::Settings;
+use crate::toolchain::screenshot::Screenshot;
+
+// Bytes per pixel of the RGB24 streaming texture
+const BYTES_PER_PIXEL: usize = 3;
+
+const OSD_WINDOW_HEIGHT: u32 = 200; // Height of the window in OSD font pixels, at most
+const OSD_TEXT_COLOR: pixels::Color = pixels::Color::RGB(255, 255, 255);
+const OSD_BOX_COLOR: pixels::Color = pixels::Color::RGBA(0, 0, 0, 160); // Behind the OSD text
+
+/// Window changes requested through the input driver
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DisplayEvent {
+    /// The window was resized or needs to be redrawn
+    Resized,
+
+    /// The fullscreen hotkey was pressed
+    ToggleFullscreen,
+
+    /// The palette hotkey was pressed
+    CyclePalette,
+
+    /// The screenshot hotkey was pressed, handled by the processor which owns the screen
+    Screenshot,
+
+    /// The recording hotkey was pressed, handled by the processor which owns the recorder
+    ToggleRecording,
+
+    /// The pause hotkey was pressed, handled by the processor
+    TogglePause,
+
+    /// The hotkey of the FPS and IPS counter was pressed
+    ToggleStats,
+}
 
 pub(crate) struct DisplayDriver {
-    foreground_color: pixels::Color,
-    background_color: pixels::Color,
+    palette_preset: PalettePreset,
+    palette: Palette,
+    integer_scale: bool,
+    screenshot: Screenshot,
+
+    /// Area of the window the display is scaled to, the rest is filled with the background
+    viewport: Rect,
+
+    /// Flicker reduction applied to every frame before it is drawn
+    filter: FrameFilter,
+
+    /// Display pixels as RGB24, uploaded to the texture on every draw
+    frame: [u8; SCREEN_SIZE * BYTES_PER_PIXEL],
+
+    /// Effects applied to the display upscaled by the scale factor, if any are selected
+    post_processor: Option<PostProcessor>,
+
+    /// Messages and status drawn over the display, and whether they were on the last draw
+    osd: Osd,
+    osd_drawn: bool,
+
+    /// Streaming texture of the display, 64x32 or upscaled with the effects,
+    /// scaled to the window in a single copy
+    texture: Texture,
+
     canvas: Canvas<Window>,
 }
 
 impl DisplayDriver {
-    pub(crate) fn new(sdl_context: &sdl2::Sdl) -> Self {
-        let foreground_color = pixels::Color::RGB(FG[0], FG[1], FG[2]);
-        let background_color = pixels::Color::RGB(BG[0], BG[1], BG[2]);
+    pub(crate) fn new(sdl_context: &sdl2::Sdl, settings: &Settings) -> Self {
+        let [bg_r, bg_g, bg_b] = settings.palette.background();
+        let background_color = pixels::Color::RGB(bg_r, bg_g, bg_b);
+        let scale_factor = settings.scale_factor;
 
         let video_subsystem = sdl_context.video().unwrap();
-        let window = video_subsystem
-            .window(
-                "Chip8",
-                (SCREEN_WIDTH as u32) * SCALE_FACTOR,
-                (SCREEN_HEIGHT as u32) * SCALE_FACTOR,
-            )
-            .position_centered()
-            .opengl()
-            .build()
-            .unwrap();
+        let mut window_builder = video_subsystem.window(
+            "Chip8",
+            (SCREEN_WIDTH as u32) * scale_factor,
+            (SCREEN_HEIGHT as u32) * scale_factor,
+        );
+        window_builder.position_centered().resizable().opengl();
+        if settings.fullscreen {
+            window_builder.fullscreen_desktop();
+        }
+        let window = window_builder.build().unwrap();
 
         let mut canvas = window.into_canvas().build().unwrap();
+        let post_processor = (!settings.post_effects.is_empty())
+            .then(|| PostProcessor::new(&settings.post_effects, scale_factor as usize));
+        let texture_scale = if post_processor.is_some() {
+            scale_factor
+        } else {
+            1
+        };
+        let texture = canvas
+            .texture_creator()
+            .create_texture_streaming(
+                PixelFormatEnum::RGB24,
+                SCREEN_WIDTH as u32 * texture_scale,
+                SCREEN_HEIGHT as u32 * texture_scale,
+            )
+            .unwrap();
 
         canvas.set_draw_color(background_color);
         canvas.clear();
         canvas.present();
 
-        DisplayDriver {
+        let mut display_driver = DisplayDriver {
+            palette_preset: settings.palette_preset,
+            palette: settings.palette,
+            screenshot: Screenshot::new(settings),
+            integer_scale: settings.integer_scale,
+            viewport: Rect::new(0, 0, 1, 1),
+            filter: FrameFilter::new(
+                settings.display_filter,
+                settings.filter_decay,
+                settings.blend_frames,
+            ),
+            frame: [0; SCREEN_SIZE * BYTES_PER_PIXEL],
+            post_processor,
+            osd: Osd::new(settings.show_fps, Instant::now()),
+            osd_drawn: false,
+            texture,
             canvas,
-            foreground_color,
-            background_color,
+        };
+        display_driver.layout();
+        display_driver
+    }
+
+    // Fits the display to the current size of the window
+    fn layout(&mut self) {
+        let (width, height) = self.canvas.output_size().unwrap();
+        self.viewport = viewport(width, height, self.integer_scale);
+    }
+
+    // Draws the OSD over the whole window, with font pixels scaled to the window height
+    fn draw_osd(&mut self) {
+        let now = Instant::now();
+        self.osd_drawn = self.osd.is_visible(now);
+        if !self.osd_drawn {
+            return;
         }
+
+        let (width, height) = self.canvas.output_size().unwrap();
+        let scale = (height / OSD_WINDOW_HEIGHT).max(1);
+        let texts = self
+            .osd
+            .layout((width / scale) as usize, (height / scale) as usize, now);
+
+        let font_rect = |x: usize, y: usize, width: usize, height: usize| {
+            Rect::new(
+                x as i32 * scale as i32,
+                y as i32 * scale as i32,
+                width as u32 * scale,
+                height as u32 * scale,
+            )
+        };
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        for text in texts {
+            // One font pixel of padding around the text
+            let background =
+                font_rect(text.x, text.y, text_width(&text.text) + 2, GLYPH_HEIGHT + 2)
+                    .left_shifted(scale as i32)
+                    .top_shifted(scale as i32);
+            self.canvas.set_draw_color(OSD_BOX_COLOR);
+            self.canvas.fill_rect(background).unwrap();
+
+            let pixels: Vec<Rect> = text_pixels(&text.text)
+                .map(|(x, y)| font_rect(text.x + x, text.y + y, 1, 1))
+                .collect();
+            self.canvas.set_draw_color(OSD_TEXT_COLOR);
+            self.canvas.fill_rects(&pixels).unwrap();
+        }
+        self.canvas.set_blend_mode(BlendMode::None);
     }
 
-    pub(crate) fn draw(&mut self, buffer: &[bool; SCREEN_WIDTH * SCREEN_HEIGHT]) {
-        // Clear canvas with background color
-        self.canvas.set_draw_color(self.background_color);
+    /// Draws pixels of the given brightnesses, then the OSD on top of them
+    fn render(&mut self, intensities: &[f32; SCREEN_SIZE]) {
+        for (intensity, rgb) in intensities
+            .iter()
+            .zip(self.frame.chunks_exact_mut(BYTES_PER_PIXEL))
+        {
+            let (foreground, background) = (self.palette.foreground(), self.palette.background());
+            for (channel, (foreground, background)) in
+                rgb.iter_mut().zip(foreground.iter().zip(&background))
+            {
+                let (foreground, background) = (*foreground as f32, *background as f32);
+                *channel = (background + (foreground - background) * intensity).round() as u8;
+            }
+        }
+
+        match &mut self.post_processor {
+            Some(post_processor) => {
+                let image = post_processor.process(&self.frame);
+                self.texture
+                    .update(None, &image.pixels, image.width * BYTES_PER_PIXEL)
+                    .unwrap();
+            }
+            None => self
+                .texture
+                .update(None, &self.frame, SCREEN_WIDTH * BYTES_PER_PIXEL)
+                .unwrap(),
+        }
+        let [bg_r, bg_g, bg_b] = self.palette.background();
+        self.canvas
+            .set_draw_color(pixels::Color::RGB(bg_r, bg_g, bg_b));
         self.canvas.clear();
+        self.canvas
+            .copy(&self.texture, None, self.viewport)
+            .unwrap();
+        self.draw_osd();
+        self.canvas.present();
+    }
+}
 
-        // Now set draw color to foreground color, iterate through each pixel and see if it should be drawn
-        for (i, pixel) in buffer.iter().enumerate() {
-            if *pixel {
-                self.canvas.set_draw_color(self.foreground_color);
-            } else {
-                self.canvas.set_draw_color(self.background_color)
+impl DisplayFrontend for DisplayDriver {
+    fn handle_event(&mut self, event: DisplayEvent) {
+        match event {
+            DisplayEvent::Resized
+            | DisplayEvent::Screenshot
+            | DisplayEvent::ToggleRecording
+            | DisplayEvent::TogglePause => {}
+            DisplayEvent::ToggleStats => self.osd.toggle_stats(),
+            DisplayEvent::ToggleFullscreen => {
+                let window = self.canvas.window_mut();
+                let fullscreen = match window.fullscreen_state() {
+                    FullscreenType::Off => FullscreenType::Desktop,
+                    _ => FullscreenType::Off,
+                };
+                if let Err(e) = window.set_fullscreen(fullscreen) {
+                    log::error!("Failed to toggle fullscreen: {}", e);
+                }
+            }
+            // Custom colors are dropped, every preset is shown as is
+            DisplayEvent::CyclePalette => {
+                self.palette_preset = self.palette_preset.next();
+                self.palette = self.palette_preset.palette();
+                log::info!("Palette: {:?}", self.palette_preset);
+                self.show_message(format!("Palette: {:?}", self.palette_preset));
             }
+        }
+        self.layout();
+    }
+
+    fn save_screenshot(&mut self, pixels: &[bool]) -> Result<(), String> {
+        let path = self.screenshot.save(pixels, &self.palette)?;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.show_message(format!("Screenshot saved to {}", file_name));
+        Ok(())
+    }
+
+    fn show_message(&mut self, message: String) {
+        self.osd.show_message(message, Instant::now());
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.osd.set_paused(paused);
+    }
+
+    fn count_frame(&mut self, instructions: usize) {
+        self.osd.count_frame(instructions, Instant::now());
+    }
 
-            // Convert our 1D array's index into a 2D (x,y) position
-            let x = (i % SCREEN_WIDTH) as u32;
-            let y = (i / SCREEN_WIDTH) as u32;
-
-            // Draw a rectangle at (x,y), scaled up by our SCALE_FACTOR value
-            let rect = Rect::new(
-                (x * SCALE_FACTOR) as i32,
-                (y * SCALE_FACTOR) as i32,
-                SCALE_FACTOR,
-                SCALE_FACTOR,
-            );
-            self.canvas.fill_rect(rect).unwrap();
+    fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    // While the filter still needs frames to fade out older pixels,
+    // and while the OSD shows something or has just been cleared
+    fn needs_redraw(&self) -> bool {
+        self.filter.is_fading() || self.osd_drawn || self.osd.is_visible(Instant::now())
+    }
+
+    fn advance_frame(&mut self, buffer: &[bool; SCREEN_SIZE]) {
+        self.filter.advance(buffer);
+    }
+
+    fn draw(&mut self, buffer: &[bool; SCREEN_SIZE]) {
+        let intensities = self.filter.intensities(buffer);
+        self.render(&intensities);
+    }
+
+    fn draw_keypad(&mut self, highlighted_key: usize) {
+        // Each key is a 16x8 cell with its 4x5 font sprite in the middle
+        const CELL_WIDTH: usize = SCREEN_WIDTH / 4;
+        const CELL_HEIGHT: usize = SCREEN_HEIGHT / 4;
+
+        let mut buffer = [false; SCREEN_SIZE];
+        for (position, key) in KEYPAD_LAYOUT.iter().enumerate() {
+            let cell_x = (position % 4) * CELL_WIDTH;
+            let cell_y = (position / 4) * CELL_HEIGHT;
+            let highlighted = *key == highlighted_key;
+
+            for y in 0..CELL_HEIGHT {
+                for x in 0..CELL_WIDTH {
+                    let glyph_x = x.wrapping_sub(6);
+                    let glyph_y = y.wrapping_sub(1);
+                    let lit = glyph_x < 4
+                        && glyph_y < 5
+                        && FONT_SPRITES[key * 5 + glyph_y] & (0b1000_0000 >> glyph_x) != 0;
+                    buffer[cell_x + x + SCREEN_WIDTH * (cell_y + y)] = lit != highlighted;
+                }
+            }
         }
-        self.canvas.present();
+        // The keypad is drawn as is, without the fading of the filter
+        self.render(&buffer.map(|lit| if lit { 1.0 } else { 0.0 }));
+    }
+}
+
+/// Returns the largest area with the aspect ratio of the display fitting in the window,
+/// centered, with letterboxing on the sides left over
+pub(crate) fn viewport(window_width: u32, window_height: u32, integer_scale: bool) -> Rect {
+    let width_scale = window_width as f64 / SCREEN_WIDTH as f64;
+    let height_scale = window_height as f64 / SCREEN_HEIGHT as f64;
+    let mut scale = width_scale.min(height_scale);
+    if integer_scale {
+        // Windows smaller than the display still show it, cropped
+        scale = scale.floor().max(1.0);
     }
+
+    let width = (SCREEN_WIDTH as f64 * scale).round() as u32;
+    let height = (SCREEN_HEIGHT as f64 * scale).round() as u32;
+    Rect::new(
+        (window_width as i32 - width as i32) / 2,
+        (window_height as i32 - height as i32) / 2,
+        width.max(1),
+        height.max(1),
+    )
 }
+
+#[cfg(test)]
+#[path = "./display_driver_test.rs"]
+mod display_driver_test;
@@ -0,0 +1,23 @@
+use super::*;
+
+#[test]
+fn test_viewport_fills_window_with_display_ratio() {
+    assert_eq!(viewport(640, 320, false), Rect::new(0, 0, 640, 320));
+    assert_eq!(viewport(640, 320, true), Rect::new(0, 0, 640, 320));
+}
+
+#[test]
+fn test_viewport_letterboxing() {
+    // Bars above and below in a taller window, on the sides in a wider one
+    assert_eq!(viewport(640, 480, false), Rect::new(0, 80, 640, 320));
+    assert_eq!(viewport(1000, 320, false), Rect::new(180, 0, 640, 320));
+}
+
+#[test]
+fn test_viewport_integer_scale() {
+    assert_eq!(viewport(700, 500, false), Rect::new(0, 75, 700, 350));
+    assert_eq!(viewport(700, 500, true), Rect::new(30, 90, 640, 320));
+
+    // Windows smaller than the display keep a scale of 1
+    assert_eq!(viewport(32, 32, true), Rect::new(-16, 0, 64, 32));
+}
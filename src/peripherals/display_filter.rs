@@ -0,0 +1,122 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::constants::SCREEN_SIZE;
+
+// Brightness below which a fading pixel is turned off, less than one step of a color channel
+const MIN_INTENSITY: f32 = 1.0 / 255.0;
+
+/// Filters reducing the flicker of sprites erased and redrawn with XOR
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum DisplayFilter {
+    /// Every frame is shown as is (default)
+    None,
+
+    /// Pixels fade out over several frames after turning off
+    Phosphor,
+
+    /// Pixels lit in any of the last frames stay lit, older frames dimmer
+    Blend,
+}
+
+/// Turns the frames of the display into pixel brightnesses, on the CPU.
+/// Each call to `advance` is one 60Hz frame, however often the display is drawn.
+pub(crate) struct FrameFilter {
+    filter: DisplayFilter,
+
+    /// Brightness kept from one frame to the next (0 = none, 1 = all)
+    decay: f32,
+
+    /// Number of frames combined by the blend filter
+    blend_frames: usize,
+
+    /// Brightness of every pixel in the last frame, from 0 (background) to 1 (foreground)
+    intensities: [f32; SCREEN_SIZE],
+
+    /// Last frames, the most recent first (blend filter only)
+    history: VecDeque<[bool; SCREEN_SIZE]>,
+
+    /// Last frame given to the filter
+    pixels: [bool; SCREEN_SIZE],
+}
+
+impl FrameFilter {
+    pub(crate) fn new(filter: DisplayFilter, decay: f32, blend_frames: usize) -> Self {
+        FrameFilter {
+            filter,
+            decay,
+            blend_frames: blend_frames.max(1),
+            intensities: [0.0; SCREEN_SIZE],
+            history: VecDeque::new(),
+            pixels: [false; SCREEN_SIZE],
+        }
+    }
+
+    /// Moves on to the next 60Hz frame, fading the pixels turned off by one more step
+    pub(crate) fn advance(&mut self, pixels: &[bool; SCREEN_SIZE]) {
+        self.pixels = *pixels;
+        match self.filter {
+            DisplayFilter::None => {
+                for (intensity, pixel) in self.intensities.iter_mut().zip(pixels) {
+                    *intensity = if *pixel { 1.0 } else { 0.0 };
+                }
+            }
+            DisplayFilter::Phosphor => {
+                for (intensity, pixel) in self.intensities.iter_mut().zip(pixels) {
+                    *intensity = if *pixel { 1.0 } else { *intensity * self.decay };
+                    if *intensity < MIN_INTENSITY {
+                        *intensity = 0.0;
+                    }
+                }
+            }
+            DisplayFilter::Blend => {
+                self.history.push_front(*pixels);
+                self.history.truncate(self.blend_frames);
+                for (index, intensity) in self.intensities.iter_mut().enumerate() {
+                    *intensity = 0.0;
+                    let mut weight = 1.0;
+                    for frame in &self.history {
+                        if frame[index] {
+                            *intensity = weight;
+                            break;
+                        }
+                        weight *= self.decay;
+                    }
+                    if *intensity < MIN_INTENSITY {
+                        *intensity = 0.0;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the brightness of every pixel without moving on to the next frame, so that
+    /// drawing the display again does not speed up the fading. The lit pixels of the
+    /// display are at full brightness, even if they were turned on since the last frame.
+    pub(crate) fn intensities(&self, pixels: &[bool; SCREEN_SIZE]) -> [f32; SCREEN_SIZE] {
+        let mut intensities = self.intensities;
+        for (intensity, pixel) in intensities.iter_mut().zip(pixels) {
+            if *pixel {
+                *intensity = 1.0;
+            } else if self.filter == DisplayFilter::None {
+                *intensity = 0.0;
+            }
+        }
+        intensities
+    }
+
+    /// Returns true while the last frame still shows older ones,
+    /// so the display keeps being redrawn until they fade out
+    pub(crate) fn is_fading(&self) -> bool {
+        self.intensities
+            .iter()
+            .zip(&self.pixels)
+            .any(|(intensity, pixel)| *intensity != if *pixel { 1.0 } else { 0.0 })
+    }
+}
+
+#[cfg(test)]
+#[path = "./display_filter_test.rs"]
+mod display_filter_test;
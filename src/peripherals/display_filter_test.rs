@@ -0,0 +1,84 @@
+use super::*;
+
+fn apply(filter: &mut FrameFilter, pixels: &[bool; SCREEN_SIZE]) -> [f32; SCREEN_SIZE] {
+    filter.advance(pixels);
+    filter.intensities(pixels)
+}
+
+fn frame(lit: &[usize]) -> [bool; SCREEN_SIZE] {
+    let mut pixels = [false; SCREEN_SIZE];
+    for index in lit {
+        pixels[*index] = true;
+    }
+    pixels
+}
+
+#[test]
+fn test_no_filter() {
+    let mut filter = FrameFilter::new(DisplayFilter::None, 0.5, 3);
+    apply(&mut filter, &frame(&[0]));
+    let intensities = apply(&mut filter, &frame(&[1]));
+    assert_eq!(intensities[0], 0.0);
+    assert_eq!(intensities[1], 1.0);
+    assert!(!filter.is_fading());
+}
+
+#[test]
+fn test_phosphor_decay() {
+    let mut filter = FrameFilter::new(DisplayFilter::Phosphor, 0.5, 3);
+    apply(&mut filter, &frame(&[0, 1]));
+
+    let intensities = apply(&mut filter, &frame(&[1]));
+    assert_eq!(intensities[0], 0.5);
+    assert_eq!(intensities[1], 1.0);
+    assert!(filter.is_fading());
+
+    assert_eq!(apply(&mut filter, &frame(&[1]))[0], 0.25);
+
+    // Relighting a fading pixel restores its full brightness
+    assert_eq!(apply(&mut filter, &frame(&[0, 1]))[0], 1.0);
+
+    // Pixels end up turned off, and the display stops fading
+    for _ in 0..8 {
+        apply(&mut filter, &frame(&[]));
+    }
+    assert_eq!(apply(&mut filter, &frame(&[]))[0], 0.0);
+    assert!(!filter.is_fading());
+}
+
+#[test]
+fn test_blend_frames() {
+    let mut filter = FrameFilter::new(DisplayFilter::Blend, 1.0, 3);
+    apply(&mut filter, &frame(&[0]));
+    apply(&mut filter, &frame(&[1]));
+    let intensities = apply(&mut filter, &frame(&[2]));
+    assert_eq!(intensities[0..3], [1.0, 1.0, 1.0]);
+    assert!(filter.is_fading());
+
+    // Frames older than the last 3 are dropped
+    let intensities = apply(&mut filter, &frame(&[2]));
+    assert_eq!(intensities[0..3], [0.0, 1.0, 1.0]);
+    apply(&mut filter, &frame(&[2]));
+    assert!(!filter.is_fading());
+}
+
+#[test]
+fn test_blend_decay() {
+    let mut filter = FrameFilter::new(DisplayFilter::Blend, 0.5, 3);
+    apply(&mut filter, &frame(&[0]));
+    apply(&mut filter, &frame(&[1]));
+    let intensities = apply(&mut filter, &frame(&[0, 2]));
+    assert_eq!(intensities[0..3], [1.0, 0.5, 1.0]);
+}
+
+#[test]
+fn test_redraws_do_not_fade() {
+    let mut filter = FrameFilter::new(DisplayFilter::Phosphor, 0.5, 3);
+    apply(&mut filter, &frame(&[0]));
+    apply(&mut filter, &frame(&[]));
+    for _ in 0..3 {
+        assert_eq!(filter.intensities(&frame(&[]))[0], 0.5);
+    }
+    // Pixels lit since the last frame are shown at once
+    assert_eq!(filter.intensities(&frame(&[1]))[1], 1.0);
+}
@@ -0,0 +1,72 @@
+use crate::constants::SCREEN_SIZE;
+
+use super::audio_driver::AudioEvent;
+use super::debugger_window::DebuggerEvent;
+use super::display_driver::DisplayEvent;
+use super::input_driver::KeyEvent;
+use super::palette::Palette;
+
+//  The processor run loop only talks to the frontend through these traits,
+//  implemented by the SDL drivers and by the terminal frontend.
+
+/// Shows the display and the messages of the emulator
+pub(crate) trait DisplayFrontend {
+    /// Moves the effects lasting over several frames, like phosphor fading, one emulated frame on
+    fn advance_frame(&mut self, buffer: &[bool; SCREEN_SIZE]);
+
+    fn draw(&mut self, buffer: &[bool; SCREEN_SIZE]);
+
+    /// Returns true if the display must be drawn again even though its pixels did not change
+    fn needs_redraw(&self) -> bool;
+
+    fn handle_event(&mut self, event: DisplayEvent);
+
+    /// Draws the Chip8 keypad, highlighting the key being rebound
+    fn draw_keypad(&mut self, highlighted_key: usize);
+
+    /// Writes the display pixels to a PNG file, in the current palette
+    fn save_screenshot(&mut self, pixels: &[bool]) -> Result<(), String>;
+
+    /// Shows a message for a few seconds
+    fn show_message(&mut self, message: String);
+
+    /// Shows or hides the pause banner
+    fn set_paused(&mut self, paused: bool);
+
+    /// Counts an emulated frame and its instructions for the FPS and IPS counter
+    fn count_frame(&mut self, instructions: usize);
+
+    /// Colours the display is currently drawn in
+    fn palette(&self) -> &Palette;
+}
+
+/// Reads the keypad and the hotkeys
+pub(crate) trait InputFrontend {
+    /// Returns the Chip8 keys pressed and released since the last poll, or an error on quit
+    fn poll(&mut self) -> Result<Vec<KeyEvent>, ()>;
+
+    /// Returns the display changes received since the last call
+    fn take_display_events(&mut self) -> Vec<DisplayEvent>;
+
+    /// Returns the sound changes received since the last call
+    fn take_audio_events(&mut self) -> Vec<AudioEvent>;
+
+    /// Returns the debugger window changes received since the last call
+    fn take_debugger_events(&mut self) -> Vec<DebuggerEvent> {
+        Vec::new()
+    }
+
+    /// Returns the Chip8 key being rebound, while the rebinding screen is open
+    fn rebinding_key(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Plays the beeper
+pub(crate) trait AudioFrontend {
+    /// Starts or stops the beep, called once per frame
+    fn set_beeping(&mut self, beeping: bool);
+
+    /// Applies a sound change, returning a message describing the new state
+    fn handle_event(&mut self, event: AudioEvent) -> String;
+}
@@ -1,8 +1,17 @@
-use sdl2;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::controller::GameController;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::mouse::MouseButton;
+use sdl2::GameControllerSubsystem;
 
-//  Keyboard              Chip8
+use super::audio_driver::AudioEvent;
+use super::controller_map::ControllerMap;
+use super::debugger_window::DebuggerEvent;
+use super::display_driver::DisplayEvent;
+use super::frontend::InputFrontend;
+use super::keymap::{Keymap, KEYPAD_LAYOUT};
+
+//  Keyboard (QWERTY)     Chip8
 //  +---+---+---+---+     +---+---+---+---+
 //  | 1 | 2 | 3 | 4 |     | 1 | 2 | 3 | C |
 //  +---+---+---+---+     +---+---+---+---+
@@ -12,65 +21,283 @@ use sdl2::keyboard::Keycode;
 //  +---+---+---+---+     +---+---+---+---+
 //  | Z | X | C | V |     | A | 0 | B | F |
 //  +---+---+---+---+     +---+---+---+---+
+//
+//  Other layouts and custom bindings are provided by the keymap,
+//  game controller buttons by the controller map.
+
+const REBIND_KEY: Keycode = Keycode::F1; // Opens the rebinding screen
+const REBIND_SKIP_KEY: Keycode = Keycode::RETURN; // Keeps the current binding of a key
+const REBIND_CANCEL_KEY: Keycode = Keycode::ESCAPE; // Closes the rebinding screen, discarding changes
+const FULLSCREEN_KEY: Keycode = Keycode::F11; // Switches between fullscreen and windowed mode
+const PALETTE_KEY: Keycode = Keycode::F2; // Switches to the next palette
+const PAUSE_KEY: Keycode = Keycode::F3; // Pauses or resumes the game
+const STATS_KEY: Keycode = Keycode::F4; // Shows or hides the FPS and IPS counter
+const SCREENSHOT_KEY: Keycode = Keycode::F12; // Writes the display to a PNG file
+const RECORD_KEY: Keycode = Keycode::F10; // Starts or stops recording the display
+const MUTE_KEY: Keycode = Keycode::F7; // Mutes or unmutes the beeper
+const VOLUME_DOWN_KEY: Keycode = Keycode::F8; // Lowers the beeper volume
+const VOLUME_UP_KEY: Keycode = Keycode::F9; // Raises the beeper volume
+
+/// Change of state of a Chip8 key (0-F)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum KeyEvent {
+    Pressed(usize),
+    Released(usize),
+}
 
 pub(crate) struct InputDriver {
     events: sdl2::EventPump,
+    keymap: Keymap,
+
+    game_controller_subsystem: GameControllerSubsystem,
+    controller_map: ControllerMap,
+
+    /// Game controllers currently connected, opened and closed as they are plugged in and out
+    controllers: Vec<GameController>,
+
+    /// Keypad state at the last poll, 1 bit per key
+    keypad: u16,
+
+    /// Window changes received since the display driver last took them
+    display_events: Vec<DisplayEvent>,
+
+    /// Sound changes received since the audio driver last took them
+    audio_events: Vec<AudioEvent>,
+
+    /// Window whose clicks and closing go to the debugger, if it is open
+    debugger_window_id: Option<u32>,
+
+    /// Debugger window changes received since the debugger last took them
+    debugger_events: Vec<DebuggerEvent>,
+
+    /// Position in the keypad layout of the key being rebound, while the rebinding screen is open
+    rebinding_index: Option<usize>,
+
+    /// Keymap to restore if the rebinding is cancelled
+    rebinding_backup: Option<Keymap>,
 }
 
 impl InputDriver {
-    pub(crate) fn new(sdl_context: &sdl2::Sdl) -> Self {
+    pub(crate) fn new(
+        sdl_context: &sdl2::Sdl,
+        keymap: Keymap,
+        controller_map: ControllerMap,
+    ) -> Self {
         InputDriver {
             events: sdl_context.event_pump().unwrap(),
+            keymap,
+            game_controller_subsystem: sdl_context.game_controller().unwrap(),
+            controller_map,
+            controllers: Vec::new(),
+            keypad: 0,
+            display_events: Vec::new(),
+            audio_events: Vec::new(),
+            debugger_window_id: None,
+            debugger_events: Vec::new(),
+            rebinding_index: None,
+            rebinding_backup: None,
         }
     }
 
-    pub(crate) fn poll(&mut self) -> Result<u16, ()> {
-        for event in self.events.poll_iter() {
-            if let Event::Quit { .. } = event {
-                return Err(());
-            };
-        }
-
-        let keys: Vec<Keycode> = self
-            .events
-            .keyboard_state()
-            .pressed_scancodes()
-            .filter_map(Keycode::from_scancode)
-            .collect();
+    fn read_keypad(&self) -> u16 {
+        let keyboard_state = self.events.keyboard_state();
 
         // Bit: 15 14 13 12 11 10 9 8 7 6 5 4 3 2 1 0
         // Key:  F  E  D  C  B  A 9 8 7 6 5 4 3 2 1 0
-        let mut chip8_keys: u16 = 0;
-        for key in keys {
-            let index = match key {
-                Keycode::Num1 => Some(0b0000_0000_0000_0010), // Bit  1 active => Key 1 pressed
-                Keycode::Num2 => Some(0b0000_0000_0000_0100), // Bit  2 active => Key 2 pressed
-                Keycode::Num3 => Some(0b0000_0000_0000_1000), // Bit  3 active => Key 3 pressed
-                Keycode::Num4 => Some(0b0001_0000_0000_0000), // Bit 12 active => Key C pressed
-
-                Keycode::Q => Some(0b0000_0000_0001_0000), // Bit  4 active => Key 4 pressed
-                Keycode::W => Some(0b0000_0000_0010_0000), // Bit  5 active => Key 5 pressed
-                Keycode::E => Some(0b0000_0000_0100_0000), // Bit  6 active => Key 6 pressed
-                Keycode::R => Some(0b0010_0000_0000_0000), // Bit 13 active => Key D pressed
-
-                Keycode::A => Some(0b0000_0000_1000_0000), // Bit  7 active => Key 7 pressed
-                Keycode::S => Some(0b0000_0001_0000_0000), // Bit  8 active => Key 8 pressed
-                Keycode::D => Some(0b0000_0010_0000_0000), // Bit  9 active => Key 9 pressed
-                Keycode::F => Some(0b0100_0000_0000_0000), // Bit 14 active => Key E pressed
-
-                Keycode::Z => Some(0b0000_0100_0000_0000), // Bit 10 active => Key A pressed
-                Keycode::X => Some(0b0000_0000_0000_0001), // Bit  0 active => Key 0 pressed
-                Keycode::C => Some(0b0000_1000_0000_0000), // Bit 11 active => Key B pressed
-                Keycode::V => Some(0b1000_0000_0000_0000), // Bit 15 active => Key F pressed
-
-                _ => None,
-            };
-
-            if let Some(i) = index {
-                chip8_keys |= i;
+        let mut chip8_keys = self.keymap.keypad_bits(|keycode| {
+            Scancode::from_keycode(keycode)
+                .is_some_and(|scancode| keyboard_state.is_scancode_pressed(scancode))
+        });
+        for controller in &self.controllers {
+            chip8_keys |= self
+                .controller_map
+                .keypad_bits(|button| controller.button(button));
+        }
+        chip8_keys
+    }
+
+    fn open_controller(&mut self, device_index: u32) {
+        match self.game_controller_subsystem.open(device_index) {
+            Ok(controller) => {
+                log::info!("Game controller connected: {}", controller.name());
+                self.controllers.push(controller);
             }
+            Err(e) => log::error!("Failed to open game controller {}: {}", device_index, e),
         }
+    }
+
+    fn close_controller(&mut self, instance_id: u32) {
+        self.controllers.retain(|controller| {
+            let removed = controller.instance_id() == instance_id;
+            if removed {
+                log::info!("Game controller disconnected: {}", controller.name());
+            }
+            !removed
+        });
+    }
 
-        Ok(chip8_keys)
+    /// Sends the clicks and closing of the given window to the debugger
+    pub(crate) fn set_debugger_window(&mut self, window_id: u32) {
+        self.debugger_window_id = Some(window_id);
+    }
+
+    fn handle_key_down(&mut self, keycode: Keycode) {
+        let Some(index) = self.rebinding_index else {
+            if keycode == REBIND_KEY {
+                log::info!("Rebinding keys: press a key for each Chip8 key, Return to skip, Escape to cancel");
+                self.rebinding_backup = Some(self.keymap.clone());
+                self.rebinding_index = Some(0);
+            }
+            return;
+        };
+
+        match keycode {
+            REBIND_CANCEL_KEY => {
+                if let Some(keymap) = self.rebinding_backup.take() {
+                    self.keymap = keymap;
+                }
+                self.rebinding_index = None;
+                log::info!("Rebinding keys cancelled");
+                return;
+            }
+            REBIND_SKIP_KEY | REBIND_KEY => {}
+            _ => self.keymap.bind(KEYPAD_LAYOUT[index], keycode),
+        }
+
+        if index + 1 < KEYPAD_LAYOUT.len() {
+            self.rebinding_index = Some(index + 1);
+        } else {
+            self.rebinding_index = None;
+            self.rebinding_backup = None;
+            log::info!("Key bindings updated: {:?}", self.keymap.to_names());
+        }
     }
 }
+
+impl InputFrontend for InputDriver {
+    fn poll(&mut self) -> Result<Vec<KeyEvent>, ()> {
+        let events: Vec<Event> = self.events.poll_iter().collect();
+        for event in events {
+            match event {
+                Event::Quit { .. } => return Err(()),
+                Event::KeyDown {
+                    keycode: Some(FULLSCREEN_KEY),
+                    repeat: false,
+                    ..
+                } => self.display_events.push(DisplayEvent::ToggleFullscreen),
+                Event::KeyDown {
+                    keycode: Some(PALETTE_KEY),
+                    repeat: false,
+                    ..
+                } => self.display_events.push(DisplayEvent::CyclePalette),
+                Event::KeyDown {
+                    keycode: Some(PAUSE_KEY),
+                    repeat: false,
+                    ..
+                } => self.display_events.push(DisplayEvent::TogglePause),
+                Event::KeyDown {
+                    keycode: Some(STATS_KEY),
+                    repeat: false,
+                    ..
+                } => self.display_events.push(DisplayEvent::ToggleStats),
+                Event::KeyDown {
+                    keycode: Some(SCREENSHOT_KEY),
+                    repeat: false,
+                    ..
+                } => self.display_events.push(DisplayEvent::Screenshot),
+                Event::KeyDown {
+                    keycode: Some(RECORD_KEY),
+                    repeat: false,
+                    ..
+                } => self.display_events.push(DisplayEvent::ToggleRecording),
+                Event::KeyDown {
+                    keycode: Some(MUTE_KEY),
+                    repeat: false,
+                    ..
+                } => self.audio_events.push(AudioEvent::ToggleMute),
+                Event::KeyDown {
+                    keycode: Some(VOLUME_DOWN_KEY),
+                    ..
+                } => self.audio_events.push(AudioEvent::VolumeDown),
+                Event::KeyDown {
+                    keycode: Some(VOLUME_UP_KEY),
+                    ..
+                } => self.audio_events.push(AudioEvent::VolumeUp),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => self.handle_key_down(keycode),
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..) | WindowEvent::Exposed,
+                    ..
+                } => self.display_events.push(DisplayEvent::Resized),
+                // Closing the game window quits even while the debugger window is open
+                Event::Window {
+                    win_event: WindowEvent::Close,
+                    window_id,
+                    ..
+                } => {
+                    if Some(window_id) != self.debugger_window_id {
+                        return Err(());
+                    }
+                    self.debugger_events.push(DebuggerEvent::Close);
+                }
+                Event::MouseButtonDown {
+                    window_id,
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if Some(window_id) == self.debugger_window_id => {
+                    self.debugger_events.push(DebuggerEvent::Click { x, y })
+                }
+                // Sent for every controller already connected at startup too
+                Event::ControllerDeviceAdded { which, .. } => self.open_controller(which),
+                Event::ControllerDeviceRemoved { which, .. } => self.close_controller(which),
+                _ => {}
+            }
+        }
+
+        // Every key is released while the rebinding screen is open
+        let keypad = if self.rebinding_index.is_some() {
+            0
+        } else {
+            self.read_keypad()
+        };
+        let key_events = key_events(self.keypad, keypad);
+        self.keypad = keypad;
+        Ok(key_events)
+    }
+
+    fn take_display_events(&mut self) -> Vec<DisplayEvent> {
+        std::mem::take(&mut self.display_events)
+    }
+
+    fn take_audio_events(&mut self) -> Vec<AudioEvent> {
+        std::mem::take(&mut self.audio_events)
+    }
+
+    fn take_debugger_events(&mut self) -> Vec<DebuggerEvent> {
+        std::mem::take(&mut self.debugger_events)
+    }
+
+    fn rebinding_key(&self) -> Option<usize> {
+        self.rebinding_index.map(|index| KEYPAD_LAYOUT[index])
+    }
+}
+
+/// Returns the events turning the `previous` keypad state into the `current` one
+pub(crate) fn key_events(previous: u16, current: u16) -> Vec<KeyEvent> {
+    (0..16)
+        .filter_map(|key| match ((previous >> key) & 1, (current >> key) & 1) {
+            (0, 1) => Some(KeyEvent::Pressed(key)),
+            (1, 0) => Some(KeyEvent::Released(key)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "./input_driver_test.rs"]
+mod input_driver_test;
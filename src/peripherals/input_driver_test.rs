@@ -0,0 +1,94 @@
+use super::*;
+use sdl2::controller::Button;
+use sdl2::sys;
+use std::collections::BTreeMap;
+
+use crate::peripherals::keymap::KeymapPreset;
+
+// Attaches an SDL virtual game controller, so no hardware is needed
+struct VirtualController {
+    device_index: i32,
+    joystick: *mut sys::SDL_Joystick,
+}
+
+impl VirtualController {
+    fn attach() -> Self {
+        unsafe {
+            let device_index = sys::SDL_JoystickAttachVirtual(
+                sys::SDL_JoystickType::SDL_JOYSTICK_TYPE_GAMECONTROLLER,
+                sys::SDL_GameControllerAxis::SDL_CONTROLLER_AXIS_MAX as i32,
+                sys::SDL_GameControllerButton::SDL_CONTROLLER_BUTTON_MAX as i32,
+                0,
+            );
+            assert!(device_index >= 0, "failed to attach virtual controller");
+            let joystick = sys::SDL_JoystickOpen(device_index);
+            assert!(!joystick.is_null(), "failed to open virtual controller");
+            VirtualController {
+                device_index,
+                joystick,
+            }
+        }
+    }
+
+    fn set_button(&self, button: Button, pressed: bool) {
+        unsafe {
+            sys::SDL_JoystickSetVirtualButton(self.joystick, button as i32, pressed as u8);
+        }
+    }
+
+    fn detach(self) {
+        unsafe {
+            sys::SDL_JoystickClose(self.joystick);
+            sys::SDL_JoystickDetachVirtual(self.device_index);
+        }
+    }
+}
+
+// SDL can only be initialized from one thread, so every SDL check lives in this test
+#[test]
+fn test_virtual_game_controller() {
+    let sdl_context = sdl2::init().unwrap();
+    let keymap = Keymap::new(KeymapPreset::Qwerty, &BTreeMap::new()).unwrap();
+    let overrides = BTreeMap::from([
+        ("a".to_string(), "C".to_string()),
+        ("b".to_string(), "none".to_string()),
+    ]);
+    let controller_map = ControllerMap::new(&overrides).unwrap();
+    let mut input_driver = InputDriver::new(&sdl_context, keymap, controller_map);
+
+    // Hot-plug: the controller is opened on the next poll
+    let controller = VirtualController::attach();
+    assert_eq!(input_driver.poll(), Ok(vec![]));
+
+    controller.set_button(Button::DPadUp, true);
+    assert_eq!(input_driver.poll(), Ok(vec![KeyEvent::Pressed(0x2)]));
+
+    // Held buttons are only reported once
+    controller.set_button(Button::A, true);
+    assert_eq!(input_driver.poll(), Ok(vec![KeyEvent::Pressed(0xC)]));
+
+    controller.set_button(Button::DPadUp, false);
+    controller.set_button(Button::A, false);
+    controller.set_button(Button::B, true);
+    assert_eq!(
+        input_driver.poll(),
+        Ok(vec![KeyEvent::Released(0x2), KeyEvent::Released(0xC)])
+    );
+
+    // Unplugging closes the controller, its buttons are released
+    controller.set_button(Button::Start, true);
+    assert_eq!(input_driver.poll(), Ok(vec![KeyEvent::Pressed(0xF)]));
+    controller.detach();
+    assert_eq!(input_driver.poll(), Ok(vec![KeyEvent::Released(0xF)]));
+}
+
+#[test]
+fn test_key_events() {
+    assert_eq!(key_events(0, 0), vec![]);
+    assert_eq!(key_events(0b0110, 0b0110), vec![]);
+    assert_eq!(
+        key_events(0b0110, 0b1010),
+        vec![KeyEvent::Released(0x2), KeyEvent::Pressed(0x3)]
+    );
+    assert_eq!(key_events(1 << 0xF, 0), vec![KeyEvent::Released(0xF)]);
+}
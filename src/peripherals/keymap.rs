@@ -0,0 +1,156 @@
+use clap::ValueEnum;
+use sdl2::keyboard::Keycode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+//  Chip8 keypad layout, the order used by the presets below
+//  +---+---+---+---+
+//  | 1 | 2 | 3 | C |
+//  +---+---+---+---+
+//  | 4 | 5 | 6 | D |
+//  +---+---+---+---+
+//  | 7 | 8 | 9 | E |
+//  +---+---+---+---+
+//  | A | 0 | B | F |
+//  +---+---+---+---+
+pub(crate) const KEYPAD_LAYOUT: [usize; 16] = [
+    0x1, 0x2, 0x3, 0xC, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x7, 0x8, 0x9, 0xE, //
+    0xA, 0x0, 0xB, 0xF, //
+];
+
+// Host keys in the same physical position as the keypad on each layout
+const QWERTY_KEYS: [&str; 16] = [
+    "1", "2", "3", "4", //
+    "Q", "W", "E", "R", //
+    "A", "S", "D", "F", //
+    "Z", "X", "C", "V", //
+];
+const AZERTY_KEYS: [&str; 16] = [
+    "&", "é", "\"", "'", //
+    "A", "Z", "E", "R", //
+    "Q", "S", "D", "F", //
+    "W", "X", "C", "V", //
+];
+const DVORAK_KEYS: [&str; 16] = [
+    "1", "2", "3", "4", //
+    "'", ",", ".", "P", //
+    "A", "O", "E", "U", //
+    ";", "Q", "J", "K", //
+];
+
+// Extra bindings of the arrows preset: directions on 2/4/6/8, action on 5
+const ARROW_KEYS: [(usize, &str); 5] = [
+    (0x2, "Up"),
+    (0x4, "Left"),
+    (0x6, "Right"),
+    (0x8, "Down"),
+    (0x5, "Space"),
+];
+
+/// Built-in keyboard layouts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum KeymapPreset {
+    /// 1234/QWER/ASDF/ZXCV on a QWERTY keyboard (default)
+    Qwerty,
+
+    /// Same key positions on an AZERTY keyboard
+    Azerty,
+
+    /// Same key positions on a Dvorak keyboard
+    Dvorak,
+
+    /// QWERTY, plus arrow keys on 2/4/6/8 and space on 5
+    Arrows,
+}
+
+/// Maps host keys to Chip8 keys, each Chip8 key can be bound to several host keys
+#[derive(Clone)]
+pub(crate) struct Keymap {
+    bindings: [Vec<Keycode>; 16],
+}
+
+impl Keymap {
+    /// Builds the keymap of a preset, replacing the keys found in `overrides`
+    /// (Chip8 key as a hex digit => host key names)
+    pub(crate) fn new(
+        preset: KeymapPreset,
+        overrides: &BTreeMap<String, Vec<String>>,
+    ) -> Result<Self, String> {
+        let mut keymap = Keymap {
+            bindings: Default::default(),
+        };
+
+        let layout_keys = match preset {
+            KeymapPreset::Qwerty | KeymapPreset::Arrows => QWERTY_KEYS,
+            KeymapPreset::Azerty => AZERTY_KEYS,
+            KeymapPreset::Dvorak => DVORAK_KEYS,
+        };
+        for (key, name) in KEYPAD_LAYOUT.iter().zip(layout_keys) {
+            keymap.bindings[*key].push(parse_host_key(name)?);
+        }
+        if preset == KeymapPreset::Arrows {
+            for (key, name) in ARROW_KEYS {
+                keymap.bindings[key].push(parse_host_key(name)?);
+            }
+        }
+
+        for (key, names) in overrides {
+            let key = parse_keypad_key(key)?;
+            keymap.bindings[key] = names
+                .iter()
+                .map(|name| parse_host_key(name))
+                .collect::<Result<_, _>>()?;
+        }
+
+        Ok(keymap)
+    }
+
+    /// Returns the keypad bits of the Chip8 keys with at least one host key pressed
+    pub(crate) fn keypad_bits(&self, is_pressed: impl Fn(Keycode) -> bool) -> u16 {
+        self.bindings
+            .iter()
+            .enumerate()
+            .filter(|(_, keycodes)| keycodes.iter().any(|keycode| is_pressed(*keycode)))
+            .fold(0, |bits, (key, _)| bits | (1 << key))
+    }
+
+    /// Binds a Chip8 key to a single host key, unbinding that host key from any other Chip8 key
+    pub(crate) fn bind(&mut self, key: usize, keycode: Keycode) {
+        for keycodes in self.bindings.iter_mut() {
+            keycodes.retain(|bound| *bound != keycode);
+        }
+        self.bindings[key] = vec![keycode];
+    }
+
+    /// Returns the bindings in the format used by the configuration file
+    pub(crate) fn to_names(&self) -> BTreeMap<String, Vec<String>> {
+        self.bindings
+            .iter()
+            .enumerate()
+            .map(|(key, keycodes)| {
+                let names = keycodes.iter().map(|keycode| keycode.name()).collect();
+                (format!("{:X}", key), names)
+            })
+            .collect()
+    }
+}
+
+/// Parses a Chip8 key written as a single hex digit (0-F)
+pub(crate) fn parse_keypad_key(value: &str) -> Result<usize, String> {
+    match usize::from_str_radix(value, 16) {
+        Ok(key) if value.len() == 1 => Ok(key),
+        _ => Err(format!("invalid Chip8 key '{}', expected 0-F", value)),
+    }
+}
+
+/// Parses a host key by its SDL name (e.g. "Q", "Space", "Left", "Keypad 5")
+pub(crate) fn parse_host_key(name: &str) -> Result<Keycode, String> {
+    Keycode::from_name(name).ok_or_else(|| format!("unknown host key '{}'", name))
+}
+
+#[cfg(test)]
+#[path = "./keymap_test.rs"]
+mod keymap_test;
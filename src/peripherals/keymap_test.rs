@@ -0,0 +1,71 @@
+use super::*;
+
+fn key(name: &str) -> Keycode {
+    Keycode::from_name(name).unwrap()
+}
+
+fn bits(keymap: &Keymap, name: &str) -> u16 {
+    let pressed = key(name);
+    keymap.keypad_bits(|keycode| keycode == pressed)
+}
+
+#[test]
+fn test_qwerty_preset() {
+    let keymap = Keymap::new(KeymapPreset::Qwerty, &BTreeMap::new()).unwrap();
+    assert_eq!(bits(&keymap, "1"), 1 << 0x1);
+    assert_eq!(bits(&keymap, "4"), 1 << 0xC);
+    assert_eq!(bits(&keymap, "X"), 1 << 0x0);
+    assert_eq!(bits(&keymap, "V"), 1 << 0xF);
+    assert_eq!(bits(&keymap, "Up"), 0);
+}
+
+#[test]
+fn test_azerty_preset() {
+    let keymap = Keymap::new(KeymapPreset::Azerty, &BTreeMap::new()).unwrap();
+    assert_eq!(bits(&keymap, "&"), 1 << 0x1);
+    assert_eq!(bits(&keymap, "Z"), 1 << 0x5);
+    assert_eq!(bits(&keymap, "Q"), 1 << 0x7);
+    assert_eq!(bits(&keymap, "W"), 1 << 0xA);
+}
+
+#[test]
+fn test_dvorak_preset() {
+    let keymap = Keymap::new(KeymapPreset::Dvorak, &BTreeMap::new()).unwrap();
+    assert_eq!(bits(&keymap, ","), 1 << 0x5);
+    assert_eq!(bits(&keymap, "O"), 1 << 0x8);
+    assert_eq!(bits(&keymap, "K"), 1 << 0xF);
+}
+
+#[test]
+fn test_arrows_preset_binds_several_host_keys() {
+    let keymap = Keymap::new(KeymapPreset::Arrows, &BTreeMap::new()).unwrap();
+    assert_eq!(bits(&keymap, "W"), 1 << 0x5);
+    assert_eq!(bits(&keymap, "Space"), 1 << 0x5);
+    assert_eq!(bits(&keymap, "Left"), 1 << 0x4);
+    assert_eq!(bits(&keymap, "Down"), 1 << 0x8);
+}
+
+#[test]
+fn test_overrides_replace_preset_keys() {
+    let overrides = BTreeMap::from([("5".to_string(), vec!["Space".to_string(), "K".to_string()])]);
+    let keymap = Keymap::new(KeymapPreset::Qwerty, &overrides).unwrap();
+    assert_eq!(bits(&keymap, "W"), 0);
+    assert_eq!(bits(&keymap, "Space"), 1 << 0x5);
+    assert_eq!(bits(&keymap, "K"), 1 << 0x5);
+}
+
+#[test]
+fn test_invalid_overrides() {
+    let overrides = BTreeMap::from([("G".to_string(), vec!["Space".to_string()])]);
+    assert!(Keymap::new(KeymapPreset::Qwerty, &overrides).is_err());
+    let overrides = BTreeMap::from([("5".to_string(), vec!["NoSuchKey".to_string()])]);
+    assert!(Keymap::new(KeymapPreset::Qwerty, &overrides).is_err());
+}
+
+#[test]
+fn test_bind_moves_host_key() {
+    let mut keymap = Keymap::new(KeymapPreset::Qwerty, &BTreeMap::new()).unwrap();
+    keymap.bind(0x0, key("Q"));
+    assert_eq!(bits(&keymap, "Q"), 1 << 0x0);
+    assert_eq!(bits(&keymap, "X"), 0);
+}
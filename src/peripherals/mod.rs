@@ -1,4 +1,16 @@
 pub(crate) mod audio_driver;
 pub(crate) mod cartridge_driver;
+pub(crate) mod controller_map;
+pub(crate) mod debugger_window;
 pub(crate) mod display_driver;
+pub(crate) mod display_filter;
+pub(crate) mod frontend;
 pub(crate) mod input_driver;
+pub(crate) mod keymap;
+pub(crate) mod osd;
+pub(crate) mod osd_font;
+pub(crate) mod palette;
+pub(crate) mod post_processing;
+pub(crate) mod rom_loader;
+pub(crate) mod terminal_driver;
+pub(crate) mod tone;
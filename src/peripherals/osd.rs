@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::osd_font::{text_width, GLYPH_HEIGHT};
+
+const MESSAGE_DURATION: Duration = Duration::from_secs(3); // Time a message stays on screen
+const MAX_MESSAGES: usize = 4; // Messages shown at once, the oldest ones are dropped
+const STATS_PERIOD: Duration = Duration::from_secs(1); // Time the FPS and IPS are averaged over
+
+const MARGIN: usize = 2; // Distance of the text from the edges of the window, in font pixels
+const LINE_HEIGHT: usize = GLYPH_HEIGHT + 2;
+
+const PAUSE_BANNER: &str = "PAUSED";
+
+/// Line of text of the OSD, at a position in font pixels
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct OsdText {
+    pub(crate) x: usize,
+    pub(crate) y: usize,
+    pub(crate) text: String,
+}
+
+/// On-screen display drawn over the game: transient messages at the bottom left,
+/// the frame and instruction rates at the top right, and a banner while paused
+pub(crate) struct Osd {
+    /// Messages and the time they were shown at, oldest first
+    messages: VecDeque<(String, Instant)>,
+
+    show_stats: bool,
+    paused: bool,
+
+    /// Rates of the last complete period, and the counts of the current one
+    stats: Option<String>,
+    frames: usize,
+    instructions: usize,
+    period_start: Instant,
+}
+
+impl Osd {
+    pub(crate) fn new(show_stats: bool, now: Instant) -> Self {
+        Osd {
+            messages: VecDeque::new(),
+            show_stats,
+            paused: false,
+            stats: None,
+            frames: 0,
+            instructions: 0,
+            period_start: now,
+        }
+    }
+
+    pub(crate) fn show_message(&mut self, message: String, now: Instant) {
+        self.messages.push_back((message, now));
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+    }
+
+    pub(crate) fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub(crate) fn toggle_stats(&mut self) {
+        self.show_stats = !self.show_stats;
+    }
+
+    /// Counts an emulated frame and its instructions towards the displayed rates
+    pub(crate) fn count_frame(&mut self, instructions: usize, now: Instant) {
+        self.frames += 1;
+        self.instructions += instructions;
+
+        let elapsed = now.duration_since(self.period_start);
+        if elapsed >= STATS_PERIOD {
+            let seconds = elapsed.as_secs_f64();
+            self.stats = Some(format!(
+                "{:.0} FPS {:.0} IPS",
+                self.frames as f64 / seconds,
+                self.instructions as f64 / seconds
+            ));
+            self.frames = 0;
+            self.instructions = 0;
+            self.period_start = now;
+        }
+    }
+
+    /// Returns true if anything is shown at the given time
+    pub(crate) fn is_visible(&self, now: Instant) -> bool {
+        self.paused
+            || (self.show_stats && self.stats.is_some())
+            || self.messages.iter().any(|(_, shown)| is_shown(*shown, now))
+    }
+
+    /// Returns the lines of text to draw on a window of the given size in font pixels,
+    /// forgetting the expired messages
+    pub(crate) fn layout(&mut self, width: usize, height: usize, now: Instant) -> Vec<OsdText> {
+        self.messages.retain(|(_, shown)| is_shown(*shown, now));
+
+        let mut texts = Vec::new();
+        if let Some(stats) = self.stats.as_ref().filter(|_| self.show_stats) {
+            texts.push(OsdText {
+                x: width.saturating_sub(MARGIN + text_width(stats)),
+                y: MARGIN,
+                text: stats.clone(),
+            });
+        }
+        if self.paused {
+            texts.push(OsdText {
+                x: width.saturating_sub(text_width(PAUSE_BANNER)) / 2,
+                y: height.saturating_sub(GLYPH_HEIGHT) / 2,
+                text: PAUSE_BANNER.to_string(),
+            });
+        }
+        let bottom = height.saturating_sub(MARGIN + GLYPH_HEIGHT);
+        for (line, (message, _)) in self.messages.iter().rev().enumerate() {
+            texts.push(OsdText {
+                x: MARGIN,
+                y: bottom.saturating_sub(line * LINE_HEIGHT),
+                text: message.clone(),
+            });
+        }
+        texts
+    }
+}
+
+fn is_shown(shown: Instant, now: Instant) -> bool {
+    now.duration_since(shown) < MESSAGE_DURATION
+}
+
+#[cfg(test)]
+#[path = "./osd_test.rs"]
+mod osd_test;
@@ -0,0 +1,71 @@
+use super::*;
+
+// Window of 200x100 font pixels
+const WIDTH: usize = 200;
+const HEIGHT: usize = 100;
+
+fn texts(osd: &mut Osd, now: Instant) -> Vec<(usize, usize, String)> {
+    osd.layout(WIDTH, HEIGHT, now)
+        .into_iter()
+        .map(|text| (text.x, text.y, text.text))
+        .collect()
+}
+
+#[test]
+fn test_messages_stack_up_and_expire() {
+    let start = Instant::now();
+    let mut osd = Osd::new(false, start);
+    assert!(!osd.is_visible(start));
+
+    osd.show_message("Palette: Amber".to_string(), start);
+    osd.show_message("Volume: 30%".to_string(), start + Duration::from_secs(2));
+    assert_eq!(
+        texts(&mut osd, start + Duration::from_secs(2)),
+        [
+            (2, 90, "Volume: 30%".to_string()),
+            (2, 80, "Palette: Amber".to_string())
+        ]
+    );
+
+    let later = start + MESSAGE_DURATION;
+    assert!(osd.is_visible(later));
+    assert_eq!(texts(&mut osd, later), [(2, 90, "Volume: 30%".to_string())]);
+    assert!(!osd.is_visible(later + Duration::from_secs(2)));
+}
+
+#[test]
+fn test_oldest_messages_are_dropped() {
+    let now = Instant::now();
+    let mut osd = Osd::new(false, now);
+    for index in 0..MAX_MESSAGES + 2 {
+        osd.show_message(index.to_string(), now);
+    }
+    let messages: Vec<String> = texts(&mut osd, now)
+        .into_iter()
+        .map(|text| text.2)
+        .collect();
+    assert_eq!(messages, ["5", "4", "3", "2"]);
+}
+
+#[test]
+fn test_stats_and_pause_banner() {
+    let start = Instant::now();
+    let mut osd = Osd::new(true, start);
+    for frame in 1..=60 {
+        osd.count_frame(15, start + STATS_PERIOD * frame / 60);
+    }
+    osd.set_paused(true);
+
+    // "60 FPS 900 IPS" is 14 characters, 83 font pixels wide
+    assert_eq!(
+        texts(&mut osd, start + STATS_PERIOD),
+        [
+            (115, 2, "60 FPS 900 IPS".to_string()),
+            (82, 46, "PAUSED".to_string())
+        ]
+    );
+
+    osd.toggle_stats();
+    osd.set_paused(false);
+    assert!(!osd.is_visible(start + STATS_PERIOD));
+}
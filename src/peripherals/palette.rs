@@ -0,0 +1,102 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{BACKGROUND_COLOR, FOREGROUND_COLOR};
+
+// Number of colours of a palette, one per combination of the two display planes
+pub(crate) const PALETTE_SIZE: usize = 4;
+
+/// Built-in colour palettes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PalettePreset {
+    /// Classic green on black (default)
+    Green,
+
+    /// Amber monochrome monitor
+    Amber,
+
+    /// White on black
+    White,
+
+    /// Original Game Boy greens
+    Gameboy,
+
+    /// Yellow on black, with the brightest colours for the extra planes
+    Contrast,
+
+    /// Default colours of the Octo IDE
+    Octo,
+}
+
+/// Colours of the display, indexed by the planes a pixel is lit on:
+/// background, plane 1 (foreground), plane 2, both planes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Palette {
+    pub(crate) colors: [[u8; 3]; PALETTE_SIZE],
+}
+
+impl Palette {
+    pub(crate) fn background(&self) -> [u8; 3] {
+        self.colors[0]
+    }
+
+    pub(crate) fn foreground(&self) -> [u8; 3] {
+        self.colors[1]
+    }
+}
+
+impl PalettePreset {
+    pub(crate) fn palette(self) -> Palette {
+        let colors = match self {
+            PalettePreset::Green => [
+                BACKGROUND_COLOR,
+                FOREGROUND_COLOR,
+                [0x1F, 0x75, 0x4E],
+                [0xA0, 0xF6, 0xCE],
+            ],
+            PalettePreset::Amber => [
+                [0x1A, 0x12, 0x00],
+                [0xFF, 0xB0, 0x00],
+                [0x80, 0x58, 0x00],
+                [0xFF, 0xD8, 0x80],
+            ],
+            PalettePreset::White => [
+                [0x00, 0x00, 0x00],
+                [0xFF, 0xFF, 0xFF],
+                [0x55, 0x55, 0x55],
+                [0xAA, 0xAA, 0xAA],
+            ],
+            PalettePreset::Gameboy => [
+                [0x9B, 0xBC, 0x0F],
+                [0x0F, 0x38, 0x0F],
+                [0x8B, 0xAC, 0x0F],
+                [0x30, 0x62, 0x30],
+            ],
+            PalettePreset::Contrast => [
+                [0x00, 0x00, 0x00],
+                [0xFF, 0xFF, 0x00],
+                [0x00, 0xFF, 0xFF],
+                [0xFF, 0x00, 0xFF],
+            ],
+            PalettePreset::Octo => [
+                [0x99, 0x66, 0x00],
+                [0xFF, 0xCC, 0x00],
+                [0xFF, 0x66, 0x00],
+                [0x66, 0x22, 0x00],
+            ],
+        };
+        Palette { colors }
+    }
+
+    /// Returns the preset after this one, back to the first after the last
+    pub(crate) fn next(self) -> Self {
+        let presets = Self::value_variants();
+        let index = presets.iter().position(|preset| *preset == self).unwrap();
+        presets[(index + 1) % presets.len()]
+    }
+}
+
+#[cfg(test)]
+#[path = "./palette_test.rs"]
+mod palette_test;
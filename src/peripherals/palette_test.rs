@@ -0,0 +1,20 @@
+use super::*;
+
+#[test]
+fn test_default_palette_uses_constants() {
+    let palette = PalettePreset::Green.palette();
+    assert_eq!(palette.background(), BACKGROUND_COLOR);
+    assert_eq!(palette.foreground(), FOREGROUND_COLOR);
+}
+
+#[test]
+fn test_cycle_every_preset() {
+    let mut preset = PalettePreset::Green;
+    let mut seen = Vec::new();
+    for _ in 0..PalettePreset::value_variants().len() {
+        seen.push(preset);
+        preset = preset.next();
+    }
+    assert_eq!(preset, PalettePreset::Green);
+    assert_eq!(seen, PalettePreset::value_variants());
+}
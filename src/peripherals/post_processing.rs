@@ -0,0 +1,227 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// Bytes per pixel of the RGB24 images
+const BYTES_PER_PIXEL: usize = 3;
+
+const SCANLINE_BRIGHTNESS: f32 = 0.5; // Brightness of the dark lines between pixel rows
+const GRID_BRIGHTNESS: f32 = 0.7; // Brightness of the outline of every pixel
+const GLOW_STRENGTH: f32 = 0.6; // Share of the light of lit neighbours added around them
+const CURVATURE: f32 = 0.08; // Barrel distortion at the corners of the screen
+const VIGNETTE: f32 = 0.2; // Brightness lost at the corners of the screen
+
+/// Looks applied to the upscaled display, on the CPU
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PostEffect {
+    /// Light bleeding around lit pixels
+    Glow,
+
+    /// Dark lines between pixel rows, as on a CRT
+    Scanlines,
+
+    /// Dark outline around every pixel, as on an LCD
+    Grid,
+
+    /// Curved screen with darker corners
+    Crt,
+}
+
+/// RGB24 image
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Image {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![0; width * height * BYTES_PER_PIXEL],
+        }
+    }
+
+    fn offset(&self, x: usize, y: usize) -> usize {
+        (x + y * self.width) * BYTES_PER_PIXEL
+    }
+}
+
+/// Upscales the 64x32 display and applies the effects, always in the order
+/// glow, scanlines, grid, crt, whatever the order they were selected in
+pub(crate) struct PostProcessor {
+    effects: Vec<PostEffect>,
+
+    /// Size of a display pixel in the processed image
+    scale: usize,
+
+    image: Image,
+    scratch: Image,
+}
+
+impl PostProcessor {
+    pub(crate) fn new(effects: &[PostEffect], scale: usize) -> Self {
+        let mut effects = effects.to_vec();
+        effects.sort();
+        effects.dedup();
+
+        let scale = scale.max(1);
+        let image = Image::new(SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale);
+        PostProcessor {
+            effects,
+            scale,
+            scratch: image.clone(),
+            image,
+        }
+    }
+
+    /// Returns the processed image of a 64x32 RGB24 frame
+    pub(crate) fn process(&mut self, frame: &[u8]) -> &Image {
+        self.upscale(frame);
+        for effect in self.effects.clone() {
+            match effect {
+                PostEffect::Glow => self.glow(),
+                PostEffect::Scanlines => self.scanlines(),
+                PostEffect::Grid => self.grid(),
+                PostEffect::Crt => self.crt(),
+            }
+        }
+        &self.image
+    }
+
+    fn upscale(&mut self, frame: &[u8]) {
+        for y in 0..self.image.height {
+            for x in 0..self.image.width {
+                let source = (x / self.scale + (y / self.scale) * SCREEN_WIDTH) * BYTES_PER_PIXEL;
+                let offset = self.image.offset(x, y);
+                self.image.pixels[offset..offset + BYTES_PER_PIXEL]
+                    .copy_from_slice(&frame[source..source + BYTES_PER_PIXEL]);
+            }
+        }
+    }
+
+    // Brightens every pixel towards the average of its neighbourhood, when brighter
+    fn glow(&mut self) {
+        box_blur(&self.image, &mut self.scratch, self.scale);
+        for (channel, blurred) in self.image.pixels.iter_mut().zip(&self.scratch.pixels) {
+            if blurred > channel {
+                let added = (*blurred - *channel) as f32 * GLOW_STRENGTH;
+                *channel = (*channel as f32 + added).round().min(255.0) as u8;
+            }
+        }
+    }
+
+    // Darkens the bottom third of every pixel row
+    fn scanlines(&mut self) {
+        if self.scale < 2 {
+            return;
+        }
+        let lines = (self.scale / 3).max(1);
+        for y in (0..self.image.height).filter(|y| y % self.scale >= self.scale - lines) {
+            let start = self.image.offset(0, y);
+            let end = self.image.offset(0, y + 1);
+            darken(&mut self.image.pixels[start..end], SCANLINE_BRIGHTNESS);
+        }
+    }
+
+    // Darkens the last row and column of every pixel
+    fn grid(&mut self) {
+        if self.scale < 2 {
+            return;
+        }
+        for y in 0..self.image.height {
+            for x in 0..self.image.width {
+                if x % self.scale == self.scale - 1 || y % self.scale == self.scale - 1 {
+                    let offset = self.image.offset(x, y);
+                    darken(
+                        &mut self.image.pixels[offset..offset + BYTES_PER_PIXEL],
+                        GRID_BRIGHTNESS,
+                    );
+                }
+            }
+        }
+    }
+
+    // Bends the image as a curved screen, the corners turning black, and darkens the edges
+    fn crt(&mut self) {
+        let (width, height) = (self.image.width as f32, self.image.height as f32);
+        for y in 0..self.image.height {
+            for x in 0..self.image.width {
+                // Position from the center, -1 to 1 on both axes
+                let u = (x as f32 + 0.5) / width * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / height * 2.0 - 1.0;
+                let distance = u * u + v * v;
+
+                let bend = 1.0 + CURVATURE * distance;
+                let source_x = ((u * bend + 1.0) / 2.0 * width).floor();
+                let source_y = ((v * bend + 1.0) / 2.0 * height).floor();
+
+                let offset = self.scratch.offset(x, y);
+                let target = &mut self.scratch.pixels[offset..offset + BYTES_PER_PIXEL];
+                if source_x < 0.0 || source_y < 0.0 || source_x >= width || source_y >= height {
+                    target.fill(0);
+                } else {
+                    let source = self.image.offset(source_x as usize, source_y as usize);
+                    target.copy_from_slice(&self.image.pixels[source..source + BYTES_PER_PIXEL]);
+                    darken(target, 1.0 - VIGNETTE * distance / 2.0);
+                }
+            }
+        }
+        std::mem::swap(&mut self.image, &mut self.scratch);
+    }
+}
+
+fn darken(channels: &mut [u8], brightness: f32) {
+    for channel in channels {
+        *channel = (*channel as f32 * brightness).round() as u8;
+    }
+}
+
+// Averages every channel over a square of the given radius, clamped to the image,
+// as a horizontal then a vertical pass with running sums
+fn box_blur(image: &Image, blurred: &mut Image, radius: usize) {
+    let mut horizontal = vec![0u32; image.pixels.len()];
+    for y in 0..image.height {
+        for channel in 0..BYTES_PER_PIXEL {
+            let value = |x: usize| image.pixels[image.offset(x, y) + channel] as u32;
+            blur_line(image.width, radius, value, |x, average| {
+                horizontal[image.offset(x, y) + channel] = average
+            });
+        }
+    }
+    for x in 0..image.width {
+        for channel in 0..BYTES_PER_PIXEL {
+            let value = |y: usize| horizontal[image.offset(x, y) + channel];
+            blur_line(image.height, radius, value, |y, average| {
+                blurred.pixels[image.offset(x, y) + channel] = average as u8
+            });
+        }
+    }
+}
+
+fn blur_line(
+    length: usize,
+    radius: usize,
+    value: impl Fn(usize) -> u32,
+    mut store: impl FnMut(usize, u32),
+) {
+    let mut sum: u32 = (0..radius.min(length)).map(&value).sum();
+    for position in 0..length {
+        if position + radius < length {
+            sum += value(position + radius);
+        }
+        if position > radius {
+            sum -= value(position - radius - 1);
+        }
+        let count = (position + radius).min(length - 1) + 1 - position.saturating_sub(radius);
+        store(position, (sum + count as u32 / 2) / count as u32);
+    }
+}
+
+#[cfg(test)]
+#[path = "./post_processing_test.rs"]
+mod post_processing_test;
@@ -0,0 +1,116 @@
+use super::*;
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use crate::constants::{BACKGROUND_COLOR, FOREGROUND_COLOR};
+use crate::motherboard::memory::FONT_SPRITES;
+use crate::toolchain::screenshot::write_png;
+
+// Size of a display pixel in the snapshots
+const SCALE: usize = 4;
+
+// 64x32 frame with the 16 font glyphs on two rows inside a border
+fn test_frame() -> Vec<u8> {
+    let mut lit = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+    for x in 0..SCREEN_WIDTH {
+        lit[x] = true;
+        lit[x + (SCREEN_HEIGHT - 1) * SCREEN_WIDTH] = true;
+    }
+    for y in 0..SCREEN_HEIGHT {
+        lit[y * SCREEN_WIDTH] = true;
+        lit[SCREEN_WIDTH - 1 + y * SCREEN_WIDTH] = true;
+    }
+    for glyph in 0..16 {
+        let (left, top) = (4 + (glyph % 8) * 7, 6 + (glyph / 8) * 12);
+        for row in 0..5 {
+            for column in 0..4 {
+                if FONT_SPRITES[glyph * 5 + row] & (0b1000_0000 >> column) != 0 {
+                    lit[left + column + (top + row) * SCREEN_WIDTH] = true;
+                }
+            }
+        }
+    }
+
+    lit.iter()
+        .flat_map(|pixel| {
+            if *pixel {
+                FOREGROUND_COLOR
+            } else {
+                BACKGROUND_COLOR
+            }
+        })
+        .collect()
+}
+
+// Compares the image with the reference in `snapshots`,
+// rewriting the reference instead when UPDATE_SNAPSHOTS is set
+fn assert_snapshot(name: &str, image: &Image) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/peripherals/snapshots")
+        .join(format!("{}.png", name));
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        write_png(&path, image).unwrap();
+    }
+
+    let file = BufReader::new(File::open(&path).unwrap());
+    let mut reader = png::Decoder::new(file).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(
+        (info.width as usize, info.height as usize),
+        (image.width, image.height),
+        "{}: size differs from the reference",
+        name
+    );
+
+    // Allows rounding differences between platforms
+    let differences = pixels
+        .iter()
+        .zip(&image.pixels)
+        .filter(|(reference, actual)| reference.abs_diff(**actual) > 1)
+        .count();
+    assert_eq!(differences, 0, "{}: differs from the reference", name);
+}
+
+fn process(effects: &[PostEffect]) -> Image {
+    PostProcessor::new(effects, SCALE)
+        .process(&test_frame())
+        .clone()
+}
+
+#[test]
+fn test_no_effect_upscales() {
+    let image = process(&[]);
+    assert_eq!((image.width, image.height), (256, 128));
+    assert_eq!(image.pixels[0..3], FOREGROUND_COLOR);
+    assert_eq!(image.pixels[image.offset(3, 3)..][..3], FOREGROUND_COLOR);
+    assert_eq!(image.pixels[image.offset(4, 4)..][..3], BACKGROUND_COLOR);
+}
+
+#[test]
+fn test_effect_order_is_fixed() {
+    assert_eq!(
+        process(&[PostEffect::Grid, PostEffect::Glow, PostEffect::Grid]),
+        process(&[PostEffect::Glow, PostEffect::Grid])
+    );
+}
+
+#[test]
+fn test_snapshots() {
+    assert_snapshot("glow", &process(&[PostEffect::Glow]));
+    assert_snapshot("scanlines", &process(&[PostEffect::Scanlines]));
+    assert_snapshot("grid", &process(&[PostEffect::Grid]));
+    assert_snapshot("crt", &process(&[PostEffect::Crt]));
+    assert_snapshot(
+        "all",
+        &process(&[
+            PostEffect::Glow,
+            PostEffect::Scanlines,
+            PostEffect::Grid,
+            PostEffect::Crt,
+        ]),
+    );
+}
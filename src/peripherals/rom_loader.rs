@@ -0,0 +1,269 @@
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+
+use crate::constants::MEMORY_SIZE;
+use crate::toolchain::octo_assembler::assemble;
+
+//  ROM sources, as given for <ROM file>:
+//
+//  game.ch8            bytes of the ROM
+//  game.hex            hex (1200A22A, 12 00 A2 2A or 0x12, 0x00) or base64 text of the ROM
+//  pack.zip            first ROM of the archive (.ch8, .c8, .sc8, .xo8), or its only file
+//  pack.zip:game.ch8   entry of the archive
+//  game.gif            Octo cartridge, its Octo source assembled (see octo_assembler.rs)
+//  -                   any of the above read from stdin
+//
+//  Text is recognized from the contents, the .txt, .hex, .b64 and .base64 extensions
+//  making it required.
+
+const STDIN_SOURCE: &str = "-";
+const MAX_SOURCE_SIZE: usize = 1 << 20; // Bytes read from a source, archives and text being larger than the ROM
+
+const ROM_EXTENSIONS: [&str; 4] = ["ch8", "c8", "sc8", "xo8"];
+const TEXT_EXTENSIONS: [&str; 4] = ["txt", "hex", "b64", "base64"];
+
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+const GIF_SIGNATURE: &[u8] = b"GIF8";
+
+/// Payload of an Octo cartridge, the program being Octo source code
+#[derive(Deserialize)]
+struct Cartridge {
+    program: String,
+}
+
+/// Reads a ROM from a file, an archive entry or stdin, decoding it as needed
+pub(crate) fn load(source: &str) -> Result<Vec<u8>, String> {
+    let (bytes, entry) = read_source(source)?;
+    let rom = decode(source, bytes, entry.as_deref())
+        .map_err(|e| format!("Error: <ROM file> {} {}", source, e))?;
+    if rom.is_empty() {
+        return Err(format!("Error: <ROM file> {} empty", source));
+    }
+    if rom.len() > MEMORY_SIZE {
+        return Err(format!(
+            "Error: <ROM file> {} is {} bytes, more than the {} bytes of memory",
+            source,
+            rom.len(),
+            MEMORY_SIZE
+        ));
+    }
+    Ok(rom)
+}
+
+/// Returns the bytes of the source, and the archive entry following the archive path if any
+fn read_source(source: &str) -> Result<(Vec<u8>, Option<String>), String> {
+    let error = |e: String| format!("Error: <ROM file> {} {}", source, e);
+    if source == STDIN_SOURCE {
+        return Ok((read_limited(io::stdin().lock()).map_err(error)?, None));
+    }
+    let (path, entry) = if Path::new(source).exists() {
+        (source, None)
+    } else {
+        // The archive is the longest existing file before a colon
+        source
+            .rmatch_indices(':')
+            .map(|(index, _)| (&source[..index], Some(source[index + 1..].to_string())))
+            .find(|(archive, _)| Path::new(archive).is_file())
+            .unwrap_or((source, None))
+    };
+    let file = File::open(path).map_err(|e| error(format!("not readable: {}", e)))?;
+    Ok((read_limited(file).map_err(error)?, entry))
+}
+
+/// Reads the whole source, refusing the ones too large to hold a ROM
+fn read_limited(reader: impl Read) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    reader
+        .take(MAX_SOURCE_SIZE as u64 + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("not readable: {}", e))?;
+    if bytes.len() > MAX_SOURCE_SIZE {
+        return Err(format!(
+            "larger than {} bytes, too large to hold a ROM",
+            MAX_SOURCE_SIZE
+        ));
+    }
+    Ok(bytes)
+}
+
+fn has_extension(name: &str, extensions: &[&str]) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|known| extension.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Returns the ROM held by the bytes of a source, opening archives first
+fn decode(name: &str, bytes: Vec<u8>, entry: Option<&str>) -> Result<Vec<u8>, String> {
+    if bytes.starts_with(ZIP_SIGNATURE) {
+        let (entry, contents) = read_archive(bytes, entry)?;
+        return decode_contents(&entry, contents).map_err(|e| format!("entry {} {}", entry, e));
+    }
+    if let Some(entry) = entry {
+        return Err(format!("not a ZIP archive, no entry '{}' to open", entry));
+    }
+    decode_contents(name, bytes)
+}
+
+fn decode_contents(name: &str, bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    if bytes.starts_with(GIF_SIGNATURE) {
+        return read_cartridge(&bytes);
+    }
+    // Binary ROMs may happen to be printable, so text is only looked for without a ROM extension
+    let text_required = has_extension(name, &TEXT_EXTENSIONS);
+    if text_required || (!has_extension(name, &ROM_EXTENSIONS) && is_text(&bytes)) {
+        match decode_text(&bytes) {
+            Some(rom) => return Ok(rom),
+            None if text_required => return Err("invalid, expected hex or base64 text".to_string()),
+            None => {}
+        }
+    }
+    Ok(bytes)
+}
+
+/// Returns the name and contents of an archive entry, the first ROM or the only file by default
+fn read_archive(bytes: Vec<u8>, entry: Option<&str>) -> Result<(String, Vec<u8>), String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| format!("invalid ZIP archive: {}", e))?;
+    let mut names = Vec::new();
+    for index in 0..archive.len() {
+        let file = archive
+            .by_index(index)
+            .map_err(|e| format!("invalid ZIP archive: {}", e))?;
+        if !file.is_dir() {
+            names.push(file.name().to_string());
+        }
+    }
+
+    let name = match entry {
+        Some(entry) => names
+            .iter()
+            .find(|name| *name == entry)
+            .ok_or_else(|| format!("has no entry '{}', entries: {}", entry, names.join(", ")))?,
+        None => names
+            .iter()
+            .find(|name| has_extension(name, &ROM_EXTENSIONS))
+            .or(if names.len() == 1 {
+                names.first()
+            } else {
+                None
+            })
+            .ok_or_else(|| {
+                format!(
+                    "has no ROM entry (.ch8, .c8, .sc8 or .xo8), choose one of: {}",
+                    names.join(", ")
+                )
+            })?,
+    }
+    .clone();
+    let file = archive
+        .by_name(&name)
+        .map_err(|e| format!("entry {} not readable: {}", name, e))?;
+    let contents = read_limited(file).map_err(|e| format!("entry {} {}", name, e))?;
+    Ok((name, contents))
+}
+
+fn is_text(bytes: &[u8]) -> bool {
+    !bytes.is_empty()
+        && bytes
+            .iter()
+            .all(|byte| byte.is_ascii_graphic() || byte.is_ascii_whitespace())
+}
+
+/// Decodes hex or base64 text, None if it is neither
+fn decode_text(bytes: &[u8]) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    decode_hex(text).or_else(|| decode_base64(text))
+}
+
+/// Decodes hex digits, in groups of whole bytes separated by spaces or commas,
+/// each group optionally starting with 0x
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let mut rom = Vec::new();
+    for group in text.split(|c: char| c.is_whitespace() || c == ',') {
+        if group.is_empty() {
+            continue;
+        }
+        let digits = group
+            .strip_prefix("0x")
+            .or_else(|| group.strip_prefix("0X"))
+            .unwrap_or(group);
+        if digits.is_empty() || digits.len() % 2 != 0 {
+            return None;
+        }
+        for index in (0..digits.len()).step_by(2) {
+            rom.push(u8::from_str_radix(digits.get(index..index + 2)?, 16).ok()?);
+        }
+    }
+    (!rom.is_empty()).then_some(rom)
+}
+
+/// Decodes base64 text, with or without padding, in the standard or the URL alphabet
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD]
+        .iter()
+        .find_map(|engine| engine.decode(&text).ok())
+        .filter(|rom| !rom.is_empty())
+}
+
+//  Octo cartridges are GIF images whose pixels carry a payload in the low 4 bits of
+//  their palette index, two pixels per byte, high nibble first, over every frame:
+//
+//  payload size (4 bytes, big endian)
+//  payload, JSON { "program": "<Octo source>", "options": { ... } }
+
+const CARTRIDGE_SIZE_BYTES: usize = 4;
+
+/// Returns the ROM assembled from the program of an Octo cartridge
+fn read_cartridge(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options
+        .read_info(bytes)
+        .map_err(|e| format!("invalid GIF image: {}", e))?;
+    let mut nibbles = Vec::new();
+    while let Some(frame) = decoder
+        .read_next_frame()
+        .map_err(|e| format!("invalid GIF image: {}", e))?
+    {
+        nibbles.extend(frame.buffer.iter().map(|index| index & 0xF));
+    }
+    let payload: Vec<u8> = nibbles
+        .chunks_exact(2)
+        .map(|pair| pair[0] << 4 | pair[1])
+        .collect();
+
+    let not_cartridge = || "not an Octo cartridge".to_string();
+    let size_bytes = payload
+        .get(..CARTRIDGE_SIZE_BYTES)
+        .ok_or_else(not_cartridge)?;
+    let size = u32::from_be_bytes(size_bytes.try_into().unwrap()) as usize;
+    let json = CARTRIDGE_SIZE_BYTES
+        .checked_add(size)
+        .and_then(|end| payload.get(CARTRIDGE_SIZE_BYTES..end))
+        .ok_or_else(not_cartridge)?;
+    let cartridge: Cartridge = serde_json::from_slice(json).map_err(|_| not_cartridge())?;
+    let rom = assemble(&cartridge.program).map_err(|e| {
+        format!(
+            "is an Octo cartridge whose program does not assemble, {}",
+            e
+        )
+    })?;
+    if rom.is_empty() {
+        return Err("is an Octo cartridge with an empty program".to_string());
+    }
+    Ok(rom)
+}
+
+#[cfg(test)]
+#[path = "./rom_loader_test.rs"]
+mod rom_loader_test;
@@ -0,0 +1,162 @@
+use super::*;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("chip8-{}-{}", std::process::id(), name))
+}
+
+fn zip_archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    for (name, contents) in entries {
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(contents).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// Builds a cartridge the way Octo does, the payload in the pixels of a single frame
+fn cartridge(program: &str) -> Vec<u8> {
+    cartridge_payload(&format!("{{\"program\":{:?},\"options\":{{}}}}", program))
+}
+
+fn cartridge_payload(json: &str) -> Vec<u8> {
+    let mut payload = (json.len() as u32).to_be_bytes().to_vec();
+    payload.extend(json.as_bytes());
+    let mut pixels: Vec<u8> = payload
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0xF])
+        .collect();
+    let width = 32;
+    pixels.resize(pixels.len().div_ceil(width) * width, 0);
+
+    let palette: Vec<u8> = (0..16).flat_map(|shade| [shade * 16; 3]).collect();
+    let mut gif = Vec::new();
+    {
+        let height = (pixels.len() / width) as u16;
+        let mut encoder = gif::Encoder::new(&mut gif, width as u16, height, &palette).unwrap();
+        let frame = gif::Frame {
+            width: width as u16,
+            height,
+            buffer: pixels.into(),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).unwrap();
+    }
+    gif
+}
+
+#[test]
+fn test_decode_text() {
+    assert_eq!(
+        decode_text(b"1200A22A\n"),
+        Some(vec![0x12, 0x00, 0xA2, 0x2A])
+    );
+    assert_eq!(
+        decode_text(b"0x12, 0x00,\n0xa2"),
+        Some(vec![0x12, 0x00, 0xA2])
+    );
+    assert_eq!(decode_text(b"EgCiKg=="), Some(vec![0x12, 0x00, 0xA2, 0x2A]));
+    assert_eq!(decode_text(b"EgCiKg"), Some(vec![0x12, 0x00, 0xA2, 0x2A]));
+    assert_eq!(decode_text(b"not a rom!"), None);
+}
+
+#[test]
+fn test_decode_keeps_binary_roms() {
+    assert_eq!(
+        decode("game.ch8", vec![0x12, 0x00], None),
+        Ok(vec![0x12, 0x00])
+    );
+    // "0123" is also the hex of 0x01 0x23
+    assert_eq!(
+        decode("game.ch8", b"0123".to_vec(), None),
+        Ok(b"0123".to_vec())
+    );
+    assert_eq!(decode("game", b"0123".to_vec(), None), Ok(vec![0x01, 0x23]));
+    assert!(decode("game.hex", b"not hex!".to_vec(), None).is_err());
+    assert!(decode("game.ch8", vec![0x12, 0x00], Some("game.ch8")).is_err());
+}
+
+#[test]
+fn test_decode_zip_archive() {
+    let archive = zip_archive(&[
+        ("readme.txt", b"Press 5 to start"),
+        ("game.ch8", &[0x12, 0x00]),
+        ("other.hex", b"A2 2A"),
+    ]);
+    assert_eq!(
+        decode("pack.zip", archive.clone(), None),
+        Ok(vec![0x12, 0x00])
+    );
+    assert_eq!(
+        decode("pack.zip", archive.clone(), Some("other.hex")),
+        Ok(vec![0xA2, 0x2A])
+    );
+    assert_eq!(
+        decode("pack.zip", archive, Some("missing.ch8")),
+        Err("has no entry 'missing.ch8', entries: readme.txt, game.ch8, other.hex".to_string())
+    );
+
+    let archive = zip_archive(&[("one.bin", &[0x00, 0xE0]), ("two.bin", &[0x00, 0xE0])]);
+    assert!(decode("pack.zip", archive, None).is_err());
+}
+
+#[test]
+fn test_decode_octo_cartridge() {
+    assert_eq!(
+        decode(
+            "game.gif",
+            cartridge("0x12 0x00 # jump\n162 0b101010"),
+            None
+        ),
+        Ok(vec![0x12, 0x00, 0xA2, 0x2A])
+    );
+    assert_eq!(
+        decode("game.gif", cartridge("# nothing"), None),
+        Err("is an Octo cartridge with an empty program".to_string())
+    );
+
+    // Payload of a cartridge saved by Octo, its source assembled
+    let json = concat!(
+        r##"{"program":"# Chip8 is a virtual machine designed in 1977\n: main\n\tv0 := 5\n"##,
+        r##"\ti := hex v0\n\tsprite v1 v2 5\n\tloop again\n","options":{"tickrate":20,"##,
+        r##""fillColor":"#FFCC00","fillColor2":"#FF6600","blendColor":"#662200","##,
+        r##""backgroundColor":"#996600","buzzColor":"#FFAA00","quietColor":"#000000","##,
+        r##""shiftQuirks":false,"loadStoreQuirks":false,"vfOrderQuirks":false,"##,
+        r##""clipQuirks":false,"vBlankQuirks":false,"jumpQuirks":false,"screenRotation":0,"##,
+        r##""maxSize":3584,"touchInputMode":"none","logicQuirks":false,"fontStyle":"octo"}}"##
+    );
+    assert_eq!(
+        decode("game.gif", cartridge_payload(json), None),
+        Ok(vec![0x60, 0x05, 0xF0, 0x29, 0xD1, 0x25, 0x12, 0x06])
+    );
+    assert_eq!(
+        decode("game.gif", cartridge(": main\n  jump nowhere"), None),
+        Err(
+            "is an Octo cartridge whose program does not assemble, line 2: undefined name 'nowhere'"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_load_checks_the_size() {
+    let path = temp_path("large.ch8");
+    fs::write(&path, vec![0x00; MEMORY_SIZE + 1]).unwrap();
+    let source = path.to_str().unwrap();
+    assert_eq!(
+        load(source),
+        Err(format!(
+            "Error: <ROM file> {} is 4097 bytes, more than the 4096 bytes of memory",
+            source
+        ))
+    );
+
+    fs::write(&path, zip_archive(&[("game.ch8", &[0x00, 0xE0])])).unwrap();
+    assert_eq!(load(&format!("{}:game.ch8", source)), Ok(vec![0x00, 0xE0]));
+    fs::remove_file(&path).unwrap();
+
+    assert!(load(source).is_err());
+}
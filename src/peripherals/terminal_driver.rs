@@ -0,0 +1,403 @@
+use clap::ValueEnum;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use sdl2::keyboard::Keycode;
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+
+use crate::constants::{SCREEN_HEIGHT, SCREEN_SIZE, SCREEN_WIDTH};
+use crate::settings::Settings;
+use crate::toolchain::screenshot::Screenshot;
+
+use super::audio_driver::AudioEvent;
+use super::display_driver::DisplayEvent;
+use super::frontend::{AudioFrontend, DisplayFrontend, InputFrontend};
+use super::input_driver::{key_events, KeyEvent};
+use super::keymap::Keymap;
+use super::osd::Osd;
+use super::palette::{Palette, PalettePreset};
+
+//  Terminal frontend, for games over SSH: the display is drawn with Unicode characters in the
+//  palette colours, keys are read in raw mode and the beeper rings the terminal bell.
+//
+//  Hotkeys: Escape or Ctrl+C quits, F2 palette, F3 pause, F4 FPS/IPS, F7 bell on/off,
+//  F10 recording, F12 screenshot.
+
+const PALETTE_KEY: KeyCode = KeyCode::F(2);
+const PAUSE_KEY: KeyCode = KeyCode::F(3);
+const STATS_KEY: KeyCode = KeyCode::F(4);
+const MUTE_KEY: KeyCode = KeyCode::F(7);
+const RECORD_KEY: KeyCode = KeyCode::F(10);
+const SCREENSHOT_KEY: KeyCode = KeyCode::F(12);
+
+// Most terminals only report key presses, repeated while the key is held:
+// a key is released when it was not pressed or repeated for this long
+const KEY_HOLD: Duration = Duration::from_millis(200);
+
+const BELL: &str = "\x07";
+
+/// Characters the display is drawn with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum TerminalCharset {
+    /// Half blocks, 2 pixels per character, 64x16 characters (default)
+    HalfBlock,
+
+    /// Braille patterns, 2x4 pixels per character, 32x8 characters, for small terminals
+    Braille,
+}
+
+/// Returns the lines of characters showing the display, lit pixels in the foreground colour
+pub(crate) fn render(pixels: &[bool], charset: TerminalCharset) -> Vec<String> {
+    let pixel = |x: usize, y: usize| pixels[x + SCREEN_WIDTH * y];
+    match charset {
+        TerminalCharset::HalfBlock => (0..SCREEN_HEIGHT / 2)
+            .map(|row| {
+                (0..SCREEN_WIDTH)
+                    .map(|x| match (pixel(x, row * 2), pixel(x, row * 2 + 1)) {
+                        (false, false) => ' ',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (true, true) => '█',
+                    })
+                    .collect()
+            })
+            .collect(),
+        TerminalCharset::Braille => {
+            // Bits of the dots of a braille pattern, by row of the 2x4 cell
+            const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+            (0..SCREEN_HEIGHT / 4)
+                .map(|row| {
+                    (0..SCREEN_WIDTH / 2)
+                        .map(|column| {
+                            let mut dots = 0;
+                            for (dy, bits) in DOTS.iter().enumerate() {
+                                for (dx, bit) in bits.iter().enumerate() {
+                                    if pixel(column * 2 + dx, row * 4 + dy) {
+                                        dots |= bit;
+                                    }
+                                }
+                            }
+                            if dots == 0 {
+                                ' '
+                            } else {
+                                char::from_u32(0x2800 + dots).unwrap()
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    }
+}
+
+/// Returns the SDL keycode of a terminal key, to look it up in the keymap
+pub(crate) fn host_keycode(code: KeyCode) -> Option<Keycode> {
+    match code {
+        KeyCode::Char(c) => Keycode::from_i32(c.to_lowercase().next()? as i32),
+        KeyCode::Up => Some(Keycode::UP),
+        KeyCode::Down => Some(Keycode::DOWN),
+        KeyCode::Left => Some(Keycode::LEFT),
+        KeyCode::Right => Some(Keycode::RIGHT),
+        KeyCode::Enter => Some(Keycode::RETURN),
+        KeyCode::Tab => Some(Keycode::TAB),
+        KeyCode::Backspace => Some(Keycode::BACKSPACE),
+        _ => None,
+    }
+}
+
+fn terminal_color([r, g, b]: [u8; 3]) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+/// Draws the display on the alternate screen of the terminal, with a status line below it
+/// showing the messages, the pause banner and the FPS and IPS counter
+pub(crate) struct TerminalDisplay {
+    stdout: Stdout,
+    charset: TerminalCharset,
+    palette_preset: PalettePreset,
+    palette: Palette,
+    screenshot: Screenshot,
+
+    /// Messages and status of the status line, and whether it showed anything on the last draw
+    osd: Osd,
+    osd_drawn: bool,
+
+    /// The terminal is cleared on the next draw, after it was resized
+    needs_clear: bool,
+}
+
+impl TerminalDisplay {
+    pub(crate) fn new(settings: &Settings, charset: TerminalCharset) -> Result<Self, String> {
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, Hide, Clear(ClearType::All))
+            .map_err(|e| format!("Error: failed to set up the terminal: {}", e))?;
+        Ok(TerminalDisplay {
+            stdout,
+            charset,
+            palette_preset: settings.palette_preset,
+            palette: settings.palette,
+            screenshot: Screenshot::new(settings),
+            osd: Osd::new(settings.show_fps, Instant::now()),
+            osd_drawn: false,
+            needs_clear: false,
+        })
+    }
+
+    fn write_frame(&mut self, buffer: &[bool]) -> io::Result<()> {
+        if self.needs_clear {
+            queue!(self.stdout, ResetColor, Clear(ClearType::All))?;
+            self.needs_clear = false;
+        }
+
+        let lines = render(buffer, self.charset);
+        queue!(
+            self.stdout,
+            SetForegroundColor(terminal_color(self.palette.foreground())),
+            SetBackgroundColor(terminal_color(self.palette.background()))
+        )?;
+        for (row, line) in lines.iter().enumerate() {
+            queue!(self.stdout, MoveTo(0, row as u16), Print(line))?;
+        }
+
+        // The OSD texts on a single line, cut to the width of the terminal
+        let now = Instant::now();
+        self.osd_drawn = self.osd.is_visible(now);
+        let texts: Vec<String> = self
+            .osd
+            .layout(0, 0, now)
+            .into_iter()
+            .map(|text| text.text)
+            .collect();
+        let width = terminal::size().map_or(SCREEN_WIDTH as u16, |(width, _)| width);
+        let status: String = texts.join("  ").chars().take(width as usize).collect();
+        queue!(
+            self.stdout,
+            ResetColor,
+            MoveTo(0, lines.len() as u16),
+            Print(status),
+            Clear(ClearType::UntilNewLine)
+        )?;
+        self.stdout.flush()
+    }
+}
+
+impl DisplayFrontend for TerminalDisplay {
+    // Pixels are shown as is in the terminal, without any filter
+    fn advance_frame(&mut self, _buffer: &[bool; SCREEN_SIZE]) {}
+
+    fn draw(&mut self, buffer: &[bool; SCREEN_SIZE]) {
+        if let Err(e) = self.write_frame(buffer) {
+            log::error!("Failed to draw to the terminal: {}", e);
+        }
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.osd_drawn || self.osd.is_visible(Instant::now())
+    }
+
+    fn handle_event(&mut self, event: DisplayEvent) {
+        match event {
+            DisplayEvent::Resized => self.needs_clear = true,
+            DisplayEvent::ToggleStats => self.osd.toggle_stats(),
+            DisplayEvent::CyclePalette => {
+                self.palette_preset = self.palette_preset.next();
+                self.palette = self.palette_preset.palette();
+                log::info!("Palette: {:?}", self.palette_preset);
+                self.show_message(format!("Palette: {:?}", self.palette_preset));
+            }
+            DisplayEvent::ToggleFullscreen
+            | DisplayEvent::Screenshot
+            | DisplayEvent::ToggleRecording
+            | DisplayEvent::TogglePause => {}
+        }
+    }
+
+    // The terminal input never opens the rebinding screen
+    fn draw_keypad(&mut self, _highlighted_key: usize) {}
+
+    fn save_screenshot(&mut self, pixels: &[bool]) -> Result<(), String> {
+        let path = self.screenshot.save(pixels, &self.palette)?;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.show_message(format!("Screenshot saved to {}", file_name));
+        Ok(())
+    }
+
+    fn show_message(&mut self, message: String) {
+        self.osd.show_message(message, Instant::now());
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.osd.set_paused(paused);
+    }
+
+    fn count_frame(&mut self, instructions: usize) {
+        self.osd.count_frame(instructions, Instant::now());
+    }
+
+    fn palette(&self) -> &Palette {
+        &self.palette
+    }
+}
+
+impl Drop for TerminalDisplay {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, ResetColor, Show, LeaveAlternateScreen);
+    }
+}
+
+/// Reads the keys from the terminal in raw mode, mapped to the keypad with the keymap
+pub(crate) struct TerminalInput {
+    keymap: Keymap,
+
+    /// Host keys held down, with the time they were last pressed or repeated
+    held: Vec<(Keycode, Instant)>,
+
+    /// True if the terminal reports key releases, otherwise keys are released after `KEY_HOLD`
+    reports_releases: bool,
+
+    /// Keypad state at the last poll, 1 bit per key
+    keypad: u16,
+
+    display_events: Vec<DisplayEvent>,
+    audio_events: Vec<AudioEvent>,
+}
+
+impl TerminalInput {
+    pub(crate) fn new(keymap: Keymap) -> Result<Self, String> {
+        terminal::enable_raw_mode()
+            .map_err(|e| format!("Error: failed to set the terminal to raw mode: {}", e))?;
+        let reports_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if reports_releases {
+            let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+            if let Err(e) = execute!(io::stdout(), PushKeyboardEnhancementFlags(flags)) {
+                log::error!("Failed to enable key releases: {}", e);
+            }
+        }
+        log::info!("Terminal reports key releases: {}", reports_releases);
+
+        Ok(TerminalInput {
+            keymap,
+            held: Vec::new(),
+            reports_releases,
+            keypad: 0,
+            display_events: Vec::new(),
+            audio_events: Vec::new(),
+        })
+    }
+
+    // Returns false on quit
+    fn handle_key(&mut self, key: event::KeyEvent, now: Instant) -> bool {
+        let keycode = host_keycode(key.code);
+        if key.kind == KeyEventKind::Release {
+            self.held.retain(|(held, _)| Some(*held) != keycode);
+            return true;
+        }
+
+        let repeat = key.kind == KeyEventKind::Repeat;
+        match key.code {
+            KeyCode::Esc => return false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            PALETTE_KEY if !repeat => self.display_events.push(DisplayEvent::CyclePalette),
+            PAUSE_KEY if !repeat => self.display_events.push(DisplayEvent::TogglePause),
+            STATS_KEY if !repeat => self.display_events.push(DisplayEvent::ToggleStats),
+            RECORD_KEY if !repeat => self.display_events.push(DisplayEvent::ToggleRecording),
+            SCREENSHOT_KEY if !repeat => self.display_events.push(DisplayEvent::Screenshot),
+            MUTE_KEY if !repeat => self.audio_events.push(AudioEvent::ToggleMute),
+            _ => {
+                if let Some(keycode) = keycode {
+                    self.held.retain(|(held, _)| *held != keycode);
+                    self.held.push((keycode, now));
+                }
+            }
+        }
+        true
+    }
+}
+
+impl InputFrontend for TerminalInput {
+    fn poll(&mut self) -> Result<Vec<KeyEvent>, ()> {
+        let now = Instant::now();
+        while event::poll(Duration::ZERO).map_err(|_| ())? {
+            match event::read().map_err(|_| ())? {
+                Event::Key(key) if !self.handle_key(key, now) => return Err(()),
+                Event::Resize(..) => self.display_events.push(DisplayEvent::Resized),
+                _ => {}
+            }
+        }
+
+        if !self.reports_releases {
+            self.held
+                .retain(|(_, pressed)| now.duration_since(*pressed) < KEY_HOLD);
+        }
+        let keypad = self
+            .keymap
+            .keypad_bits(|keycode| self.held.iter().any(|(held, _)| *held == keycode));
+        let key_events = key_events(self.keypad, keypad);
+        self.keypad = keypad;
+        Ok(key_events)
+    }
+
+    fn take_display_events(&mut self) -> Vec<DisplayEvent> {
+        std::mem::take(&mut self.display_events)
+    }
+
+    fn take_audio_events(&mut self) -> Vec<AudioEvent> {
+        std::mem::take(&mut self.audio_events)
+    }
+}
+
+impl Drop for TerminalInput {
+    fn drop(&mut self) {
+        if self.reports_releases {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Rings the terminal bell when a beep starts, the terminal having no volume control
+pub(crate) struct TerminalBell {
+    stdout: Stdout,
+    enabled: bool,
+    beeping: bool,
+}
+
+impl TerminalBell {
+    pub(crate) fn new(settings: &Settings) -> Self {
+        TerminalBell {
+            stdout: io::stdout(),
+            enabled: settings.sound,
+            beeping: false,
+        }
+    }
+}
+
+impl AudioFrontend for TerminalBell {
+    fn set_beeping(&mut self, beeping: bool) {
+        if beeping && !self.beeping && self.enabled {
+            let _ = write!(self.stdout, "{}", BELL).and_then(|_| self.stdout.flush());
+        }
+        self.beeping = beeping;
+    }
+
+    // The volume hotkeys are not read from the terminal
+    fn handle_event(&mut self, event: AudioEvent) -> String {
+        if event == AudioEvent::ToggleMute {
+            self.enabled = !self.enabled;
+        }
+        let message = format!("Bell: {}", if self.enabled { "on" } else { "off" });
+        log::info!("{}", message);
+        message
+    }
+}
+
+#[cfg(test)]
+#[path = "./terminal_driver_test.rs"]
+mod terminal_driver_test;
@@ -0,0 +1,44 @@
+use super::*;
+
+fn pixels(lit: &[(usize, usize)]) -> [bool; SCREEN_SIZE] {
+    let mut pixels = [false; SCREEN_SIZE];
+    for (x, y) in lit {
+        pixels[x + SCREEN_WIDTH * y] = true;
+    }
+    pixels
+}
+
+#[test]
+fn test_render_half_blocks() {
+    let lines = render(
+        &pixels(&[(0, 0), (1, 1), (2, 0), (2, 1), (63, 31)]),
+        TerminalCharset::HalfBlock,
+    );
+    assert_eq!(lines.len(), 16);
+    assert!(lines.iter().all(|line| line.chars().count() == 64));
+    assert!(lines[0].starts_with("▀▄█ "));
+    assert!(lines[15].ends_with(" ▄"));
+}
+
+#[test]
+fn test_render_braille() {
+    let lit: Vec<(usize, usize)> = (0..4)
+        .flat_map(|y| [(2, y), (3, y)])
+        .chain([(0, 0), (1, 3)])
+        .collect();
+    let lines = render(&pixels(&lit), TerminalCharset::Braille);
+    assert_eq!(lines.len(), 8);
+    assert!(lines.iter().all(|line| line.chars().count() == 32));
+    assert!(lines[0].starts_with("⢁⣿ "));
+    assert!(lines[7].chars().all(|c| c == ' '));
+}
+
+#[test]
+fn test_host_keycodes() {
+    assert_eq!(host_keycode(KeyCode::Char('Q')), Some(Keycode::Q));
+    assert_eq!(host_keycode(KeyCode::Char('q')), Some(Keycode::Q));
+    assert_eq!(host_keycode(KeyCode::Char('&')), Some(Keycode::AMPERSAND));
+    assert_eq!(host_keycode(KeyCode::Char(' ')), Some(Keycode::SPACE));
+    assert_eq!(host_keycode(KeyCode::Up), Some(Keycode::UP));
+    assert_eq!(host_keycode(KeyCode::F(5)), None);
+}
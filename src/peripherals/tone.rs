@@ -0,0 +1,91 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+use crate::constants::{BEEP_ATTACK, BEEP_RELEASE};
+use crate::settings::Settings;
+
+/// Shapes of the beeper sound
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Waveform {
+    /// Harsh buzz of the original hardware (default)
+    Square,
+
+    /// Softer, flute-like
+    Triangle,
+
+    /// Pure tone
+    Sine,
+}
+
+/// Beeper oscillator. The gate opens and closes the sound through short attack and release
+/// ramps instead of cutting it, and the wave restarts from the same phase at every beep.
+pub(crate) struct Tone {
+    waveform: Waveform,
+    phase_inc: f32,
+    phase: f32,
+    volume: f32,
+
+    /// Envelope level from 0 (silent) to 1, moving towards 1 while the gate is open
+    gain: f32,
+    attack_step: f32,
+    release_step: f32,
+    gate: bool,
+}
+
+impl Tone {
+    pub(crate) fn new(settings: &Settings, sample_rate: i32) -> Self {
+        let sample_rate = sample_rate as f32;
+        Tone {
+            waveform: settings.waveform,
+            phase_inc: settings.beep_frequency / sample_rate,
+            phase: 0.0,
+            volume: settings.beep_volume,
+            gain: 0.0,
+            attack_step: 1.0 / (BEEP_ATTACK * sample_rate).max(1.0),
+            release_step: 1.0 / (BEEP_RELEASE * sample_rate).max(1.0),
+            gate: false,
+        }
+    }
+
+    /// Starts or stops the beep
+    pub(crate) fn set_gate(&mut self, gate: bool) {
+        self.gate = gate;
+    }
+
+    pub(crate) fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    /// Returns the next sample, from -1 to 1
+    pub(crate) fn next_sample(&mut self) -> f32 {
+        self.gain = if self.gate {
+            (self.gain + self.attack_step).min(1.0)
+        } else {
+            (self.gain - self.release_step).max(0.0)
+        };
+        if self.gain == 0.0 {
+            self.phase = 0.0;
+            return 0.0;
+        }
+
+        let value = match self.waveform {
+            Waveform::Square => {
+                if self.phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 1.0 - 4.0 * ((self.phase + 0.25) % 1.0 - 0.5).abs(),
+            Waveform::Sine => (self.phase * TAU).sin(),
+        };
+        self.phase = (self.phase + self.phase_inc) % 1.0;
+        value * self.volume * self.gain
+    }
+}
+
+#[cfg(test)]
+#[path = "./tone_test.rs"]
+mod tone_test;
@@ -0,0 +1,53 @@
+use super::*;
+
+const SAMPLE_RATE: i32 = 1000;
+
+fn tone(waveform: Waveform) -> Tone {
+    Tone::new(
+        &Settings {
+            beep_frequency: 250.0,
+            beep_volume: 1.0,
+            waveform,
+            ..Settings::default()
+        },
+        SAMPLE_RATE,
+    )
+}
+
+// Samples rounded to 4 decimals
+fn samples(tone: &mut Tone, count: usize) -> Vec<f32> {
+    (0..count)
+        .map(|_| (tone.next_sample() * 1e4).round() / 1e4)
+        .collect()
+}
+
+#[test]
+fn test_waveforms() {
+    for (waveform, expected) in [
+        (Waveform::Square, [1.0, 1.0, -1.0, -1.0]),
+        (Waveform::Triangle, [0.0, 1.0, 0.0, -1.0]),
+        (Waveform::Sine, [0.0, 1.0, 0.0, -1.0]),
+    ] {
+        let mut tone = tone(waveform);
+        tone.set_gate(true);
+        // Past the attack, 5 samples at 1000Hz, and back to the start of a period
+        samples(&mut tone, 8);
+        assert_eq!(samples(&mut tone, 4), expected, "{:?}", waveform);
+    }
+}
+
+#[test]
+fn test_envelope_ramps_and_resets_the_phase() {
+    let mut tone = tone(Waveform::Square);
+    assert_eq!(samples(&mut tone, 2), [0.0, 0.0]);
+
+    tone.set_gate(true);
+    assert_eq!(samples(&mut tone, 6), [0.2, 0.4, -0.6, -0.8, 1.0, 1.0]);
+
+    tone.set_gate(false);
+    assert_eq!(samples(&mut tone, 6), [-0.8, -0.6, 0.4, 0.2, 0.0, 0.0]);
+
+    // The next beep starts from the beginning of the wave
+    tone.set_gate(true);
+    assert_eq!(samples(&mut tone, 1), [0.2]);
+}
@@ -0,0 +1,340 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, ConfigSection};
+use crate::constants::{DATABASE_DIRECTORY_NAME, FRAME_FREQUENCY};
+use crate::motherboard::quirks::{QuirkPreset, Quirks};
+use crate::peripherals::palette::PALETTE_SIZE;
+
+//  ROM metadata in the format of the community chip-8-database, two JSON files:
+//
+//  programs.json       [{ "title": "Pong (1 player)", "authors": ["Paul Vervalin"],
+//                         "release": "1990", "roms": { "<SHA-1>": {
+//                           "platforms": ["originalChip8"], "tickrate": 15,
+//                           "startAddress": 1536,
+//                           "keys": { "up": 1, "down": 4 },
+//                           "colors": { "pixels": ["#000000", "#FFFFFF"] } } } }]
+//  sha1-hashes.json    { "<SHA-1>": <index in programs.json> }
+//
+//  A copy of the full database in the database directory of the configuration
+//  replaces the one bundled with the emulator. Other fields are ignored.
+
+const BUNDLED_PROGRAMS: &str = include_str!("../database/programs.json");
+const BUNDLED_HASHES: &str = include_str!("../database/sha1-hashes.json");
+
+const PROGRAMS_FILE_NAME: &str = "programs.json";
+const HASHES_FILE_NAME: &str = "sha1-hashes.json";
+
+// Database keys bound to the game controller buttons
+const KEY_BUTTONS: [(&str, &str); 6] = [
+    ("up", "dpup"),
+    ("down", "dpdown"),
+    ("left", "dpleft"),
+    ("right", "dpright"),
+    ("a", "a"),
+    ("b", "b"),
+];
+
+/// Program of the database, holding one or more versions of a ROM
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct Program {
+    pub(crate) title: String,
+
+    #[serde(default)]
+    pub(crate) description: Option<String>,
+
+    #[serde(default)]
+    pub(crate) authors: Vec<String>,
+
+    /// Year of release, sometimes a full date
+    #[serde(default)]
+    pub(crate) release: Option<String>,
+
+    #[serde(default)]
+    roms: BTreeMap<String, RomEntry>,
+}
+
+/// Version of a program, keyed by its SHA-1
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RomEntry {
+    /// Platforms the ROM runs on, the preferred one first
+    #[serde(default)]
+    pub(crate) platforms: Vec<String>,
+
+    /// Platforms the ROM runs on with some of their quirks changed (quirk => enabled)
+    #[serde(default)]
+    pub(crate) quirky_platforms: BTreeMap<String, BTreeMap<String, bool>>,
+
+    /// Instructions per frame
+    #[serde(default)]
+    pub(crate) tickrate: Option<usize>,
+
+    /// Address the ROM is loaded at and started from, 512 (0x200) by default
+    #[serde(default)]
+    pub(crate) start_address: Option<usize>,
+
+    /// Database key name (up, down, left, right, a, b) => Chip8 key
+    #[serde(default)]
+    pub(crate) keys: BTreeMap<String, u8>,
+
+    #[serde(default)]
+    pub(crate) colors: Option<RomColors>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct RomColors {
+    /// Background, foreground, and the colors of the extra planes
+    #[serde(default)]
+    pub(crate) pixels: Vec<String>,
+}
+
+/// ROM found in the database
+#[derive(Clone, Debug)]
+pub(crate) struct KnownRom {
+    pub(crate) program: Program,
+    pub(crate) rom: RomEntry,
+}
+
+/// Known ROMs keyed by the lowercase SHA-1 of their contents
+pub(crate) struct RomDatabase {
+    programs: Vec<Program>,
+    hashes: BTreeMap<String, usize>,
+}
+
+impl RomDatabase {
+    /// Loads the database from the configuration directory, or the bundled one when it has none
+    pub(crate) fn load() -> Result<Self, String> {
+        match Self::directory() {
+            Some(directory) if directory.join(PROGRAMS_FILE_NAME).exists() => {
+                Self::load_directory(&directory)
+            }
+            _ => Self::parse(BUNDLED_PROGRAMS, BUNDLED_HASHES),
+        }
+    }
+
+    pub(crate) fn directory() -> Option<PathBuf> {
+        Config::default_path().and_then(|path| {
+            path.parent()
+                .map(|directory| directory.join(DATABASE_DIRECTORY_NAME))
+        })
+    }
+
+    fn load_directory(directory: &Path) -> Result<Self, String> {
+        let read = |name: &str| {
+            let path = directory.join(name);
+            fs::read_to_string(&path).map_err(|e| {
+                format!(
+                    "Error: ROM database file {} not readable: {}",
+                    path.display(),
+                    e
+                )
+            })
+        };
+        Self::parse(&read(PROGRAMS_FILE_NAME)?, &read(HASHES_FILE_NAME)?)
+            .map_err(|e| format!("Error: ROM database {} invalid: {}", directory.display(), e))
+    }
+
+    pub(crate) fn parse(programs: &str, hashes: &str) -> Result<Self, String> {
+        let programs: Vec<Program> =
+            serde_json::from_str(programs).map_err(|e| format!("{}: {}", PROGRAMS_FILE_NAME, e))?;
+        let hashes: BTreeMap<String, usize> =
+            serde_json::from_str(hashes).map_err(|e| format!("{}: {}", HASHES_FILE_NAME, e))?;
+        if let Some((sha1, index)) = hashes.iter().find(|(_, index)| **index >= programs.len()) {
+            return Err(format!(
+                "{}: {} points to program {}, out of the {} programs",
+                HASHES_FILE_NAME,
+                sha1,
+                index,
+                programs.len()
+            ));
+        }
+        let hashes = hashes
+            .into_iter()
+            .map(|(sha1, index)| (sha1.to_lowercase(), index))
+            .collect();
+        Ok(RomDatabase { programs, hashes })
+    }
+
+    /// Returns the program and the version of the ROM with the given SHA-1, if known
+    pub(crate) fn lookup(&self, rom_sha1: &str) -> Option<KnownRom> {
+        let sha1 = rom_sha1.to_lowercase();
+        let program = &self.programs[*self.hashes.get(&sha1)?];
+        // Versions are keyed by SHA-1 too, whatever the case of the hex digits
+        let rom = program
+            .roms
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(&sha1))
+            .map(|(_, rom)| rom.clone())
+            .unwrap_or_default();
+        Some(KnownRom {
+            program: program.clone(),
+            rom,
+        })
+    }
+}
+
+/// Returns the preset of a database platform, None for the platforms without one
+fn platform_preset(platform: &str) -> Option<QuirkPreset> {
+    match platform {
+        "originalChip8" | "hybridVIP" | "chip8x" => Some(QuirkPreset::Cosmac),
+        "modernChip8" | "xochip" => Some(QuirkPreset::Modern),
+        "chip48" | "superchip1" | "superchip" | "megachip8" => Some(QuirkPreset::Schip),
+        _ => None,
+    }
+}
+
+/// Changes the quirks named in the database, the unknown ones being ignored
+fn apply_database_quirks(quirks: &mut Quirks, changes: &BTreeMap<String, bool>) {
+    for (name, enabled) in changes {
+        match name.as_str() {
+            "shift" => quirks.shift_uses_vy = !enabled,
+            "memoryLeaveIUnchanged" => quirks.load_store_increments_i = !enabled,
+            // I ends on the last register instead of past it, the closest is incrementing it
+            "memoryIncrementByX" if *enabled => quirks.load_store_increments_i = true,
+            "jump" => quirks.jump_uses_vx = *enabled,
+            "logic" => quirks.logic_resets_vf = *enabled,
+            "wrap" => quirks.clip_sprites = !enabled,
+            _ => {}
+        }
+    }
+}
+
+/// Returns the preset sharing the most quirks with the given ones, the first on a tie
+fn closest_preset(quirks: Quirks, presets: &[QuirkPreset]) -> QuirkPreset {
+    let differences = |preset: &QuirkPreset| {
+        let other = preset.quirks();
+        [
+            quirks.shift_uses_vy != other.shift_uses_vy,
+            quirks.load_store_increments_i != other.load_store_increments_i,
+            quirks.jump_uses_vx != other.jump_uses_vx,
+            quirks.logic_resets_vf != other.logic_resets_vf,
+            quirks.clip_sprites != other.clip_sprites,
+        ]
+        .iter()
+        .filter(|differs| **differs)
+        .count()
+    };
+    *presets
+        .iter()
+        .min_by_key(|preset| differences(preset))
+        .expect("Error: no quirk preset")
+}
+
+impl KnownRom {
+    /// Returns the platform the ROM is run as, the first one with a preset
+    pub(crate) fn platform(&self) -> Option<&str> {
+        self.rom
+            .platforms
+            .iter()
+            .chain(self.rom.quirky_platforms.keys())
+            .map(String::as_str)
+            .find(|platform| platform_preset(platform).is_some())
+    }
+
+    /// Returns the preset of the platform, or the closest to its quirks as changed for the ROM
+    pub(crate) fn quirk_preset(&self) -> Option<QuirkPreset> {
+        let platform = self.platform()?;
+        let preset = platform_preset(platform)?;
+        let Some(changes) = self.rom.quirky_platforms.get(platform) else {
+            return Some(preset);
+        };
+        let mut quirks = preset.quirks();
+        apply_database_quirks(&mut quirks, changes);
+        let others = [QuirkPreset::Modern, QuirkPreset::Cosmac, QuirkPreset::Schip]
+            .into_iter()
+            .filter(|other| *other != preset);
+        Some(closest_preset(
+            quirks,
+            &[preset].into_iter().chain(others).collect::<Vec<_>>(),
+        ))
+    }
+
+    /// Returns the controller bindings of the database keys (button => Chip8 key)
+    pub(crate) fn controller_bindings(&self) -> BTreeMap<String, String> {
+        KEY_BUTTONS
+            .iter()
+            .filter_map(|(name, button)| {
+                let key = self.rom.keys.get(*name).filter(|key| **key <= 0xF)?;
+                Some((button.to_string(), format!("{:X}", key)))
+            })
+            .collect()
+    }
+
+    /// Returns the settings recommended by the database, applied before the ROM section of
+    /// the configuration
+    pub(crate) fn to_config_section(&self) -> ConfigSection {
+        let colors = self
+            .rom
+            .colors
+            .as_ref()
+            .map(|colors| {
+                colors
+                    .pixels
+                    .iter()
+                    .take(PALETTE_SIZE)
+                    .cloned()
+                    .collect::<Vec<String>>()
+            })
+            .filter(|pixels| !pixels.is_empty());
+        let controller = self.controller_bindings();
+        ConfigSection {
+            colors,
+            frame_size: self.rom.tickrate.filter(|tickrate| *tickrate > 0),
+            quirks: self.quirk_preset(),
+            load_address: self.rom.start_address,
+            controller: (!controller.is_empty()).then_some(controller),
+            ..ConfigSection::default()
+        }
+    }
+
+    /// Returns the metadata as lines of the info command
+    pub(crate) fn describe(&self) -> Vec<String> {
+        let mut lines = vec![format!("Title:        {}", self.program.title)];
+        if !self.program.authors.is_empty() {
+            lines.push(format!("Authors:      {}", self.program.authors.join(", ")));
+        }
+        if let Some(release) = &self.program.release {
+            lines.push(format!("Released:     {}", release));
+        }
+        if let Some(description) = &self.program.description {
+            lines.push(format!("Description:  {}", description));
+        }
+        if let Some(platform) = self.platform() {
+            let preset = self
+                .quirk_preset()
+                .map(QuirkPreset::name)
+                .unwrap_or_default();
+            lines.push(format!("Platform:     {} ({} quirks)", platform, preset));
+        }
+        if let Some(tickrate) = self.rom.tickrate {
+            lines.push(format!(
+                "Speed:        {} instructions per frame ({} IPS)",
+                tickrate,
+                (tickrate as f64 * FRAME_FREQUENCY) as u64
+            ));
+        }
+        if let Some(address) = self.rom.start_address {
+            lines.push(format!("Loaded at:    {:#05X}", address));
+        }
+        if !self.rom.keys.is_empty() {
+            let keys: Vec<String> = self
+                .rom
+                .keys
+                .iter()
+                .map(|(name, key)| format!("{} {:X}", name, key))
+                .collect();
+            lines.push(format!("Keys:         {}", keys.join(", ")));
+        }
+        if let Some(colors) = self.rom.colors.as_ref().filter(|c| !c.pixels.is_empty()) {
+            lines.push(format!("Colors:       {}", colors.pixels.join(", ")));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+#[path = "./rom_database_test.rs"]
+mod rom_database_test;
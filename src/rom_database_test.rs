@@ -0,0 +1,108 @@
+use super::*;
+
+const PROGRAMS: &str = r##"[
+  {
+    "title": "Test game",
+    "authors": ["Someone", "Someone else"],
+    "release": "2024",
+    "images": ["ignored.png"],
+    "roms": {
+      "0123456789ABCDEF0123456789ABCDEF01234567": {
+        "file": "game.ch8",
+        "platforms": ["unknownPlatform", "superchip"],
+        "tickrate": 30,
+        "startAddress": 1536,
+        "keys": { "up": 5, "a": 6, "player2Up": 8, "b": 16 },
+        "colors": { "pixels": ["#000000", "#ff8000"], "buzzer": "#ffffff" }
+      }
+    }
+  },
+  {
+    "title": "Quirky game",
+    "roms": {
+      "89abcdef0123456789abcdef0123456789abcdef": {
+        "quirkyPlatforms": { "originalChip8": { "shift": true, "memoryLeaveIUnchanged": true, "logic": false } }
+      }
+    }
+  }
+]"##;
+
+const HASHES: &str = r#"{
+  "0123456789abcdef0123456789abcdef01234567": 0,
+  "89abcdef0123456789abcdef0123456789abcdef": 1
+}"#;
+
+#[test]
+fn test_lookup_is_case_insensitive() {
+    let database = RomDatabase::parse(PROGRAMS, HASHES).unwrap();
+    let known_rom = database
+        .lookup("0123456789ABCDEF0123456789ABCDEF01234567")
+        .unwrap();
+    assert_eq!(known_rom.program.title, "Test game");
+    assert_eq!(known_rom.rom.tickrate, Some(30));
+    assert!(database
+        .lookup("0000000000000000000000000000000000000000")
+        .is_none());
+}
+
+#[test]
+fn test_known_rom_settings() {
+    let database = RomDatabase::parse(PROGRAMS, HASHES).unwrap();
+    let known_rom = database
+        .lookup("0123456789abcdef0123456789abcdef01234567")
+        .unwrap();
+    assert_eq!(known_rom.platform(), Some("superchip"));
+
+    let section = known_rom.to_config_section();
+    assert_eq!(section.quirks, Some(QuirkPreset::Schip));
+    assert_eq!(section.frame_size, Some(30));
+    assert_eq!(section.load_address, Some(0x600));
+    assert_eq!(
+        section.colors,
+        Some(vec!["#000000".to_string(), "#ff8000".to_string()])
+    );
+    // Player 2 keys and keys past F are left out
+    assert_eq!(
+        section.controller,
+        Some(BTreeMap::from([
+            ("a".to_string(), "6".to_string()),
+            ("dpup".to_string(), "5".to_string()),
+        ]))
+    );
+    assert_eq!(
+        known_rom.describe()[..4],
+        [
+            "Title:        Test game",
+            "Authors:      Someone, Someone else",
+            "Released:     2024",
+            "Platform:     superchip (schip quirks)",
+        ]
+    );
+}
+
+#[test]
+fn test_quirky_platform_takes_closest_preset() {
+    let database = RomDatabase::parse(PROGRAMS, HASHES).unwrap();
+    let known_rom = database
+        .lookup("89abcdef0123456789abcdef0123456789abcdef")
+        .unwrap();
+    assert_eq!(known_rom.platform(), Some("originalChip8"));
+    assert_eq!(known_rom.quirk_preset(), Some(QuirkPreset::Modern));
+    assert_eq!(known_rom.to_config_section().controller, None);
+}
+
+#[test]
+fn test_invalid_database() {
+    assert!(RomDatabase::parse("{}", HASHES).is_err());
+    assert!(RomDatabase::parse(PROGRAMS, r#"{ "0123": 2 }"#).is_err());
+}
+
+#[test]
+fn test_bundled_database() {
+    let database = RomDatabase::parse(BUNDLED_PROGRAMS, BUNDLED_HASHES).unwrap();
+    let known_rom = database
+        .lookup("b232ef880bd6060fb45fa6effed7edf0ae95670e")
+        .unwrap();
+    assert_eq!(known_rom.program.title, "Pong (1 player)");
+    assert_eq!(known_rom.quirk_preset(), Some(QuirkPreset::Cosmac));
+}
@@ -0,0 +1,568 @@
+use log::LevelFilter;
+use std::collections::BTreeMap;
+
+use crate::cli::{
+    check_address, parse_color, parse_decay, parse_frequency, parse_volume, CaptureOptions,
+    DisplayOptions, EmulatorOptions, InputOptions, LayoutOptions,
+};
+use crate::config::{Config, ConfigSection};
+use crate::constants::{
+    BEEP_FREQUENCY, BEEP_VOLUME, BLEND_FRAMES, CAPTURE_DIRECTORY, FILTER_DECAY, FRAME_FREQUENCY,
+    FRAME_SIZE, LOG_FILE_PATH, LOG_LEVEL, RECORD_SCALE, SCALE_FACTOR,
+};
+use crate::motherboard::layout::{MemoryLayout, ProgramLayout};
+use crate::motherboard::quirks::{KeyWait, QuirkPreset, Quirks};
+use crate::peripherals::controller_map::{parse_button, ControllerMap, UNBOUND};
+use crate::peripherals::display_filter::DisplayFilter;
+use crate::peripherals::keymap::{parse_keypad_key, Keymap, KeymapPreset};
+use crate::peripherals::palette::{Palette, PalettePreset, PALETTE_SIZE};
+use crate::peripherals::post_processing::PostEffect;
+use crate::peripherals::tone::Waveform;
+use crate::toolchain::recorder::RecordingFormat;
+use crate::toolchain::screenshot::ScreenshotSize;
+
+/// Effective emulator settings, starting from the defaults in `constants.rs`
+pub(crate) struct Settings {
+    /// Scaling factor for rendering the CHIP-8 display
+    pub(crate) scale_factor: u32,
+
+    /// Palette the display colors were taken from
+    pub(crate) palette_preset: PalettePreset,
+
+    /// Custom colors replacing the ones of the palette preset, in palette order
+    pub(crate) custom_colors: [Option<[u8; 3]>; PALETTE_SIZE],
+
+    /// Display colors: background, foreground and the colors of the extra planes
+    pub(crate) palette: Palette,
+
+    /// Number of CPU cycles (instructions) to execute per frame
+    pub(crate) frame_size: usize,
+
+    /// Quirk preset the processor quirks were taken from
+    pub(crate) quirk_preset: QuirkPreset,
+
+    /// Interpreter behaviours for ambiguous opcodes
+    pub(crate) quirks: Quirks,
+
+    /// Detect the quirk preset from the ROM at startup, no preset having been given
+    pub(crate) detect_quirks: bool,
+
+    /// FX0A behaviour replacing the one of the quirk preset
+    pub(crate) key_wait: Option<KeyWait>,
+
+    /// Memory layout the load and entry addresses were taken from
+    pub(crate) memory_layout: MemoryLayout,
+
+    /// Load address replacing the one of the memory layout
+    pub(crate) load_address: Option<usize>,
+
+    /// Entry address replacing the one of the memory layout
+    pub(crate) entry_address: Option<usize>,
+
+    /// Addresses the ROM is loaded at and started from
+    pub(crate) program_layout: ProgramLayout,
+
+    /// Seed for the RND instruction, random if not set
+    pub(crate) seed: Option<u64>,
+
+    /// Path to the log file
+    pub(crate) log_file_path: String,
+
+    /// Maximum level of the messages written to the log file
+    pub(crate) log_level: LevelFilter,
+
+    /// Play the beeper through the sound card
+    pub(crate) sound: bool,
+
+    /// Pitch of the beeper in Hz
+    pub(crate) beep_frequency: f32,
+
+    /// Volume of the beeper, from 0 to 1
+    pub(crate) beep_volume: f32,
+
+    /// Shape of the beeper sound
+    pub(crate) waveform: Waveform,
+
+    /// Start the window in fullscreen mode
+    pub(crate) fullscreen: bool,
+
+    /// Show the FPS and IPS counter over the display
+    pub(crate) show_fps: bool,
+
+    /// Open the debugger window next to the display
+    pub(crate) debugger: bool,
+
+    /// Scale the display by whole numbers only when the window is resized
+    pub(crate) integer_scale: bool,
+
+    /// Flicker reduction filter applied to every frame
+    pub(crate) display_filter: DisplayFilter,
+
+    /// Brightness kept by a turned off pixel after each frame (phosphor and blend filters)
+    pub(crate) filter_decay: f32,
+
+    /// Number of frames combined by the blend filter
+    pub(crate) blend_frames: usize,
+
+    /// Post-processing effects applied to the upscaled display
+    pub(crate) post_effects: Vec<PostEffect>,
+
+    /// Directory the screenshots and recordings are written to
+    pub(crate) capture_directory: String,
+
+    /// Resolution of the screenshots
+    pub(crate) screenshot_size: ScreenshotSize,
+
+    /// Format of the recordings without a known file extension
+    pub(crate) record_format: RecordingFormat,
+
+    /// Size of a display pixel in the recordings
+    pub(crate) record_scale: usize,
+
+    /// Keyboard layout the key bindings start from
+    pub(crate) keymap_preset: KeymapPreset,
+
+    /// Custom key bindings replacing the preset ones (Chip8 key => host key names)
+    pub(crate) key_bindings: BTreeMap<String, Vec<String>>,
+
+    /// Game controller bindings replacing the default ones (button => Chip8 key or "none")
+    pub(crate) controller_bindings: BTreeMap<String, String>,
+}
+
+impl Settings {
+    /// Builds the settings from the defaults in `constants.rs`, the `[defaults]`
+    /// section of the configuration, the ROM database entry and the section
+    /// of the given ROM, in this order
+    pub(crate) fn from_config(
+        config: &Config,
+        rom_sha1: Option<&str>,
+        database: Option<&ConfigSection>,
+    ) -> Result<Self, String> {
+        let mut settings = Settings::default();
+        settings.apply_config_section(&config.defaults)?;
+        if let Some(section) = database {
+            settings.apply_config_section(section)?;
+        }
+        if let Some(section) = rom_sha1.and_then(|sha1| config.rom_section(sha1)) {
+            settings.apply_config_section(section)?;
+        }
+        Ok(settings)
+    }
+
+    pub(crate) fn apply_config_section(&mut self, section: &ConfigSection) -> Result<(), String> {
+        if let Some(scale) = section.scale_factor {
+            if scale == 0 {
+                return Err("invalid scale_factor 0, expected at least 1".to_string());
+            }
+            self.scale_factor = scale;
+        }
+        if let Some(preset) = section.palette {
+            self.palette_preset = preset;
+        }
+        if let Some(colors) = &section.colors {
+            if colors.len() > PALETTE_SIZE {
+                return Err(format!(
+                    "invalid colors, expected at most {} colors",
+                    PALETTE_SIZE
+                ));
+            }
+            for (index, color) in colors.iter().enumerate() {
+                self.custom_colors[index] = Some(parse_color(color)?);
+            }
+        }
+        if let Some(background) = &section.background_color {
+            self.custom_colors[0] = Some(parse_color(background)?);
+        }
+        if let Some(foreground) = &section.foreground_color {
+            self.custom_colors[1] = Some(parse_color(foreground)?);
+        }
+        self.update_palette();
+        if let Some(frame_size) = section.frame_size {
+            if frame_size == 0 {
+                return Err("invalid frame_size 0, expected at least 1".to_string());
+            }
+            self.frame_size = frame_size;
+        }
+        if let Some(preset) = section.quirks {
+            self.quirk_preset = preset;
+            self.detect_quirks = false;
+        }
+        if section.key_wait.is_some() {
+            self.key_wait = section.key_wait;
+        }
+        self.update_quirks();
+        if let Some(layout) = section.layout {
+            self.memory_layout = layout;
+        }
+        if let Some(address) = section.load_address {
+            self.load_address = Some(check_address(address)?);
+        }
+        if let Some(address) = section.entry_address {
+            self.entry_address = Some(check_address(address)?);
+        }
+        self.update_program_layout();
+        if section.seed.is_some() {
+            self.seed = section.seed;
+        }
+        if let Some(log_file) = &section.log_file {
+            self.log_file_path = log_file.clone();
+        }
+        if let Some(log_level) = &section.log_level {
+            self.log_level = log_level
+                .parse()
+                .map_err(|_| format!("invalid log level '{}'", log_level))?;
+        }
+        if let Some(sound) = section.sound {
+            self.sound = sound;
+        }
+        if let Some(frequency) = section.beep_frequency {
+            self.beep_frequency = parse_frequency(&frequency.to_string())?;
+        }
+        if let Some(volume) = section.beep_volume {
+            self.beep_volume = parse_volume(&volume.to_string())?;
+        }
+        if let Some(waveform) = section.waveform {
+            self.waveform = waveform;
+        }
+        if let Some(fullscreen) = section.fullscreen {
+            self.fullscreen = fullscreen;
+        }
+        if let Some(show_fps) = section.show_fps {
+            self.show_fps = show_fps;
+        }
+        if let Some(debugger) = section.debugger {
+            self.debugger = debugger;
+        }
+        if let Some(integer_scale) = section.integer_scale {
+            self.integer_scale = integer_scale;
+        }
+        if let Some(filter) = section.filter {
+            self.display_filter = filter;
+        }
+        if let Some(decay) = section.filter_decay {
+            self.filter_decay = parse_decay(&decay.to_string())?;
+        }
+        if let Some(blend_frames) = section.blend_frames {
+            if blend_frames == 0 {
+                return Err("invalid blend_frames 0, expected at least 1".to_string());
+            }
+            self.blend_frames = blend_frames;
+        }
+        if let Some(effects) = &section.effects {
+            self.post_effects = effects.clone();
+        }
+        if let Some(directory) = &section.capture_dir {
+            self.capture_directory = directory.clone();
+        }
+        if let Some(size) = section.screenshot_size {
+            self.screenshot_size = size;
+        }
+        if let Some(format) = section.record_format {
+            self.record_format = format;
+        }
+        if let Some(scale) = section.record_scale {
+            if !(1..=64).contains(&scale) {
+                return Err(format!("invalid record_scale {}, expected 1 to 64", scale));
+            }
+            self.record_scale = scale;
+        }
+        if let Some(preset) = section.keymap {
+            self.keymap_preset = preset;
+        }
+        if let Some(keys) = &section.keys {
+            for (key, host_keys) in keys {
+                self.bind_key(key, host_keys.clone())?;
+            }
+        }
+        if let Some(buttons) = &section.controller {
+            for (button, key) in buttons {
+                self.bind_button(button, key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a section holding every setting, as written in the configuration
+    pub(crate) fn to_config_section(&self) -> ConfigSection {
+        let format_color = |[r, g, b]: [u8; 3]| format!("{:02X}{:02X}{:02X}", r, g, b);
+        ConfigSection {
+            scale_factor: Some(self.scale_factor),
+            foreground_color: None,
+            background_color: None,
+            palette: Some(self.palette_preset),
+            colors: self
+                .custom_colors
+                .iter()
+                .any(Option::is_some)
+                .then(|| self.palette.colors.map(format_color).to_vec()),
+            frame_size: Some(self.frame_size),
+            quirks: (!self.detect_quirks).then_some(self.quirk_preset),
+            key_wait: self.key_wait,
+            layout: Some(self.memory_layout),
+            load_address: self.load_address,
+            entry_address: self.entry_address,
+            seed: self.seed,
+            log_file: Some(self.log_file_path.clone()),
+            log_level: Some(self.log_level.to_string().to_lowercase()),
+            sound: Some(self.sound),
+            beep_frequency: Some(self.beep_frequency),
+            beep_volume: Some(self.beep_volume),
+            waveform: Some(self.waveform),
+            fullscreen: Some(self.fullscreen),
+            show_fps: Some(self.show_fps),
+            debugger: Some(self.debugger),
+            integer_scale: Some(self.integer_scale),
+            filter: Some(self.display_filter),
+            filter_decay: Some(self.filter_decay),
+            blend_frames: Some(self.blend_frames),
+            effects: Some(self.post_effects.clone()),
+            capture_dir: Some(self.capture_directory.clone()),
+            screenshot_size: Some(self.screenshot_size),
+            record_format: Some(self.record_format),
+            record_scale: Some(self.record_scale),
+            keymap: Some(self.keymap_preset),
+            keys: Some(self.key_bindings.clone()).filter(|keys| !keys.is_empty()),
+            controller: Some(self.controller_bindings.clone())
+                .filter(|buttons| !buttons.is_empty()),
+        }
+    }
+
+    pub(crate) fn keymap(&self) -> Result<Keymap, String> {
+        Keymap::new(self.keymap_preset, &self.key_bindings)
+    }
+
+    pub(crate) fn controller_map(&self) -> Result<ControllerMap, String> {
+        ControllerMap::new(&self.controller_bindings)
+    }
+
+    fn bind_button(&mut self, button: &str, key: &str) -> Result<(), String> {
+        let button = parse_button(button)?;
+        let key = if key.eq_ignore_ascii_case(UNBOUND) {
+            UNBOUND.to_string()
+        } else {
+            format!("{:X}", parse_keypad_key(key)?)
+        };
+        self.controller_bindings.insert(button.string(), key);
+        Ok(())
+    }
+
+    fn bind_key(&mut self, key: &str, host_keys: Vec<String>) -> Result<(), String> {
+        let key = parse_keypad_key(key)?;
+        self.key_bindings.insert(format!("{:X}", key), host_keys);
+        Ok(())
+    }
+
+    pub(crate) fn apply_emulator_options(&mut self, options: &EmulatorOptions) {
+        if let Some(ips) = options.ips {
+            self.frame_size = Self::frame_size_for_ips(ips);
+        }
+        if let Some(preset) = options.quirks {
+            self.quirk_preset = preset;
+            self.detect_quirks = false;
+        }
+        if options.key_wait.is_some() {
+            self.key_wait = options.key_wait;
+        }
+        self.update_quirks();
+        self.apply_layout_options(&options.layout);
+        if let Some(frequency) = options.beep_frequency {
+            self.beep_frequency = frequency;
+        }
+        if let Some(volume) = options.beep_volume {
+            self.beep_volume = volume;
+        }
+        if let Some(waveform) = options.waveform {
+            self.waveform = waveform;
+        }
+        if options.seed.is_some() {
+            self.seed = options.seed;
+        }
+        if let Some(log_file) = &options.log_file {
+            self.log_file_path = log_file.clone();
+        }
+        if let Some(log_level) = options.log_level {
+            self.log_level = log_level;
+        }
+    }
+
+    pub(crate) fn apply_layout_options(&mut self, options: &LayoutOptions) {
+        if let Some(layout) = options.layout {
+            self.memory_layout = layout;
+        }
+        if options.load_address.is_some() {
+            self.load_address = options.load_address;
+        }
+        if options.entry_address.is_some() {
+            self.entry_address = options.entry_address;
+        }
+        self.update_program_layout();
+    }
+
+    pub(crate) fn apply_display_options(&mut self, options: &DisplayOptions) {
+        if let Some(scale) = options.scale {
+            self.scale_factor = scale;
+        }
+        if let Some(preset) = options.palette {
+            self.palette_preset = preset;
+        }
+        for (index, color) in options.colors.iter().enumerate() {
+            self.custom_colors[index] = Some(*color);
+        }
+        if let Some(background) = options.background {
+            self.custom_colors[0] = Some(background);
+        }
+        if let Some(foreground) = options.foreground {
+            self.custom_colors[1] = Some(foreground);
+        }
+        self.update_palette();
+        if options.no_sound {
+            self.sound = false;
+        }
+        if options.show_fps {
+            self.show_fps = true;
+        }
+        if options.debugger {
+            self.debugger = true;
+        }
+        if options.fullscreen {
+            self.fullscreen = true;
+        }
+        if options.integer_scale {
+            self.integer_scale = true;
+        }
+        if let Some(filter) = options.filter {
+            self.display_filter = filter;
+        }
+        if let Some(decay) = options.filter_decay {
+            self.filter_decay = decay;
+        }
+        if let Some(blend_frames) = options.blend_frames {
+            self.blend_frames = blend_frames as usize;
+        }
+        if !options.effect.is_empty() {
+            self.post_effects = options.effect.clone();
+        }
+    }
+
+    pub(crate) fn apply_capture_options(&mut self, options: &CaptureOptions) {
+        if let Some(directory) = &options.capture_dir {
+            self.capture_directory = directory.clone();
+        }
+        if let Some(size) = options.screenshot_size {
+            self.screenshot_size = size;
+        }
+        if let Some(format) = options.record_format {
+            self.record_format = format;
+        }
+        if let Some(scale) = options.record_scale {
+            self.record_scale = scale as usize;
+        }
+    }
+
+    pub(crate) fn apply_input_options(&mut self, options: &InputOptions) -> Result<(), String> {
+        if let Some(preset) = options.keymap {
+            self.keymap_preset = preset;
+        }
+        for (key, host_keys) in &options.bind {
+            self.bind_key(key, host_keys.clone())?;
+        }
+        for (button, key) in &options.button {
+            self.bind_button(button, key)?;
+        }
+        Ok(())
+    }
+
+    // Custom colors apply on top of any palette preset, wherever they were set
+    fn update_palette(&mut self) {
+        self.palette = self.palette_preset.palette();
+        for (color, custom) in self.palette.colors.iter_mut().zip(self.custom_colors) {
+            if let Some(custom) = custom {
+                *color = custom;
+            }
+        }
+    }
+
+    /// Uses the quirk preset detected from the ROM, keeping the FX0A override
+    pub(crate) fn apply_detected_quirks(&mut self, preset: QuirkPreset) {
+        self.quirk_preset = preset;
+        self.update_quirks();
+    }
+
+    // The FX0A override applies on top of any preset, wherever they were set
+    fn update_quirks(&mut self) {
+        self.quirks = self.quirk_preset.quirks();
+        if let Some(key_wait) = self.key_wait {
+            self.quirks.key_wait = key_wait;
+        }
+    }
+
+    // A given load address is the entry address too, unless one is given as well
+    fn update_program_layout(&mut self) {
+        self.program_layout = self.memory_layout.program_layout();
+        if let Some(load_address) = self.load_address {
+            self.program_layout.load_address = load_address;
+            self.program_layout.entry_address = load_address;
+        }
+        if let Some(entry_address) = self.entry_address {
+            self.program_layout.entry_address = entry_address;
+        }
+    }
+
+    /// Checks that a ROM fits in memory from the load address
+    pub(crate) fn check_rom_size(&self, rom_size: usize) -> Result<(), String> {
+        let capacity = self.program_layout.capacity();
+        if rom_size > capacity {
+            return Err(format!(
+                "Error: ROM of {} bytes larger than the {} bytes of memory from its load address {:#05X}",
+                rom_size, capacity, self.program_layout.load_address
+            ));
+        }
+        Ok(())
+    }
+
+    fn frame_size_for_ips(ips: u32) -> usize {
+        ((ips as f64 / FRAME_FREQUENCY).round() as usize).max(1)
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            scale_factor: SCALE_FACTOR,
+            palette_preset: PalettePreset::Green,
+            custom_colors: [None; PALETTE_SIZE],
+            palette: PalettePreset::Green.palette(),
+            frame_size: FRAME_SIZE,
+            quirk_preset: QuirkPreset::Modern,
+            quirks: Quirks::default(),
+            detect_quirks: true,
+            key_wait: None,
+            memory_layout: MemoryLayout::Chip8,
+            load_address: None,
+            entry_address: None,
+            program_layout: ProgramLayout::default(),
+            seed: None,
+            log_file_path: LOG_FILE_PATH.to_string(),
+            log_level: LOG_LEVEL,
+            sound: true,
+            beep_frequency: BEEP_FREQUENCY,
+            beep_volume: BEEP_VOLUME,
+            waveform: Waveform::Square,
+            fullscreen: false,
+            show_fps: false,
+            debugger: false,
+            integer_scale: false,
+            display_filter: DisplayFilter::None,
+            filter_decay: FILTER_DECAY,
+            blend_frames: BLEND_FRAMES,
+            post_effects: Vec::new(),
+            capture_directory: CAPTURE_DIRECTORY.to_string(),
+            screenshot_size: ScreenshotSize::Scaled,
+            record_format: RecordingFormat::Gif,
+            record_scale: RECORD_SCALE,
+            keymap_preset: KeymapPreset::Qwerty,
+            key_bindings: BTreeMap::new(),
+            controller_bindings: BTreeMap::new(),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::constants::{AUDIO_SAMPLE_RATE, FRAME_FREQUENCY};
+use crate::peripherals::tone::Tone;
+use crate::settings::Settings;
+
+// Number of samples rendered per 60Hz frame, 735 at 44100Hz
+const SAMPLES_PER_FRAME: usize = (AUDIO_SAMPLE_RATE as f64 / FRAME_FREQUENCY) as usize;
+
+/// Renders the beeper into 16-bit PCM samples, exactly one frame of samples per 60Hz frame,
+/// with the beeps starting and stopping at frame boundaries as they are played
+pub(crate) struct AudioCapture {
+    /// WAV file written by `finish`, nothing is rendered without one
+    path: Option<PathBuf>,
+
+    tone: Tone,
+    samples: Vec<i16>,
+}
+
+impl AudioCapture {
+    pub(crate) fn new(path: Option<PathBuf>, settings: &Settings) -> Self {
+        AudioCapture {
+            path,
+            tone: Tone::new(settings, AUDIO_SAMPLE_RATE),
+            samples: Vec::new(),
+        }
+    }
+
+    /// Renders the samples of one frame, to be called at the end of every frame
+    pub(crate) fn capture_frame(&mut self, beeping: bool) {
+        if self.path.is_none() {
+            return;
+        }
+
+        self.tone.set_gate(beeping);
+        for _ in 0..SAMPLES_PER_FRAME {
+            let sample = self.tone.next_sample();
+            self.samples.push((sample * i16::MAX as f32).round() as i16);
+        }
+    }
+
+    /// Samples rendered so far, at `AUDIO_SAMPLE_RATE`
+    pub(crate) fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    /// Writes the WAV file, returning its path if there is one
+    pub(crate) fn finish(&self) -> Result<Option<PathBuf>, String> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        write_wav(path, self.samples())
+            .map_err(|e| format!("Error: WAV file {} not writable: {}", path.display(), e))?;
+        log::info!("Audio saved to {}", path.display());
+        Ok(Some(path.clone()))
+    }
+}
+
+/// Writes mono 16-bit PCM samples at `AUDIO_SAMPLE_RATE` to a WAV file
+pub(crate) fn write_wav(path: &Path, samples: &[i16]) -> std::io::Result<()> {
+    if let Some(directory) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(directory)?;
+    }
+
+    let data_size = (samples.len() * 2) as u32;
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(b"RIFF")?;
+    file.write_all(&(36 + data_size).to_le_bytes())?;
+    file.write_all(b"WAVE")?;
+
+    file.write_all(b"fmt ")?;
+    file.write_all(&16u32.to_le_bytes())?; // Size of the format chunk
+    file.write_all(&1u16.to_le_bytes())?; // PCM
+    file.write_all(&1u16.to_le_bytes())?; // Mono
+    file.write_all(&(AUDIO_SAMPLE_RATE as u32).to_le_bytes())?;
+    file.write_all(&(AUDIO_SAMPLE_RATE as u32 * 2).to_le_bytes())?; // Bytes per second
+    file.write_all(&2u16.to_le_bytes())?; // Bytes per sample
+    file.write_all(&16u16.to_le_bytes())?; // Bits per sample
+
+    file.write_all(b"data")?;
+    file.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        file.write_all(&sample.to_le_bytes())?;
+    }
+    file.flush()
+}
+
+#[cfg(test)]
+#[path = "./audio_capture_test.rs"]
+mod audio_capture_test;
@@ -0,0 +1,37 @@
+use super::*;
+
+#[test]
+fn test_every_frame_has_the_same_length() {
+    let mut audio_capture = AudioCapture::new(Some("unused.wav".into()), &Settings::default());
+    audio_capture.capture_frame(false);
+    audio_capture.capture_frame(true);
+    audio_capture.capture_frame(false);
+    assert_eq!(audio_capture.samples().len(), 3 * SAMPLES_PER_FRAME);
+
+    let (silence, beep) = audio_capture.samples().split_at(SAMPLES_PER_FRAME);
+    assert!(silence.iter().all(|sample| *sample == 0));
+    assert!(beep[..SAMPLES_PER_FRAME].iter().all(|sample| *sample != 0));
+}
+
+#[test]
+fn test_disabled_renders_nothing() {
+    let mut audio_capture = AudioCapture::new(None, &Settings::default());
+    audio_capture.capture_frame(true);
+    assert!(audio_capture.samples().is_empty());
+    assert_eq!(audio_capture.finish(), Ok(None));
+}
+
+#[test]
+fn test_write_wav() {
+    let path = std::env::temp_dir().join(format!("chip8-{}-beep.wav", std::process::id()));
+    write_wav(&path, &[1, -2]).unwrap();
+
+    let data = fs::read(&path).unwrap();
+    assert_eq!(data.len(), 44 + 4);
+    assert_eq!(&data[0..4], b"RIFF");
+    assert_eq!(u32::from_le_bytes(data[4..8].try_into().unwrap()), 40);
+    assert_eq!(u32::from_le_bytes(data[24..28].try_into().unwrap()), 44100);
+    assert_eq!(&data[36..40], b"data");
+    assert_eq!(data[44..], [1, 0, 0xFE, 0xFF]);
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::constants::OPCODE_SIZE;
+use crate::motherboard::layout::ProgramLayout;
+
+use super::debugger::Debugger;
+use super::decoder::DecodedOpcode;
+
+//  Coverage is kept in lcov tracefiles, one record per ROM, addresses standing for line numbers:
+//
+//  SF:/path/to/game.ch8
+//  DA:512,1           the instruction at 0x200 was executed once
+//  BRDA:540,0,0,12    the skip at 0x21C skipped 12 times
+//  BRDA:540,0,1,3     and went on 3 times
+//  LF:95              instructions, and instructions executed
+//  LH:80
+//  BRF:24             branches, two per skip, and branches taken
+//  BRH:10
+//  end_of_record
+//
+//  Other lcov lines are ignored when reading.
+
+/// Outcomes of a skip instruction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Branch {
+    /// The next instruction was skipped
+    pub(crate) taken: u64,
+
+    /// The next instruction was executed
+    pub(crate) not_taken: u64,
+}
+
+/// Executions of the instructions of a ROM, and outcomes of its skip instructions
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Coverage {
+    hits: BTreeMap<usize, u64>,
+    branches: BTreeMap<usize, Branch>,
+}
+
+impl Coverage {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts an executed instruction, and the outcome of a skip from the address executed next
+    pub(crate) fn record(&mut self, address: usize, skip: bool, next_address: usize) {
+        *self.hits.entry(address).or_default() += 1;
+        if skip {
+            let branch = self.branches.entry(address).or_default();
+            if next_address == address + 2 * OPCODE_SIZE {
+                branch.taken += 1;
+            } else {
+                branch.not_taken += 1;
+            }
+        }
+    }
+
+    /// Adds the instructions found by the disassembler, so that the ones never executed count
+    pub(crate) fn add_rom_instructions(
+        &mut self,
+        rom: &[u8],
+        rom_size: usize,
+        layout: ProgramLayout,
+    ) {
+        let mut debugger = Debugger::new();
+        debugger.reset(rom, rom_size, layout);
+        for address in debugger.instruction_addresses() {
+            self.hits.entry(address).or_default();
+            if instruction_at(rom, layout, address).is_some_and(|(_, decoded)| decoded.is_skip()) {
+                self.branches.entry(address).or_default();
+            }
+        }
+    }
+
+    /// Adds the counts of another run
+    pub(crate) fn merge(&mut self, other: &Coverage) {
+        for (address, hits) in &other.hits {
+            *self.hits.entry(*address).or_default() += hits;
+        }
+        for (address, branch) in &other.branches {
+            let merged = self.branches.entry(*address).or_default();
+            merged.taken += branch.taken;
+            merged.not_taken += branch.not_taken;
+        }
+    }
+
+    /// Returns the instructions executed and the instructions, then the same for the branches
+    fn totals(&self) -> ((usize, usize), (usize, usize)) {
+        let executed = self.hits.values().filter(|hits| **hits > 0).count();
+        let taken = self
+            .branches
+            .values()
+            .map(|branch| (branch.taken > 0) as usize + (branch.not_taken > 0) as usize)
+            .sum();
+        (
+            (executed, self.hits.len()),
+            (taken, 2 * self.branches.len()),
+        )
+    }
+
+    /// Returns the disassembly of the instructions with their hit counts and branch outcomes,
+    /// the ones never executed marked with #####
+    pub(crate) fn annotate(&self, rom: &[u8], layout: ProgramLayout) -> String {
+        let mut text = String::from("  Hits  Address  Opcode  Instruction           Branches\n");
+        for (address, hits) in &self.hits {
+            let hits = if *hits == 0 {
+                "#####".to_string()
+            } else {
+                hits.to_string()
+            };
+            let (opcode, instruction) = match instruction_at(rom, layout, *address) {
+                Some((opcode, decoded)) => (format!("{:04X}", opcode), decoded.to_string()),
+                None => ("----".to_string(), "(outside the ROM)".to_string()),
+            };
+            let mut line = format!(
+                "{:>6}  {:#06X}   {}    {:20}",
+                hits, address, opcode, instruction
+            );
+            if let Some(branch) = self.branches.get(address) {
+                line.push_str(&format!(
+                    "  skipped {}, not skipped {}",
+                    branch.taken, branch.not_taken
+                ));
+            }
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Returns the coverage rates, the instructions never executed and the branches never taken
+    pub(crate) fn summary(&self, rom: &[u8], layout: ProgramLayout) -> String {
+        let ((executed, instructions), (taken, branches)) = self.totals();
+        let rate = |count: usize, total: usize| 100.0 * count as f64 / total.max(1) as f64;
+        let mut text = format!(
+            "Summary coverage rate:\n  instructions..: {:.1}% ({} of {})\n  branches......: {:.1}% ({} of {})\n",
+            rate(executed, instructions),
+            executed,
+            instructions,
+            rate(taken, branches),
+            taken,
+            branches
+        );
+
+        // Consecutive instructions never executed are shown as a range
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for (address, _) in self.hits.iter().filter(|(_, hits)| **hits == 0) {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + OPCODE_SIZE == *address => *end = *address,
+                _ => ranges.push((*address, *address)),
+            }
+        }
+        if !ranges.is_empty() {
+            let ranges: Vec<String> = ranges
+                .iter()
+                .map(|(start, end)| {
+                    if start == end {
+                        format!("{:#06X}", start)
+                    } else {
+                        format!("{:#06X}-{:#06X}", start, end)
+                    }
+                })
+                .collect();
+            text.push_str(&format!("Never executed: {}\n", ranges.join(", ")));
+        }
+
+        let never_taken: Vec<String> = self
+            .branches
+            .iter()
+            .filter_map(|(address, branch)| {
+                let outcome = match (branch.taken, branch.not_taken) {
+                    (0, 0) => "never executed",
+                    (0, _) => "never skipped",
+                    (_, 0) => "always skipped",
+                    _ => return None,
+                };
+                let instruction = instruction_at(rom, layout, *address)
+                    .map(|(_, decoded)| decoded.to_string())
+                    .unwrap_or_default();
+                Some(format!(
+                    "  {:#06X}  {:20}  {}\n",
+                    address, instruction, outcome
+                ))
+            })
+            .collect();
+        if !never_taken.is_empty() {
+            text.push_str("Branches never taken:\n");
+            text.push_str(&never_taken.concat());
+        }
+        text
+    }
+}
+
+/// Returns the opcode at an address of the ROM, loaded at the load address of the layout
+fn instruction_at(
+    rom: &[u8],
+    layout: ProgramLayout,
+    address: usize,
+) -> Option<(u16, DecodedOpcode)> {
+    let offset = address.checked_sub(layout.load_address)?;
+    let bytes = rom.get(offset..offset + OPCODE_SIZE)?;
+    let opcode = (bytes[0] as u16) << 8 | bytes[1] as u16;
+    Some((opcode, DecodedOpcode::new(opcode)))
+}
+
+/// Returns the name of a ROM in the tracefiles, its absolute path when it can be resolved,
+/// so that runs started from different directories are merged
+pub(crate) fn source_name(rom: &str) -> String {
+    fs::canonicalize(rom)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| rom.to_string())
+}
+
+/// Coverage of ROMs in the lcov format, one record per ROM
+#[derive(Debug, Default)]
+pub(crate) struct Tracefile {
+    records: Vec<(String, Coverage)>,
+}
+
+impl Tracefile {
+    /// Reads a tracefile, an absent file counting as an empty one
+    pub(crate) fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path).map_err(|e| {
+            format!(
+                "Error: coverage file {} not readable: {}",
+                path.display(),
+                e
+            )
+        })?;
+        Self::parse(&text).map_err(|e| format!("Error: coverage file {}: {}", path.display(), e))
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        let mut tracefile = Tracefile::default();
+        let mut current: Option<(String, Coverage)> = None;
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: &str| format!("line {}: {}", index + 1, message);
+            let line = line.trim();
+            let (key, value) = line.split_once(':').unwrap_or((line, ""));
+            let fields: Vec<&str> = value.split(',').collect();
+            let number = |field: &str| {
+                field
+                    .parse::<u64>()
+                    .map_err(|_| error(&format!("invalid number '{}'", field)))
+            };
+
+            match (key, current.as_mut()) {
+                ("SF", _) => current = Some((value.to_string(), Coverage::new())),
+                ("DA", Some((_, coverage))) => {
+                    let [address, hits, ..] = fields[..] else {
+                        return Err(error("expected DA:<address>,<hits>"));
+                    };
+                    *coverage.hits.entry(number(address)? as usize).or_default() += number(hits)?;
+                }
+                ("BRDA", Some((_, coverage))) => {
+                    let [address, _, side, count] = fields[..] else {
+                        return Err(error("expected BRDA:<address>,<block>,<branch>,<taken>"));
+                    };
+                    // Branches of instructions never executed are counted as '-'
+                    let count = if count == "-" { 0 } else { number(count)? };
+                    let branch = coverage
+                        .branches
+                        .entry(number(address)? as usize)
+                        .or_default();
+                    match side {
+                        "0" => branch.taken += count,
+                        "1" => branch.not_taken += count,
+                        _ => return Err(error(&format!("invalid branch '{}'", side))),
+                    }
+                }
+                ("end_of_record", _) => {
+                    if let Some((source, coverage)) = current.take() {
+                        tracefile.merge(&source, &coverage);
+                    }
+                }
+                ("DA" | "BRDA", None) => return Err(error("expected SF before the counts")),
+                _ => {}
+            }
+        }
+        if let Some((source, coverage)) = current {
+            tracefile.merge(&source, &coverage);
+        }
+        Ok(tracefile)
+    }
+
+    /// Adds the coverage of a ROM to its record
+    pub(crate) fn merge(&mut self, source: &str, coverage: &Coverage) {
+        match self.records.iter_mut().find(|(name, _)| name == source) {
+            Some((_, merged)) => merged.merge(coverage),
+            None => self.records.push((source.to_string(), coverage.clone())),
+        }
+    }
+
+    pub(crate) fn coverage(&self, source: &str) -> Option<&Coverage> {
+        self.records
+            .iter()
+            .find(|(name, _)| name == source)
+            .map(|(_, coverage)| coverage)
+    }
+
+    pub(crate) fn to_lcov(&self) -> String {
+        let mut text = String::new();
+        for (source, coverage) in &self.records {
+            text.push_str(&format!("SF:{}\n", source));
+            for (address, hits) in &coverage.hits {
+                text.push_str(&format!("DA:{},{}\n", address, hits));
+            }
+            for (address, branch) in &coverage.branches {
+                let never_executed = coverage.hits.get(address).is_some_and(|hits| *hits == 0);
+                for (side, count) in [branch.taken, branch.not_taken].iter().enumerate() {
+                    let count = if never_executed {
+                        "-".to_string()
+                    } else {
+                        count.to_string()
+                    };
+                    text.push_str(&format!("BRDA:{},0,{},{}\n", address, side, count));
+                }
+            }
+            let ((executed, instructions), (taken, branches)) = coverage.totals();
+            text.push_str(&format!(
+                "LF:{}\nLH:{}\nBRF:{}\nBRH:{}\nend_of_record\n",
+                instructions, executed, branches, taken
+            ));
+        }
+        text
+    }
+
+    /// Merges the coverage of a run into a tracefile, created if needed
+    pub(crate) fn update(path: &Path, source: &str, coverage: &Coverage) -> Result<(), String> {
+        let mut tracefile = Self::load(path)?;
+        tracefile.merge(source, coverage);
+        fs::write(path, tracefile.to_lcov()).map_err(|e| {
+            format!(
+                "Error: coverage file {} not writable: {}",
+                path.display(),
+                e
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "./coverage_test.rs"]
+mod coverage_test;
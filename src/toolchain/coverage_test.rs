@@ -0,0 +1,77 @@
+use super::*;
+
+// SE V0, 0x00 / JP 0x200 / LD V1, 0x01 / JP 0x206
+const ROM: [u8; 8] = [0x30, 0x00, 0x12, 0x00, 0x61, 0x01, 0x12, 0x06];
+
+// The skip at 0x200 always skips to 0x204, which loops on 0x206
+fn build_coverage() -> Coverage {
+    let mut coverage = Coverage::new();
+    coverage.record(0x200, true, 0x204);
+    coverage.record(0x204, false, 0x206);
+    coverage.record(0x206, false, 0x206);
+    coverage.record(0x206, false, 0x206);
+    coverage.add_rom_instructions(&ROM, ROM.len(), ProgramLayout::default());
+    coverage
+}
+
+#[test]
+fn test_annotated_disassembly() {
+    let coverage = build_coverage();
+    assert_eq!(
+        coverage.annotate(&ROM, ProgramLayout::default()),
+        concat!(
+            "  Hits  Address  Opcode  Instruction           Branches\n",
+            "     1  0x0200   3000    SE V0, 0x00           skipped 1, not skipped 0\n",
+            " #####  0x0202   1200    JP 0x0200\n",
+            "     1  0x0204   6101    LD V1, 0x01\n",
+            "     2  0x0206   1206    JP 0x0206\n"
+        )
+    );
+}
+
+#[test]
+fn test_summary() {
+    let summary = build_coverage().summary(&ROM, ProgramLayout::default());
+    assert!(summary.contains("  instructions..: 75.0% (3 of 4)\n"));
+    assert!(summary.contains("  branches......: 50.0% (1 of 2)\n"));
+    assert!(summary.contains("Never executed: 0x0202\n"));
+    assert!(summary.contains("  0x0200  SE V0, 0x00           always skipped\n"));
+}
+
+#[test]
+fn test_tracefile_round_trip_merges_runs() {
+    let coverage = build_coverage();
+    let mut tracefile = Tracefile::default();
+    tracefile.merge("/roms/game.ch8", &coverage);
+    let lcov = tracefile.to_lcov();
+    assert!(lcov.starts_with("SF:/roms/game.ch8\nDA:512,1\nDA:514,0\n"));
+    assert!(lcov.contains("BRDA:512,0,0,1\nBRDA:512,0,1,0\nLF:4\nLH:3\nBRF:2\nBRH:1\n"));
+
+    // A second run adds its counts to the same record
+    let mut tracefile = Tracefile::parse(&lcov).unwrap();
+    let mut second = Coverage::new();
+    second.record(0x200, true, 0x202);
+    tracefile.merge("/roms/game.ch8", &second);
+    let merged = tracefile.coverage("/roms/game.ch8").unwrap();
+    assert_eq!(merged.hits[&0x200], 2);
+    assert_eq!(
+        merged.branches[&0x200],
+        Branch {
+            taken: 1,
+            not_taken: 1
+        }
+    );
+    assert_eq!(tracefile.coverage("/roms/other.ch8"), None);
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(
+        Tracefile::parse("DA:512,1\n").unwrap_err(),
+        "line 1: expected SF before the counts"
+    );
+    assert_eq!(
+        Tracefile::parse("SF:game.ch8\nDA:512,x\n").unwrap_err(),
+        "line 2: invalid number 'x'"
+    );
+}
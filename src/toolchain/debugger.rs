@@ -4,13 +4,14 @@ use std::collections::{HashSet, VecDeque};
 use super::decoder::DecodedOpcode;
 
 use crate::constants::{
-    RESERVED_MEMORY_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, SEGMENTS_AFTER_PROGRAM_COUNTER, STACK_SIZE,
-    V_REGISTERS_SIZE,
+    SCREEN_HEIGHT, SCREEN_WIDTH, SEGMENTS_AFTER_PROGRAM_COUNTER, STACK_SIZE, V_REGISTERS_SIZE,
 };
+use crate::motherboard::layout::ProgramLayout;
 
 pub(crate) struct Debugger {
     rom: Vec<u8>,                     // The memory where the rom is loaded
     rom_size: usize,                  // Size of the rom loaded
+    layout: ProgramLayout,            // Addresses the rom is loaded at and started from
     label_addresses: HashSet<usize>,  // List of labels for this rom
     opcode_addresses: HashSet<usize>, // List of opcodes for this rom
     current_address: usize,           // Current address in the rom
@@ -21,18 +22,35 @@ impl Debugger {
         Self {
             rom: Vec::new(),
             rom_size: 0,
+            layout: ProgramLayout::default(),
             label_addresses: HashSet::new(),
             opcode_addresses: HashSet::new(),
             current_address: 0,
         }
     }
 
-    pub(crate) fn reset(&mut self, rom_bytes: &[u8], rom_size: usize) {
+    pub(crate) fn reset(&mut self, rom_bytes: &[u8], rom_size: usize, layout: ProgramLayout) {
+        self.layout = layout;
         self.disassemble(rom_bytes, rom_size);
     }
 
+    pub(crate) fn instruction_count(&self) -> usize {
+        self.opcode_addresses.len()
+    }
+
+    pub(crate) fn label_count(&self) -> usize {
+        self.label_addresses.len()
+    }
+
+    /// Returns the addresses of the instructions found by the disassembler, in order
+    pub(crate) fn instruction_addresses(&self) -> Vec<usize> {
+        let mut addresses: Vec<usize> = self.opcode_addresses.iter().copied().collect();
+        addresses.sort_unstable();
+        addresses
+    }
+
     fn fetch_opcode(&self, address: usize) -> u16 {
-        let offset = address - RESERVED_MEMORY_SIZE;
+        let offset = address - self.layout.load_address;
         (self.rom[offset] as u16) << 8 | (self.rom[offset + 1] as u16)
     }
 
@@ -41,12 +59,14 @@ impl Debugger {
         self.rom_size = rom_size;
 
         let mut segments = VecDeque::new();
-        segments.push_back(RESERVED_MEMORY_SIZE);
+        segments.push_back(self.layout.entry_address);
 
         while let Some(segment) = segments.pop_front() {
             self.current_address = segment;
 
-            while self.current_address < self.rom_size + RESERVED_MEMORY_SIZE
+            // Jumps outside the ROM, such as into the interpreter area, are not followed
+            while (self.layout.load_address..self.layout.end(self.rom_size))
+                .contains(&self.current_address)
                 && !self.opcode_addresses.contains(&self.current_address)
             {
                 let opcode = self.fetch_opcode(self.current_address);
@@ -91,13 +111,13 @@ impl Debugger {
 
     fn get_registers_status(v_registers: &[u8; V_REGISTERS_SIZE]) -> String {
         let mut registers_status = String::new();
-        for i in 0..V_REGISTERS_SIZE {
-            let register_value = format!("{:02X}", v_registers[i]);
+        for (i, register) in v_registers.iter().enumerate() {
+            let register_value = format!("{:02X}", register);
             registers_status.push_str(&format!("| V{:X}: {}        ", i, register_value));
             if i % 4 == 3 {
                 registers_status.push_str(" |");
                 if i < V_REGISTERS_SIZE - 1 {
-                    registers_status.push_str("\n");
+                    registers_status.push('\n');
                 }
             }
         }
@@ -112,7 +132,7 @@ impl Debugger {
             if i % 4 == 3 {
                 keypad_status.push_str(" |");
                 if i < 15 {
-                    keypad_status.push_str("\n");
+                    keypad_status.push('\n');
                 }
             }
         }
@@ -121,13 +141,13 @@ impl Debugger {
 
     fn get_stack_status(stack: &[usize; STACK_SIZE]) -> String {
         let mut stack_status = String::new();
-        for i in 0..STACK_SIZE {
-            let stack_value = format!("{:#06X}", stack[i]);
+        for (i, address) in stack.iter().enumerate() {
+            let stack_value = format!("{:#06X}", address);
             stack_status.push_str(&format!("| SP{:02}: {}  ", i, stack_value));
             if i % 4 == 3 {
                 stack_status.push_str(" |");
                 if i < STACK_SIZE - 1 {
-                    stack_status.push_str("\n");
+                    stack_status.push('\n');
                 }
             }
         }
@@ -147,17 +167,17 @@ impl Debugger {
         )
     }
 
-    fn get_screen_status(screen_pixels: &[bool]) -> String {
+    pub(crate) fn get_screen_status(screen_pixels: &[bool]) -> String {
         let mut screen_status = String::new();
         for y in 0..SCREEN_HEIGHT {
-            screen_status.push_str("|");
+            screen_status.push('|');
             for x in 0..SCREEN_WIDTH {
                 let idx = x + SCREEN_WIDTH * y;
                 screen_status.push_str(if screen_pixels[idx] { "X" } else { " " });
             }
-            screen_status.push_str("|");
+            screen_status.push('|');
             if y < SCREEN_HEIGHT - 1 {
-                screen_status.push_str("\n");
+                screen_status.push('\n');
             }
         }
         screen_status
@@ -192,7 +212,7 @@ impl Debugger {
     ) -> String {
         let address_string = format!("{:#06X}", address);
         let opcode_string = format!("{:04X}", opcode);
-        let decoded_string = format!("{:20}", decoded.to_string());
+        let decoded_string = format!("{:20}", decoded);
 
         let mut status = format!(
             "| AD: {}    | OPCODE: {}  | DECODED: {}  |",
@@ -223,13 +243,13 @@ impl Debugger {
         for (i, byte) in self.rom.iter().enumerate() {
             if i < self.rom_size {
                 status.push_str(&Self::get_byte_status(
-                    i + RESERVED_MEMORY_SIZE,
+                    i + self.layout.load_address,
                     *byte,
                     None,
                     None,
                 ));
                 if i < self.rom_size - 1 {
-                    status.push_str("\n");
+                    status.push('\n');
                 }
             } else {
                 break;
@@ -238,15 +258,15 @@ impl Debugger {
         status
     }
 
-    fn get_disassembled_rom(&mut self) -> String {
-        self.current_address = RESERVED_MEMORY_SIZE;
+    pub(crate) fn get_disassembled_rom(&mut self) -> String {
+        self.current_address = self.layout.load_address;
         let mut output = String::new();
 
-        while self.current_address < self.rom_size + RESERVED_MEMORY_SIZE {
+        while self.current_address < self.layout.end(self.rom_size) {
             // Output the byte status
             if !self.opcode_addresses.contains(&self.current_address) {
                 let address = self.current_address;
-                let byte = self.rom[self.current_address - RESERVED_MEMORY_SIZE];
+                let byte = self.rom[self.current_address - self.layout.load_address];
                 let (highlight_address, highlight_symbol) =
                     if self.label_addresses.contains(&address) {
                         // Output the label status
@@ -268,7 +288,7 @@ impl Debugger {
             let decoded = &DecodedOpcode::new(opcode);
             output.push_str(&format!(
                 "{}\n",
-                Self::get_opcode_status(address, opcode, &decoded, None, None)
+                Self::get_opcode_status(address, opcode, decoded, None, None)
             ));
             self.current_address += 2;
         }
@@ -288,12 +308,13 @@ impl Debugger {
         let mut address = program_counter;
         let mut count = 0;
 
-        while address < self.rom_size + RESERVED_MEMORY_SIZE && count < total {
+        // The program counter may be outside the ROM, in the interpreter area
+        while self.layout.offset(address, self.rom_size).is_some() && count < total {
             count += 1;
 
             // Output the byte status
             if !self.opcode_addresses.contains(&address) {
-                let byte = self.rom[address - RESERVED_MEMORY_SIZE];
+                let byte = self.rom[address - self.layout.load_address];
                 output.push_str(&format!(
                     "{}\n",
                     Self::get_byte_status(address, byte, None, None)
@@ -310,7 +331,7 @@ impl Debugger {
                 Self::get_opcode_status(
                     address,
                     opcode,
-                    &decoded,
+                    decoded,
                     Some(program_counter),
                     Some("* PC".to_string())
                 )
@@ -344,6 +365,7 @@ impl Debugger {
         );
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn print_processor_status(
         &mut self,
         screen_pixels: &[bool],
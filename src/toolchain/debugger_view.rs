@@ -0,0 +1,265 @@
+use std::collections::BTreeSet;
+
+use super::decoder::DecodedOpcode;
+
+use crate::constants::{MEMORY_SIZE, OPCODE_SIZE};
+use crate::motherboard::processor::Processor;
+use crate::peripherals::keymap::KEYPAD_LAYOUT;
+
+pub(crate) const COLUMNS: usize = 80; // Width of the debugger panels in characters
+pub(crate) const ROWS: usize = 34; // Height of the debugger panels in lines
+
+const DISASSEMBLY_COLUMN: usize = 44;
+const DISASSEMBLY_LINES: usize = 20;
+const INSTRUCTIONS_BEFORE_PC: usize = 4; // Instructions shown above the program counter
+
+const MEMORY_ROW: usize = 22;
+const MEMORY_LINES: usize = 4; // Lines of the hex view around I and around PC
+const BYTES_PER_LINE: usize = 16;
+
+// The memory map is drawn by the window under its heading, one font pixel per address
+pub(crate) const MEMORY_MAP_COLUMN: usize = 60;
+pub(crate) const MEMORY_MAP_ROW: usize = MEMORY_ROW;
+
+/// How a piece of text of the debugger panels is drawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TextStyle {
+    Normal,
+    Heading,
+
+    /// Instruction or bytes at the program counter, bytes at I
+    Current,
+
+    /// Instruction with a breakpoint
+    Breakpoint,
+
+    /// Key held down
+    Pressed,
+}
+
+/// Piece of text of the debugger panels, at a position in characters
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DebuggerText {
+    pub(crate) column: usize,
+    pub(crate) row: usize,
+    pub(crate) text: String,
+    pub(crate) style: TextStyle,
+}
+
+/// Live view of the processor: registers, timers, stack, keypad, the disassembly around the
+/// program counter, the memory around I and PC and the heat map of the memory accesses,
+/// with breakpoints set by clicking instructions
+pub(crate) struct DebuggerView {
+    breakpoints: BTreeSet<usize>,
+
+    /// Breakpoint the game was last stopped at, until the program counter leaves it
+    stopped_at: Option<usize>,
+
+    /// Rows of the disassembly on the last layout, with the address of their instruction
+    instruction_rows: Vec<(usize, usize)>,
+}
+
+impl DebuggerView {
+    pub(crate) fn new() -> Self {
+        DebuggerView {
+            breakpoints: BTreeSet::new(),
+            stopped_at: None,
+            instruction_rows: Vec::new(),
+        }
+    }
+
+    /// Sets or clears the breakpoint of the instruction at the given position in characters,
+    /// returning its address and whether the breakpoint is now set
+    pub(crate) fn click(&mut self, column: usize, row: usize) -> Option<(usize, bool)> {
+        if column < DISASSEMBLY_COLUMN {
+            return None;
+        }
+        let address = self
+            .instruction_rows
+            .iter()
+            .find(|(instruction_row, _)| *instruction_row == row)
+            .map(|(_, address)| *address)?;
+        let set = self.breakpoints.insert(address);
+        if !set {
+            self.breakpoints.remove(&address);
+        }
+        Some((address, set))
+    }
+
+    /// Returns true if the game should stop before the instruction at the program counter,
+    /// only once per arrival at a breakpoint so that the game can be resumed from it
+    pub(crate) fn should_break(&mut self, program_counter: usize) -> bool {
+        if self.stopped_at == Some(program_counter) {
+            return false;
+        }
+        self.stopped_at = None;
+        if self.breakpoints.contains(&program_counter) {
+            self.stopped_at = Some(program_counter);
+            return true;
+        }
+        false
+    }
+
+    /// Returns the text of the panels for the current state of the processor
+    pub(crate) fn layout(&mut self, processor: &Processor) -> Vec<DebuggerText> {
+        let mut texts = Vec::new();
+        let mut push = |column: usize, row: usize, text: String, style: TextStyle| {
+            texts.push(DebuggerText {
+                column,
+                row,
+                text,
+                style,
+            })
+        };
+
+        push(0, 0, "REGISTERS".to_string(), TextStyle::Heading);
+        push(
+            0,
+            1,
+            format!(
+                "PC {:#06X}  I {:#06X}  SP {:02}",
+                processor.program_counter, processor.i_register, processor.stack_pointer
+            ),
+            TextStyle::Normal,
+        );
+        push(
+            0,
+            2,
+            format!(
+                "DT {:02}  ST {:02}",
+                processor.delay_timer, processor.sound_timer
+            ),
+            TextStyle::Normal,
+        );
+        for (line, registers) in processor.v_registers.chunks(4).enumerate() {
+            let text: Vec<String> = registers
+                .iter()
+                .enumerate()
+                .map(|(index, value)| format!("V{:X} {:02X}", line * 4 + index, value))
+                .collect();
+            push(0, 3 + line, text.join("  "), TextStyle::Normal);
+        }
+
+        push(0, 8, "STACK".to_string(), TextStyle::Heading);
+        for (index, address) in processor.stack.iter().enumerate() {
+            // The return address of the current subroutine stands out
+            let style = if index + 1 == processor.stack_pointer {
+                TextStyle::Current
+            } else {
+                TextStyle::Normal
+            };
+            push(
+                (index % 4) * 11,
+                9 + index / 4,
+                format!("{:02} {:#06X}", index, address),
+                style,
+            );
+        }
+
+        push(0, 14, "KEYPAD".to_string(), TextStyle::Heading);
+        for (position, key) in KEYPAD_LAYOUT.iter().enumerate() {
+            let style = if processor.keypad & (1 << key) != 0 {
+                TextStyle::Pressed
+            } else {
+                TextStyle::Normal
+            };
+            push(
+                (position % 4) * 2,
+                15 + position / 4,
+                format!("{:X}", key),
+                style,
+            );
+        }
+
+        push(
+            DISASSEMBLY_COLUMN,
+            0,
+            "DISASSEMBLY".to_string(),
+            TextStyle::Heading,
+        );
+        self.instruction_rows.clear();
+        let start = processor
+            .program_counter
+            .saturating_sub(INSTRUCTIONS_BEFORE_PC * OPCODE_SIZE);
+        let addresses = (start..MEMORY_SIZE - 1).step_by(OPCODE_SIZE);
+        for (line, address) in addresses.take(DISASSEMBLY_LINES).enumerate() {
+            let opcode = processor.fetch_opcode(address);
+            let current = address == processor.program_counter;
+            let breakpoint = self.breakpoints.contains(&address);
+            let style = match (current, breakpoint) {
+                (true, _) => TextStyle::Current,
+                (false, true) => TextStyle::Breakpoint,
+                (false, false) => TextStyle::Normal,
+            };
+            push(
+                DISASSEMBLY_COLUMN,
+                1 + line,
+                format!(
+                    "{}{} {:#06X}  {:04X}  {}",
+                    if current { '>' } else { ' ' },
+                    if breakpoint { '*' } else { ' ' },
+                    address,
+                    opcode,
+                    DecodedOpcode::new(opcode)
+                ),
+                style,
+            );
+            self.instruction_rows.push((1 + line, address));
+        }
+
+        push(
+            MEMORY_MAP_COLUMN,
+            MEMORY_MAP_ROW,
+            "MEMORY MAP".to_string(),
+            TextStyle::Heading,
+        );
+
+        let i_bytes = processor.i_register..processor.i_register + 1;
+        let pc_bytes = processor.program_counter..processor.program_counter + OPCODE_SIZE;
+        for (index, (heading, around, highlighted)) in [
+            ("MEMORY AT I", processor.i_register, i_bytes),
+            ("MEMORY AT PC", processor.program_counter, pc_bytes),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let row = MEMORY_ROW + index * (MEMORY_LINES + 2);
+            push(0, row, heading.to_string(), TextStyle::Heading);
+
+            // The line holding the address comes second, after the one before it
+            let first = (around / BYTES_PER_LINE)
+                .saturating_sub(1)
+                .min(MEMORY_SIZE / BYTES_PER_LINE - MEMORY_LINES)
+                * BYTES_PER_LINE;
+            for line in 0..MEMORY_LINES {
+                let line_address = first + line * BYTES_PER_LINE;
+                push(
+                    0,
+                    row + 1 + line,
+                    format!("{:#06X}", line_address),
+                    TextStyle::Heading,
+                );
+                for offset in 0..BYTES_PER_LINE {
+                    let address = line_address + offset;
+                    let style = if highlighted.contains(&address) {
+                        TextStyle::Current
+                    } else {
+                        TextStyle::Normal
+                    };
+                    push(
+                        7 + offset * 3,
+                        row + 1 + line,
+                        format!("{:02X}", processor.memory.load(address)),
+                        style,
+                    );
+                }
+            }
+        }
+
+        texts
+    }
+}
+
+#[cfg(test)]
+#[path = "./debugger_view_test.rs"]
+mod debugger_view_test;
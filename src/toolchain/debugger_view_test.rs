@@ -0,0 +1,97 @@
+use super::*;
+use crate::constants::RESERVED_MEMORY_SIZE;
+
+// LD V0, 0x05 / LD I, 0x0210 / ADD V0, 0x01 / JP 0x0204
+const ROM: [u8; 8] = [0x60, 0x05, 0xA2, 0x10, 0x70, 0x01, 0x12, 0x04];
+
+fn build_processor() -> Processor {
+    let mut processor = Processor::new();
+    processor
+        .memory
+        .reset(&ROM, ROM.len(), RESERVED_MEMORY_SIZE);
+    processor.program_counter = 0x202;
+    processor.i_register = 0x210;
+    processor
+}
+
+fn find<'a>(texts: &'a [DebuggerText], prefix: &str) -> &'a DebuggerText {
+    texts
+        .iter()
+        .find(|text| text.text.starts_with(prefix))
+        .unwrap_or_else(|| panic!("no text starting with {:?}", prefix))
+}
+
+#[test]
+fn test_disassembly_around_program_counter() {
+    let processor = build_processor();
+    let mut view = DebuggerView::new();
+    let texts = view.layout(&processor);
+
+    // Four instructions come before the program counter, from the reserved memory
+    let first = find(&texts, "   0x01FA");
+    assert_eq!((first.column, first.row), (DISASSEMBLY_COLUMN, 1));
+
+    let current = find(&texts, ">  0x0202");
+    assert_eq!(current.text, ">  0x0202  A210  LD I, 0x0210");
+    assert_eq!((current.row, current.style), (5, TextStyle::Current));
+
+    let registers = find(&texts, "PC ");
+    assert_eq!(registers.text, "PC 0x0202  I 0x0210  SP 00");
+}
+
+#[test]
+fn test_click_toggles_breakpoints() {
+    let processor = build_processor();
+    let mut view = DebuggerView::new();
+    view.layout(&processor);
+
+    assert_eq!(view.click(0, 6), None);
+    assert_eq!(view.click(DISASSEMBLY_COLUMN + 10, 6), Some((0x204, true)));
+
+    let texts = view.layout(&processor);
+    let breakpoint = find(&texts, " * 0x0204");
+    assert_eq!(breakpoint.style, TextStyle::Breakpoint);
+
+    assert_eq!(view.click(DISASSEMBLY_COLUMN, 6), Some((0x204, false)));
+    let texts = view.layout(&processor);
+    assert_eq!(find(&texts, "   0x0204").style, TextStyle::Normal);
+}
+
+#[test]
+fn test_breaks_once_per_arrival() {
+    let processor = build_processor();
+    let mut view = DebuggerView::new();
+    view.layout(&processor);
+    view.click(DISASSEMBLY_COLUMN, 6);
+
+    assert!(!view.should_break(0x202));
+    assert!(view.should_break(0x204));
+    // Resuming, or waiting for a key, at the breakpoint goes on
+    assert!(!view.should_break(0x204));
+    assert!(!view.should_break(0x206));
+    assert!(view.should_break(0x204));
+}
+
+#[test]
+fn test_memory_around_i_and_program_counter() {
+    let processor = build_processor();
+    let mut view = DebuggerView::new();
+    let texts = view.layout(&processor);
+
+    // The line of I comes after the line before it
+    let line = find(&texts, "0x0210");
+    assert_eq!((line.column, line.row), (0, MEMORY_ROW + 2));
+    let highlighted: Vec<(usize, usize, &str)> = texts
+        .iter()
+        .filter(|text| text.row >= MEMORY_ROW && text.style == TextStyle::Current)
+        .map(|text| (text.column, text.row, text.text.as_str()))
+        .collect();
+    assert_eq!(
+        highlighted,
+        [
+            (7, MEMORY_ROW + 2, "00"),
+            (13, MEMORY_ROW + 8, "A2"),
+            (16, MEMORY_ROW + 8, "10")
+        ]
+    );
+}
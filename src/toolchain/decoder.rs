@@ -1,3 +1,5 @@
+use std::fmt;
+
 pub enum DecodedOpcode {
     // 00E0 | CLS | Clear video memory
     Cls,
@@ -89,11 +91,11 @@ pub enum DecodedOpcode {
     // 8XY3 | XOR VX, VY | VX = VX XOR VY
     XorVxVy { vx: usize, vy: usize },
 
-    // 8XY6 | SHR VX | VF = LSB(VX); VX = VX >> 1
-    ShrVx { vx: usize },
+    // 8XY6 | SHR VX | VF = LSB(VX); VX = VX >> 1 (VY is shifted instead with the shift quirk)
+    ShrVx { vx: usize, vy: usize },
 
-    // 8XYE | SHL VX | VF = MSB(VX); VX = VX << 1
-    ShlVx { vx: usize },
+    // 8XYE | SHL VX | VF = MSB(VX); VX = VX << 1 (VY is shifted instead with the shift quirk)
+    ShlVx { vx: usize, vy: usize },
 
     // FX33 | BCD VX | Store BCD repr of VX at I (100), I+1 (10), and I+2 (1); 'I' remains unchanged
     BcdVx { vx: usize },
@@ -215,10 +217,10 @@ impl DecodedOpcode {
             (0x8, _, _, 0x3) => Self::XorVxVy { vx, vy },
 
             // OPCODE: 8XY6  => DECODED: SHR VX
-            (0x8, _, _, 0x6) => Self::ShrVx { vx },
+            (0x8, _, _, 0x6) => Self::ShrVx { vx, vy },
 
             // OPCODE: 8XYE  => DECODED: SHL VX
-            (0x8, _, _, 0xE) => Self::ShlVx { vx },
+            (0x8, _, _, 0xE) => Self::ShlVx { vx, vy },
 
             // OPCODE: FX33  => DECODED: BCD VX
             (0xF, _, 0x3, 0x3) => Self::BcdVx { vx },
@@ -233,9 +235,65 @@ impl DecodedOpcode {
             _ => Self::Unknown { opcode },
         }
     }
+    /// Returns true for the instructions skipping the next one on a condition
+    pub(crate) fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Self::SeVxNn { .. }
+                | Self::SneVxNn { .. }
+                | Self::SeVxVy { .. }
+                | Self::SneVxVy { .. }
+                | Self::SkpVx { .. }
+                | Self::SknpVx { .. }
+        )
+    }
 
-    pub(crate) fn to_string(&self) -> String {
+    /// Returns the name of the variant, such as "DrwVxVyN", to count opcodes by kind
+    pub(crate) fn variant(&self) -> &'static str {
         match self {
+            Self::Cls => "Cls",
+            Self::Ret => "Ret",
+            Self::SysNnn { .. } => "SysNnn",
+            Self::CallNnn { .. } => "CallNnn",
+            Self::JpNnn { .. } => "JpNnn",
+            Self::JpV0Nnn { .. } => "JpV0Nnn",
+            Self::SeVxNn { .. } => "SeVxNn",
+            Self::SneVxNn { .. } => "SneVxNn",
+            Self::SeVxVy { .. } => "SeVxVy",
+            Self::SneVxVy { .. } => "SneVxVy",
+            Self::SkpVx { .. } => "SkpVx",
+            Self::SknpVx { .. } => "SknpVx",
+            Self::LdVxK { .. } => "LdVxK",
+            Self::LdVxNn { .. } => "LdVxNn",
+            Self::LdVxVy { .. } => "LdVxVy",
+            Self::LdVxDt { .. } => "LdVxDt",
+            Self::LdDtVx { .. } => "LdDtVx",
+            Self::LdStVx { .. } => "LdStVx",
+            Self::LdINnn { .. } => "LdINnn",
+            Self::LdFVx { .. } => "LdFVx",
+            Self::LdAtIVx { .. } => "LdAtIVx",
+            Self::LdVxAtI { .. } => "LdVxAtI",
+            Self::AddIVx { .. } => "AddIVx",
+            Self::AddVxNn { .. } => "AddVxNn",
+            Self::AddVxVy { .. } => "AddVxVy",
+            Self::SubVxVy { .. } => "SubVxVy",
+            Self::SubnVxVy { .. } => "SubnVxVy",
+            Self::OrVxVy { .. } => "OrVxVy",
+            Self::AndVxVy { .. } => "AndVxVy",
+            Self::XorVxVy { .. } => "XorVxVy",
+            Self::ShrVx { .. } => "ShrVx",
+            Self::ShlVx { .. } => "ShlVx",
+            Self::BcdVx { .. } => "BcdVx",
+            Self::RndVxNn { .. } => "RndVxNn",
+            Self::DrwVxVyN { .. } => "DrwVxVyN",
+            Self::Unknown { .. } => "Unknown",
+        }
+    }
+}
+
+impl fmt::Display for DecodedOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
             Self::Cls => "CLS".to_string(),
             Self::Ret => "RET".to_string(),
             Self::SysNnn { nnn } => format!("SYS {:#06X}", nnn),
@@ -266,12 +324,13 @@ impl DecodedOpcode {
             Self::OrVxVy { vx, vy } => format!("OR V{:X}, V{:X}", vx, vy),
             Self::AndVxVy { vx, vy } => format!("AND V{:X}, V{:X}", vx, vy),
             Self::XorVxVy { vx, vy } => format!("XOR V{:X}, V{:X}", vx, vy),
-            Self::ShrVx { vx } => format!("SHR V{:X}", vx),
-            Self::ShlVx { vx } => format!("SHL V{:X}", vx),
+            Self::ShrVx { vx, .. } => format!("SHR V{:X}", vx),
+            Self::ShlVx { vx, .. } => format!("SHL V{:X}", vx),
             Self::BcdVx { vx } => format!("BCD V{:X}", vx),
             Self::RndVxNn { vx, nn } => format!("RND V{:X}, {:#04X}", vx, nn),
             Self::DrwVxVyN { vx, vy, n } => format!("DRW V{:X}, V{:X}, {:0}", vx, vy, n),
             Self::Unknown { opcode } => format!("UNKNOWN {:04X}", opcode),
-        }
+        };
+        f.pad(&text)
     }
 }
@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::Path;
+
+use crate::constants::MEMORY_SIZE;
+use crate::motherboard::memory::{Memory, FONT_SPRITES};
+use crate::peripherals::post_processing::Image;
+
+use super::screenshot::write_png;
+
+pub(crate) const MAP_WIDTH: usize = 64; // Addresses per row of the heat map, 64 rows for the 4 KiB
+const CELL_SIZE: usize = 8; // Size of an address in the PNG file, with a 1 pixel grid line
+const GRID_COLOR: [u8; 3] = [0, 0, 0];
+
+// Brightness of the addresses never accessed, and of the least accessed ones
+const UNTOUCHED_BRIGHTNESS: f32 = 0.3;
+const MIN_BRIGHTNESS: f32 = 0.45;
+
+/// Use of an address, shown by the hue of the heat map
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Region {
+    /// Built-in font sprites
+    Font,
+
+    /// Fetched as an instruction
+    Code,
+
+    /// Written by an instruction
+    Written,
+
+    /// Only read by instructions, such as sprites
+    Read,
+
+    /// Loaded from the ROM but never accessed
+    Rom,
+
+    /// Outside the ROM and never accessed
+    Free,
+}
+
+impl Region {
+    pub(crate) fn of(memory: &Memory, address: usize) -> Self {
+        let counts = memory.access_counts();
+        if address < FONT_SPRITES.len() {
+            Region::Font
+        } else if counts.fetches[address] > 0 {
+            Region::Code
+        } else if counts.writes[address] > 0 {
+            Region::Written
+        } else if counts.reads[address] > 0 {
+            Region::Read
+        } else if (memory.load_address()..memory.load_address() + memory.rom_size())
+            .contains(&address)
+        {
+            Region::Rom
+        } else {
+            Region::Free
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Region::Font => "font",
+            Region::Code => "code",
+            Region::Written => "written",
+            Region::Read => "read",
+            Region::Rom => "rom",
+            Region::Free => "free",
+        }
+    }
+
+    fn color(&self) -> [u8; 3] {
+        match self {
+            Region::Font => [80, 140, 255],
+            Region::Code => [65, 236, 157],
+            Region::Written => [255, 80, 80],
+            Region::Read => [255, 200, 60],
+            Region::Rom => [150, 150, 150],
+            Region::Free => [40, 40, 40],
+        }
+    }
+}
+
+/// Returns the colour of every address: the hue of its region,
+/// brighter the more it was accessed, on a logarithmic scale
+pub(crate) fn colors(memory: &Memory) -> Vec<[u8; 3]> {
+    let counts = memory.access_counts();
+    let max_total = (0..MEMORY_SIZE)
+        .map(|address| counts.total(address))
+        .max()
+        .unwrap_or(0);
+    let max_heat = (1.0 + max_total as f32).ln();
+
+    (0..MEMORY_SIZE)
+        .map(|address| {
+            let total = counts.total(address);
+            let brightness = if total == 0 {
+                UNTOUCHED_BRIGHTNESS
+            } else {
+                let heat = (1.0 + total as f32).ln() / max_heat;
+                MIN_BRIGHTNESS + (1.0 - MIN_BRIGHTNESS) * heat
+            };
+            Region::of(memory, address)
+                .color()
+                .map(|channel| (channel as f32 * brightness).round() as u8)
+        })
+        .collect()
+}
+
+/// Returns the heat map as an image, 64 addresses per row
+pub(crate) fn render(memory: &Memory) -> Image {
+    let width = MAP_WIDTH * CELL_SIZE;
+    let height = MEMORY_SIZE / MAP_WIDTH * CELL_SIZE;
+    let colors = colors(memory);
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let on_grid = x % CELL_SIZE == CELL_SIZE - 1 || y % CELL_SIZE == CELL_SIZE - 1;
+            let address = (y / CELL_SIZE) * MAP_WIDTH + x / CELL_SIZE;
+            pixels.extend(if on_grid { GRID_COLOR } else { colors[address] });
+        }
+    }
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// Returns one line per address with its region and access counts, after a header
+pub(crate) fn to_csv(memory: &Memory) -> String {
+    let counts = memory.access_counts();
+    let mut csv = String::from("address,region,reads,writes,fetches\n");
+    for address in 0..MEMORY_SIZE {
+        csv.push_str(&format!(
+            "{:#06X},{},{},{},{}\n",
+            address,
+            Region::of(memory, address).name(),
+            counts.reads[address],
+            counts.writes[address],
+            counts.fetches[address]
+        ));
+    }
+    csv
+}
+
+/// File formats of the memory accesses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HeatMapFormat {
+    Png,
+    Csv,
+}
+
+impl HeatMapFormat {
+    /// Returns the format of a file from its extension, checked before running the ROM
+    pub(crate) fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("png") => Ok(HeatMapFormat::Png),
+            Some("csv") => Ok(HeatMapFormat::Csv),
+            _ => Err(format!(
+                "Error: heat map file {} must end with .png or .csv",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// Writes the memory accesses to a PNG heat map or a CSV file, following the file extension
+pub(crate) fn save(path: &Path, memory: &Memory) -> Result<(), String> {
+    match HeatMapFormat::from_path(path)? {
+        HeatMapFormat::Png => write_png(path, &render(memory)),
+        HeatMapFormat::Csv => fs::write(path, to_csv(memory))
+            .map_err(|e| format!("Error: CSV file {} not writable: {}", path.display(), e)),
+    }
+}
+
+#[cfg(test)]
+#[path = "./heat_map_test.rs"]
+mod heat_map_test;
@@ -0,0 +1,90 @@
+use super::*;
+use crate::constants::RESERVED_MEMORY_SIZE;
+
+// Two instructions fetched, a byte of sprite read from the ROM, and a byte written after it
+fn build_memory() -> Memory {
+    let mut memory = Memory::new();
+    memory.reset(
+        &[0xA2, 0x06, 0x12, 0x02, 0x00, 0x00, 0xFF],
+        7,
+        RESERVED_MEMORY_SIZE,
+    );
+    memory.fetch(0x200);
+    for _ in 0..7 {
+        memory.fetch(0x202);
+    }
+    memory.read(0x206);
+    memory.store(0x207, 1);
+    memory
+}
+
+#[test]
+fn test_regions() {
+    let memory = build_memory();
+    let regions: Vec<Region> = [0x000, 0x100, 0x200, 0x203, 0x204, 0x206, 0x207, 0x208]
+        .iter()
+        .map(|address| Region::of(&memory, *address))
+        .collect();
+    assert_eq!(
+        regions,
+        [
+            Region::Font,
+            Region::Free,
+            Region::Code,
+            Region::Code,
+            Region::Rom,
+            Region::Read,
+            Region::Written,
+            Region::Free
+        ]
+    );
+}
+
+#[test]
+fn test_csv() {
+    let csv = to_csv(&build_memory());
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), MEMORY_SIZE + 1);
+    assert_eq!(lines[0], "address,region,reads,writes,fetches");
+    assert_eq!(lines[1 + 0x203], "0x0203,code,0,0,7");
+    assert_eq!(lines[1 + 0x206], "0x0206,read,1,0,0");
+    assert_eq!(lines[1 + 0x207], "0x0207,written,0,1,0");
+}
+
+#[test]
+fn test_render() {
+    let image = render(&build_memory());
+    assert_eq!((image.width, image.height), (512, 512));
+
+    let pixel = |address: usize| {
+        let (x, y) = (
+            (address % MAP_WIDTH) * CELL_SIZE,
+            (address / MAP_WIDTH) * CELL_SIZE,
+        );
+        let offset = (x + y * image.width) * 3;
+        [
+            image.pixels[offset],
+            image.pixels[offset + 1],
+            image.pixels[offset + 2],
+        ]
+    };
+    // The most accessed address is drawn at full brightness, the untouched ones dimmed
+    assert_eq!(pixel(0x202), Region::Code.color());
+    assert_eq!(pixel(0x100), [12, 12, 12]);
+    assert_eq!(pixel(0x000), [24, 42, 77]);
+    assert!(pixel(0x200)[1] < Region::Code.color()[1]);
+    assert_eq!(image.pixels[(CELL_SIZE - 1) * 3..CELL_SIZE * 3], GRID_COLOR);
+}
+
+#[test]
+fn test_format_from_extension() {
+    assert_eq!(
+        HeatMapFormat::from_path(Path::new("map.PNG")),
+        Ok(HeatMapFormat::Png)
+    );
+    assert_eq!(
+        HeatMapFormat::from_path(Path::new("map.csv")),
+        Ok(HeatMapFormat::Csv)
+    );
+    assert!(HeatMapFormat::from_path(Path::new("map.txt")).is_err());
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::peripherals::input_driver::KeyEvent;
+use crate::peripherals::keymap::parse_keypad_key;
+
+//  Key presses and releases fed to a headless run, one per line:
+//
+//  # frame  event    key
+//  60       press    5
+//  64       release  5
+//
+//  Frames count from 0, events happen before the first instruction of their frame.
+//  Everything after a '#' is ignored.
+
+/// Scripted keypad input for headless runs
+#[derive(Debug, Default)]
+pub(crate) struct InputScript {
+    events: BTreeMap<usize, Vec<KeyEvent>>,
+}
+
+impl InputScript {
+    pub(crate) fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Error: input script {} not readable: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("Error: input script {}: {}", path, e))
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        let mut script = InputScript::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", index + 1, message);
+
+            let [frame, event, key] = fields[..] else {
+                return Err(error("expected <frame> press|release <key>".to_string()));
+            };
+            let frame = frame
+                .parse::<usize>()
+                .map_err(|_| error(format!("invalid frame '{}'", frame)))?;
+            let key = parse_keypad_key(key).map_err(error)?;
+            let event = match event.to_lowercase().as_str() {
+                "press" => KeyEvent::Pressed(key),
+                "release" => KeyEvent::Released(key),
+                _ => {
+                    return Err(error(format!(
+                        "invalid event '{}', expected press or release",
+                        event
+                    )))
+                }
+            };
+            script.events.entry(frame).or_default().push(event);
+        }
+        Ok(script)
+    }
+
+    /// Returns the key events of a frame, in script order
+    pub(crate) fn events_at(&self, frame: usize) -> &[KeyEvent] {
+        self.events.get(&frame).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+#[path = "./input_script_test.rs"]
+mod input_script_test;
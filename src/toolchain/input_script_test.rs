@@ -0,0 +1,35 @@
+use super::*;
+
+#[test]
+fn test_parse_groups_events_by_frame() {
+    let script = InputScript::parse(
+        "# Jump twice\n\
+         10 press 5\n\
+         12 release 5   # short press\n\
+         \n\
+         12 PRESS a\n\
+         20 release A\n",
+    )
+    .unwrap();
+
+    assert_eq!(script.events_at(10), [KeyEvent::Pressed(5)]);
+    assert_eq!(
+        script.events_at(12),
+        [KeyEvent::Released(5), KeyEvent::Pressed(0xA)]
+    );
+    assert_eq!(script.events_at(20), [KeyEvent::Released(0xA)]);
+    assert!(script.events_at(11).is_empty());
+}
+
+#[test]
+fn test_parse_reports_the_line() {
+    assert_eq!(
+        InputScript::parse("1 press 5\n2 hold 5").unwrap_err(),
+        "line 2: invalid event 'hold', expected press or release"
+    );
+    assert_eq!(
+        InputScript::parse("press 5").unwrap_err(),
+        "line 1: expected <frame> press|release <key>"
+    );
+    assert!(InputScript::parse("1 press 10").is_err());
+}
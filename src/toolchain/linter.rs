@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+use crate::constants::{OPCODE_SIZE, RESERVED_MEMORY_SIZE, STACK_SIZE};
+use crate::motherboard::layout::ProgramLayout;
+
+use super::debugger::Debugger;
+use super::decoder::DecodedOpcode;
+
+/// Likely bug found at an instruction of a ROM
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Finding {
+    pub(crate) address: usize,
+    pub(crate) opcode: u16,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:#06X}  {:04X}  {:20}  {}",
+            self.address,
+            self.opcode,
+            DecodedOpcode::new(self.opcode),
+            self.message
+        )
+    }
+}
+
+/// Instructions reachable from a subroutine entry without following its calls
+#[derive(Default)]
+struct Subroutine {
+    /// A RET is reachable, or a BNNN the analysis cannot follow
+    may_return: bool,
+
+    /// Addresses of the CALL instructions and the subroutines they call
+    calls: Vec<(usize, usize)>,
+}
+
+/// ROM loaded at its load address, as the disassembler sees it
+struct Rom<'a> {
+    bytes: &'a [u8],
+    size: usize,
+    layout: ProgramLayout,
+}
+
+impl Rom<'_> {
+    fn start(&self) -> usize {
+        self.layout.load_address
+    }
+
+    fn end(&self) -> usize {
+        self.layout.end(self.size)
+    }
+
+    fn opcode(&self, address: usize) -> Option<u16> {
+        if address < self.start() || address + OPCODE_SIZE > self.end() {
+            return None;
+        }
+        let offset = address - self.start();
+        Some((self.bytes[offset] as u16) << 8 | self.bytes[offset + 1] as u16)
+    }
+
+    fn finding(&self, address: usize, message: String) -> Finding {
+        Finding {
+            address,
+            opcode: self.opcode(address).unwrap_or_default(),
+            message,
+        }
+    }
+
+    /// Follows the instructions from an entry, a CALL going on with the next instruction
+    fn subroutine(&self, entry: usize) -> Subroutine {
+        let mut subroutine = Subroutine::default();
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::from([entry]);
+        while let Some(address) = queue.pop_front() {
+            if !visited.insert(address) {
+                continue;
+            }
+            let Some(opcode) = self.opcode(address) else {
+                continue;
+            };
+            let next = address + OPCODE_SIZE;
+            match DecodedOpcode::new(opcode) {
+                DecodedOpcode::Ret | DecodedOpcode::JpV0Nnn { .. } => subroutine.may_return = true,
+                DecodedOpcode::JpNnn { nnn } => queue.push_back(nnn),
+                DecodedOpcode::CallNnn { nnn } => {
+                    subroutine.calls.push((address, nnn));
+                    queue.push_back(next);
+                }
+                decoded if decoded.is_skip() => {
+                    queue.push_back(next);
+                    queue.push_back(next + OPCODE_SIZE);
+                }
+                _ => queue.push_back(next),
+            }
+        }
+        subroutine
+    }
+}
+
+/// Returns the likely bugs of a ROM, in address order: checks of the instructions found by
+/// the disassembler, of the bytes it never reaches, and of the subroutine calls
+pub(crate) fn lint(rom_bytes: &[u8], rom_size: usize, layout: ProgramLayout) -> Vec<Finding> {
+    let rom = Rom {
+        bytes: rom_bytes,
+        size: rom_size,
+        layout,
+    };
+    let mut debugger = Debugger::new();
+    debugger.reset(rom_bytes, rom_size, layout);
+    let code: BTreeSet<usize> = debugger.instruction_addresses().into_iter().collect();
+
+    let mut findings = Vec::new();
+    let mut data = BTreeMap::new(); // Addresses loaded into I, and the first LD I loading each
+    for &address in &code {
+        let Some(opcode) = rom.opcode(address) else {
+            continue;
+        };
+        let message = match DecodedOpcode::new(opcode) {
+            DecodedOpcode::SysNnn { .. } => {
+                Some("machine code routine, ignored by modern interpreters".to_string())
+            }
+            DecodedOpcode::Unknown { .. } => Some("unknown opcode".to_string()),
+            DecodedOpcode::ShrVx { .. } | DecodedOpcode::ShlVx { .. } => Some(
+                "quirk: shifts VY into VX on the COSMAC VIP, VX in place elsewhere".to_string(),
+            ),
+            DecodedOpcode::LdAtIVx { .. } | DecodedOpcode::LdVxAtI { .. } => Some(
+                "quirk: increments I on the COSMAC VIP, leaves it unchanged elsewhere".to_string(),
+            ),
+            DecodedOpcode::JpV0Nnn { .. } => Some(
+                "quirk: jumps to XNN + VX on SUPER-CHIP, NNN + V0 elsewhere, not followed by the \
+                 disassembler"
+                    .to_string(),
+            ),
+            DecodedOpcode::LdINnn { nnn } => {
+                data.entry(nnn).or_insert(address);
+                if nnn >= rom.end() {
+                    Some(format!(
+                        "I points past the end of the ROM at {:#06X}",
+                        rom.end()
+                    ))
+                } else if nnn < rom.start() && nnn < RESERVED_MEMORY_SIZE {
+                    Some(
+                        "I points into the font area, LD F gives the address of a digit"
+                            .to_string(),
+                    )
+                } else if nnn < rom.start() {
+                    Some(format!(
+                        "I points before the start of the ROM at {:#06X}",
+                        rom.start()
+                    ))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        findings.extend(message.map(|message| rom.finding(address, message)));
+
+        if code.contains(&(address + 1)) {
+            findings.push(rom.finding(
+                address,
+                format!("overlaps the instruction at {:#06X}", address + 1),
+            ));
+        }
+    }
+
+    // Data loaded into I is not expected to be executed
+    for (&target, &load) in &data {
+        if code.contains(&target) || code.contains(&(target.wrapping_sub(1))) {
+            findings.push(rom.finding(
+                target,
+                format!("data executed as code, loaded into I at {:#06X}", load),
+            ));
+        }
+    }
+
+    findings.extend(unreachable_code(&rom, &code, &data));
+    findings.extend(call_findings(&rom));
+    findings.sort_by_key(|finding| finding.address);
+    findings
+}
+
+/// Returns the runs of bytes the disassembler never reaches that decode as instructions,
+/// the bytes from an address loaded into I on being data
+fn unreachable_code(
+    rom: &Rom,
+    code: &BTreeSet<usize>,
+    data: &BTreeMap<usize, usize>,
+) -> Vec<Finding> {
+    let covered = |address: usize| {
+        code.contains(&address) || address.checked_sub(1).is_some_and(|a| code.contains(&a))
+    };
+
+    let mut findings = Vec::new();
+    let mut address = rom.start();
+    while address < rom.end() {
+        if covered(address) {
+            address += 1;
+            continue;
+        }
+        let start = address;
+        while address < rom.end() && !covered(address) && !data.contains_key(&address) {
+            address += 1;
+        }
+        let end = address;
+        // The data after an address loaded into I goes on up to the next instruction
+        if data.contains_key(&address) {
+            while address < rom.end() && !covered(address) {
+                address += 1;
+            }
+        }
+
+        let opcodes: Vec<u16> = (start..end)
+            .step_by(OPCODE_SIZE)
+            .map_while(|address| rom.opcode(address))
+            .collect();
+        let is_code = !opcodes.is_empty()
+            && opcodes.iter().any(|opcode| *opcode != 0)
+            && opcodes.iter().all(|opcode| {
+                !matches!(DecodedOpcode::new(*opcode), DecodedOpcode::Unknown { .. })
+            });
+        if is_code {
+            findings.push(rom.finding(
+                start,
+                format!("unreachable code, {} instructions", opcodes.len()),
+            ));
+        }
+    }
+    findings
+}
+
+/// Returns the calls to subroutines that never return, and the calls that may overflow the stack
+fn call_findings(rom: &Rom) -> Vec<Finding> {
+    let mut subroutines = BTreeMap::new();
+    let mut queue = VecDeque::from([rom.layout.entry_address]);
+    while let Some(entry) = queue.pop_front() {
+        if subroutines.contains_key(&entry) {
+            continue;
+        }
+        let subroutine = rom.subroutine(entry);
+        queue.extend(subroutine.calls.iter().map(|(_, target)| *target));
+        subroutines.insert(entry, subroutine);
+    }
+
+    let mut findings = Vec::new();
+    let mut reported = BTreeSet::new();
+    for subroutine in subroutines.values() {
+        for &(site, target) in &subroutine.calls {
+            if !subroutines[&target].may_return && reported.insert(target) {
+                findings.push(rom.finding(
+                    site,
+                    format!("no RET is reachable from the subroutine at {:#06X}", target),
+                ));
+            }
+        }
+    }
+
+    let mut deepest = BTreeMap::new();
+    let mut reported = BTreeSet::new();
+    check_stack(
+        &subroutines,
+        rom.layout.entry_address,
+        0,
+        &mut Vec::new(),
+        &mut deepest,
+        &mut |site, message| {
+            if reported.insert(site) {
+                findings.push(rom.finding(site, message));
+            }
+        },
+    );
+    findings
+}
+
+/// Follows the calls from a subroutine, reporting the recursive calls
+/// and the calls going deeper than the stack
+fn check_stack(
+    subroutines: &BTreeMap<usize, Subroutine>,
+    entry: usize,
+    depth: usize,
+    path: &mut Vec<usize>,
+    deepest: &mut BTreeMap<usize, usize>,
+    report: &mut dyn FnMut(usize, String),
+) {
+    // A subroutine already followed from a deeper call cannot go deeper now
+    if deepest.get(&entry).is_some_and(|deepest| *deepest >= depth) {
+        return;
+    }
+    deepest.insert(entry, depth);
+    path.push(entry);
+    for &(site, target) in &subroutines[&entry].calls {
+        if path.contains(&target) {
+            report(
+                site,
+                format!(
+                    "recursive call, may overflow the {} stack entries",
+                    STACK_SIZE
+                ),
+            );
+        } else if depth + 1 > STACK_SIZE {
+            report(
+                site,
+                format!(
+                    "call {} deep, more than the {} stack entries",
+                    depth + 1,
+                    STACK_SIZE
+                ),
+            );
+        } else {
+            check_stack(subroutines, target, depth + 1, path, deepest, report);
+        }
+    }
+    path.pop();
+}
+
+#[cfg(test)]
+#[path = "./linter_test.rs"]
+mod linter_test;
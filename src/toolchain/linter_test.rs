@@ -0,0 +1,110 @@
+use super::*;
+use crate::motherboard::layout::MemoryLayout;
+
+fn messages(rom: &[u8]) -> Vec<(usize, String)> {
+    lint(rom, rom.len(), ProgramLayout::default())
+        .into_iter()
+        .map(|finding| (finding.address, finding.message))
+        .collect()
+}
+
+#[test]
+fn test_instruction_checks() {
+    // LD I, 0x0100 / SHR V0 / SYS 0x0123 / UNKNOWN FFFF / LD I, 0x0900 / JP 0x020A
+    let rom = [
+        0xA1, 0x00, 0x80, 0x06, 0x01, 0x23, 0xFF, 0xFF, 0xA9, 0x00, 0x12, 0x0A,
+    ];
+    let findings = lint(&rom, rom.len(), ProgramLayout::default());
+    assert_eq!(
+        findings
+            .iter()
+            .map(|finding| finding.to_string())
+            .collect::<Vec<String>>(),
+        [
+            "0x0200  A100  LD I, 0x0100          I points into the font area, LD F gives the address of a digit",
+            "0x0202  8006  SHR V0                quirk: shifts VY into VX on the COSMAC VIP, VX in place elsewhere",
+            "0x0204  0123  SYS 0x0123            machine code routine, ignored by modern interpreters",
+            "0x0206  FFFF  UNKNOWN FFFF          unknown opcode",
+            "0x0208  A900  LD I, 0x0900          I points past the end of the ROM at 0x020C",
+        ]
+    );
+}
+
+#[test]
+fn test_unreachable_code_and_data() {
+    // LD I, 0x0208 / JP 0x0202 / LD V0, 0x01 / ADD V0, 0x01 / sprite
+    let rom = [0xA2, 0x08, 0x12, 0x02, 0x60, 0x01, 0x70, 0x01, 0xFF, 0x81];
+    assert_eq!(
+        messages(&rom),
+        [(0x204, "unreachable code, 2 instructions".to_string())]
+    );
+
+    // LD I, 0x0202 / JP 0x0202
+    let rom = [0xA2, 0x02, 0x12, 0x02];
+    assert_eq!(
+        messages(&rom),
+        [(
+            0x202,
+            "data executed as code, loaded into I at 0x0200".to_string()
+        )]
+    );
+}
+
+#[test]
+fn test_calls_without_return_and_recursion() {
+    // CALL 0x0206 / CALL 0x020A / JP 0x0204 / JP 0x0206 / padding / CALL 0x020A
+    let rom = [
+        0x22, 0x06, 0x22, 0x0A, 0x12, 0x04, 0x12, 0x06, 0x00, 0x00, 0x22, 0x0A,
+    ];
+    assert_eq!(
+        messages(&rom),
+        [
+            (
+                0x200,
+                "no RET is reachable from the subroutine at 0x0206".to_string()
+            ),
+            (
+                0x202,
+                "no RET is reachable from the subroutine at 0x020A".to_string()
+            ),
+            (
+                0x20A,
+                "recursive call, may overflow the 16 stack entries".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_stack_depth() {
+    // CALL 0x0204 / JP 0x0202, then 17 subroutines each calling the next one before RET
+    let mut rom = vec![0x22, 0x04, 0x12, 0x02];
+    for index in 0..17 {
+        let next = 0x204 + 4 * (index + 1);
+        rom.extend([0x20 | (next >> 8) as u8, next as u8, 0x00, 0xEE]);
+    }
+    rom.extend([0x00, 0xEE]);
+
+    // The 16th subroutine, at 0x0240, needs a 17th stack entry for its call
+    assert_eq!(
+        messages(&rom),
+        [(
+            0x240,
+            "call 17 deep, more than the 16 stack entries".to_string()
+        )]
+    );
+}
+
+#[test]
+fn test_rom_loaded_at_another_address() {
+    // LD I, 0x0300 / JP 0x0602, loaded at 0x600 on the ETI 660
+    let rom = [0xA3, 0x00, 0x16, 0x02];
+    let findings = lint(&rom, rom.len(), MemoryLayout::Eti660.program_layout());
+    assert_eq!(
+        findings
+            .iter()
+            .map(|finding| finding.to_string())
+            .collect::<Vec<String>>(),
+        ["0x0600  A300  LD I, 0x0300          I points before the start of the ROM at 0x0600"]
+    );
+}
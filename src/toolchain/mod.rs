@@ -1,2 +1,13 @@
+pub(crate) mod audio_capture;
+pub(crate) mod coverage;
 pub(crate) mod debugger;
+pub(crate) mod debugger_view;
 pub(crate) mod decoder;
+pub(crate) mod heat_map;
+pub(crate) mod input_script;
+pub(crate) mod linter;
+pub(crate) mod octo_assembler;
+pub(crate) mod profiler;
+pub(crate) mod quirk_detector;
+pub(crate) mod recorder;
+pub(crate) mod screenshot;
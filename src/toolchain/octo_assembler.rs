@@ -0,0 +1,705 @@
+use std::collections::HashMap;
+
+use crate::constants::{MEMORY_SIZE, RESERVED_MEMORY_SIZE};
+
+//  Assembler for the Octo language of Octo cartridges, the program being loaded at 0x200:
+//
+//  : main                  label, called by its name, `jump main` or `i := main`
+//  :const speed 3          names for numbers, :alias x v4 for registers
+//  v0 := 5  v0 += speed    register operations, also -= |= &= ^= >>= <<= =- random delay key
+//  i := hex v0             i := <address>, i += vx, delay := vx, buzzer := vx
+//  sprite v0 v1 5          clear, return (or ;), save vx, load vx, bcd vx, jump, jump0
+//  if v0 == 5 then v1 += 1 conditions ==, !=, <, >, <=, >=, key and -key
+//  if v0 key begin ... else ... end
+//  loop ... while v0 != 3 ... again
+//  0x12 0b0101 18          byte literals
+//
+//  :unpack, :next, :org, :call and the SUPER-CHIP instructions are supported too.
+//  Macros, :calc, :stringmode and the XO-CHIP instructions are not.
+//  Everything after a '#' is ignored.
+
+const MAIN_LABEL: &str = "main";
+
+// Words with a meaning of their own, which cannot be used as names
+#[rustfmt::skip]
+const KEYWORDS: [&str; 38] = [
+    ":", ":const", ":alias", ":unpack", ":next", ":org", ":call", ":breakpoint", ":monitor", ";",
+    "clear", "return", "hires", "lores", "exit", "scroll-down", "scroll-left", "scroll-right",
+    "jump", "jump0", "bcd", "save", "load", "saveflags", "loadflags", "sprite", "delay", "buzzer",
+    "i", "if", "then", "begin", "else", "end", "loop", "while", "again", "key",
+];
+
+/// Returns the ROM assembled from Octo source
+pub(crate) fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let tokens = source
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            let code = line.split('#').next().unwrap_or_default();
+            code.split_whitespace().map(move |text| Token {
+                text,
+                line: index + 1,
+            })
+        })
+        .collect();
+    Assembler::new(tokens).run()
+}
+
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+/// Value of an operand, known or given by a label defined further on
+#[derive(Clone, Copy)]
+enum Operand<'a> {
+    Value(i64),
+    Label(&'a str),
+}
+
+/// Part of an instruction set once the label it refers to is defined
+enum FixupKind {
+    /// Address in the 12 low bits of an opcode
+    Address,
+
+    /// Nibble and high bits of the address, in the byte of a `v0 := nn` opcode
+    UnpackHigh(u8),
+
+    /// Low byte of the address, in the byte of a `v1 := nn` opcode
+    UnpackLow,
+}
+
+struct Fixup<'a> {
+    address: usize,
+    kind: FixupKind,
+    label: &'a str,
+    line: usize,
+}
+
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessOrEqual,
+    GreaterOrEqual,
+}
+
+/// Right-hand side of a comparison
+enum Rhs {
+    Register(u16),
+    Byte(u16),
+}
+
+enum Condition {
+    Key {
+        vx: u16,
+        pressed: bool,
+    },
+    Compare {
+        vx: u16,
+        comparison: Comparison,
+        rhs: Rhs,
+    },
+}
+
+/// Control structure still open, with the addresses of its jumps to set at its end
+enum Block {
+    If(usize),
+    Else(usize),
+    Loop { start: usize, breaks: Vec<usize> },
+}
+
+struct Assembler<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+
+    /// Line of the last token read, for the errors
+    line: usize,
+
+    rom: Vec<u8>,
+    here: usize,
+
+    labels: HashMap<&'a str, usize>,
+    constants: HashMap<&'a str, i64>,
+    aliases: HashMap<&'a str, u16>,
+    fixups: Vec<Fixup<'a>>,
+    blocks: Vec<Block>,
+
+    /// Label to define on the byte after the opcode of the next instruction
+    next_label: Option<&'a str>,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(tokens: Vec<Token<'a>>) -> Self {
+        Assembler {
+            tokens,
+            position: 0,
+            line: 1,
+            rom: Vec::new(),
+            here: RESERVED_MEMORY_SIZE,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+            next_label: None,
+        }
+    }
+
+    fn run(mut self) -> Result<Vec<u8>, String> {
+        // Programs start at main, a jump leading to it unless it comes first
+        let defines_main = self
+            .tokens
+            .windows(2)
+            .any(|pair| pair[0].text == ":" && pair[1].text == MAIN_LABEL);
+        let starts_with_main = self.tokens.len() >= 2
+            && self.tokens[0].text == ":"
+            && self.tokens[1].text == MAIN_LABEL;
+        if defines_main && !starts_with_main {
+            self.emit_jump(0x1000, Operand::Label(MAIN_LABEL))?;
+        }
+
+        while let Some(token) = self.next_token() {
+            self.statement(token.text)
+                .map_err(|e| format!("line {}: {}", self.line, e))?;
+        }
+        if !self.blocks.is_empty() {
+            return Err(format!("line {}: missing end or again", self.line));
+        }
+        self.resolve_fixups()?;
+        Ok(self.rom)
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        let token = *self.tokens.get(self.position)?;
+        self.position += 1;
+        self.line = token.line;
+        Some(token)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).map(|token| token.text)
+    }
+
+    fn word(&mut self) -> Result<&'a str, String> {
+        self.next_token()
+            .map(|token| token.text)
+            .ok_or_else(|| "unexpected end of the program".to_string())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.word()? {
+            word if word == expected => Ok(()),
+            word => Err(format!("expected '{}', found '{}'", expected, word)),
+        }
+    }
+
+    fn statement(&mut self, word: &'a str) -> Result<(), String> {
+        if let Some(vx) = self.as_register(word) {
+            return self.register_operation(vx);
+        }
+        if let Some(value) = parse_number(word) {
+            return self.emit_byte(byte(value)? as u8);
+        }
+        match word {
+            ":" => {
+                let name = self.name()?;
+                self.define_label(name, self.here)
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.known_value()?;
+                self.constants.insert(name, value);
+                Ok(())
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let vx = self.register()?;
+                self.aliases.insert(name, vx);
+                Ok(())
+            }
+            ":unpack" => {
+                let nibble = self.known_value()?;
+                if !(0..=0xF).contains(&nibble) {
+                    return Err(format!("invalid nibble {}, expected 0 to 15", nibble));
+                }
+                let label = self.operand()?;
+                let address = self.resolve_operand(label, FixupKind::UnpackHigh(nibble as u8));
+                self.emit(0x6000 | (nibble as u16) << 4 | address >> 8)?;
+                let address = self.resolve_operand(label, FixupKind::UnpackLow);
+                self.emit(0x6100 | address & 0xFF)
+            }
+            ":next" => {
+                self.next_label = Some(self.name()?);
+                Ok(())
+            }
+            ":org" => {
+                let address = self.known_value()?;
+                if !(RESERVED_MEMORY_SIZE as i64..MEMORY_SIZE as i64).contains(&address) {
+                    return Err(format!("invalid address {:#05X} for :org", address));
+                }
+                self.here = address as usize;
+                Ok(())
+            }
+            ":call" => {
+                let target = self.operand()?;
+                self.emit_jump(0x2000, target)
+            }
+            ":breakpoint" => self.word().map(|_| ()),
+            ":monitor" => self.word().and_then(|_| self.word()).map(|_| ()),
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit(0x00C0 | n)
+            }
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-left" => self.emit(0x00FC),
+            "exit" => self.emit(0x00FD),
+            "lores" => self.emit(0x00FE),
+            "hires" => self.emit(0x00FF),
+            "jump" => {
+                let target = self.operand()?;
+                self.emit_jump(0x1000, target)
+            }
+            "jump0" => {
+                let target = self.operand()?;
+                self.emit_jump(0xB000, target)
+            }
+            "bcd" => self.register_instruction(0xF033),
+            "save" => self.register_instruction(0xF055),
+            "load" => self.register_instruction(0xF065),
+            "saveflags" => self.register_instruction(0xF075),
+            "loadflags" => self.register_instruction(0xF085),
+            "sprite" => {
+                let vx = self.register()?;
+                let vy = self.register()?;
+                let n = self.nibble()?;
+                self.emit(0xD000 | vx << 8 | vy << 4 | n)
+            }
+            "delay" => {
+                self.expect(":=")?;
+                self.register_instruction(0xF015)
+            }
+            "buzzer" => {
+                self.expect(":=")?;
+                self.register_instruction(0xF018)
+            }
+            "i" => self.i_operation(),
+            "if" => {
+                let condition = self.condition()?;
+                match self.word()? {
+                    "then" => self.skip_unless(&condition),
+                    "begin" => {
+                        self.skip_unless(&negate(condition))?;
+                        let jump = self.here;
+                        self.emit(0x1000)?;
+                        self.blocks.push(Block::If(jump));
+                        Ok(())
+                    }
+                    word => Err(format!("expected 'then' or 'begin', found '{}'", word)),
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::If(jump)) => {
+                    let end_jump = self.here;
+                    self.emit(0x1000)?;
+                    self.set_jump(jump, self.here);
+                    self.blocks.push(Block::Else(end_jump));
+                    Ok(())
+                }
+                _ => Err("else without if ... begin".to_string()),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If(jump) | Block::Else(jump)) => {
+                    self.set_jump(jump, self.here);
+                    Ok(())
+                }
+                _ => Err("end without if ... begin".to_string()),
+            },
+            "loop" => {
+                self.blocks.push(Block::Loop {
+                    start: self.here,
+                    breaks: Vec::new(),
+                });
+                Ok(())
+            }
+            "while" => {
+                let condition = self.condition()?;
+                self.skip_unless(&negate(condition))?;
+                let jump = self.here;
+                self.emit(0x1000)?;
+                match self.blocks.iter_mut().rev().find_map(|block| match block {
+                    Block::Loop { breaks, .. } => Some(breaks),
+                    _ => None,
+                }) {
+                    Some(breaks) => {
+                        breaks.push(jump);
+                        Ok(())
+                    }
+                    None => Err("while outside of a loop".to_string()),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, breaks }) => {
+                    self.emit(0x1000 | start as u16)?;
+                    for jump in breaks {
+                        self.set_jump(jump, self.here);
+                    }
+                    Ok(())
+                }
+                _ => Err("again without loop".to_string()),
+            },
+            _ if word.starts_with(':') || KEYWORDS.contains(&word) => {
+                Err(format!("unsupported Octo source '{}'", word))
+            }
+            // Any other name calls the subroutine of that label
+            _ => {
+                let target = self.name_operand(word)?;
+                self.emit_jump(0x2000, target)
+            }
+        }
+    }
+
+    fn register_operation(&mut self, vx: u16) -> Result<(), String> {
+        let operator = self.word()?;
+        let (register_opcode, value_opcode) = match operator {
+            ":=" => match self.peek() {
+                Some("random") => {
+                    self.word()?;
+                    let nn = self.byte_value()?;
+                    return self.emit(0xC000 | vx << 8 | nn);
+                }
+                Some("delay") => {
+                    self.word()?;
+                    return self.emit(0xF007 | vx << 8);
+                }
+                Some("key") => {
+                    self.word()?;
+                    return self.emit(0xF00A | vx << 8);
+                }
+                _ => (0x8000, Some(0x6000)),
+            },
+            "+=" => (0x8004, Some(0x7000)),
+            "-=" => (0x8005, None),
+            "|=" => (0x8001, None),
+            "&=" => (0x8002, None),
+            "^=" => (0x8003, None),
+            ">>=" => (0x8006, None),
+            "=-" => (0x8007, None),
+            "<<=" => (0x800E, None),
+            _ => return Err(format!("unknown register operation '{}'", operator)),
+        };
+        let rhs = self.word()?;
+        if let Some(vy) = self.as_register(rhs) {
+            return self.emit(register_opcode | vx << 8 | vy << 4);
+        }
+        let nn = byte(self.value_of(rhs)?)?;
+        match (operator, value_opcode) {
+            // Subtracting a number adds its two's complement
+            ("-=", _) => self.emit(0x7000 | vx << 8 | (nn as u8).wrapping_neg() as u16),
+            (_, Some(opcode)) => self.emit(opcode | vx << 8 | nn),
+            _ => Err(format!(
+                "'{}' expects a register, found '{}'",
+                operator, rhs
+            )),
+        }
+    }
+
+    fn i_operation(&mut self) -> Result<(), String> {
+        match self.word()? {
+            ":=" => match self.peek() {
+                Some("hex") => {
+                    self.word()?;
+                    self.register_instruction(0xF029)
+                }
+                Some("bighex") => {
+                    self.word()?;
+                    self.register_instruction(0xF030)
+                }
+                Some("long") => Err("unsupported Octo source 'i := long'".to_string()),
+                _ => {
+                    let target = self.operand()?;
+                    self.emit_jump(0xA000, target)
+                }
+            },
+            "+=" => self.register_instruction(0xF01E),
+            word => Err(format!("expected ':=' or '+=' after i, found '{}'", word)),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let vx = self.register()?;
+        let comparison = match self.word()? {
+            "key" => return Ok(Condition::Key { vx, pressed: true }),
+            "-key" => return Ok(Condition::Key { vx, pressed: false }),
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            ">" => Comparison::Greater,
+            "<=" => Comparison::LessOrEqual,
+            ">=" => Comparison::GreaterOrEqual,
+            word => return Err(format!("unknown comparison '{}'", word)),
+        };
+        let rhs = self.word()?;
+        let rhs = match self.as_register(rhs) {
+            Some(vy) => Rhs::Register(vy),
+            None => Rhs::Byte(byte(self.value_of(rhs)?)?),
+        };
+        Ok(Condition::Compare {
+            vx,
+            comparison,
+            rhs,
+        })
+    }
+
+    /// Emits the instructions skipping the next one when the condition is false.
+    /// Ordering comparisons go through vF, as in Octo.
+    fn skip_unless(&mut self, condition: &Condition) -> Result<(), String> {
+        let (vx, comparison, rhs) = match condition {
+            Condition::Key { vx, pressed: true } => return self.emit(0xE0A1 | vx << 8),
+            Condition::Key { vx, pressed: false } => return self.emit(0xE09E | vx << 8),
+            Condition::Compare {
+                vx,
+                comparison,
+                rhs,
+            } => (*vx, comparison, rhs),
+        };
+        match (comparison, rhs) {
+            (Comparison::Equal, Rhs::Register(vy)) => self.emit(0x9000 | vx << 8 | vy << 4),
+            (Comparison::Equal, Rhs::Byte(nn)) => self.emit(0x4000 | vx << 8 | nn),
+            (Comparison::NotEqual, Rhs::Register(vy)) => self.emit(0x5000 | vx << 8 | vy << 4),
+            (Comparison::NotEqual, Rhs::Byte(nn)) => self.emit(0x3000 | vx << 8 | nn),
+            (comparison, rhs) => {
+                match rhs {
+                    Rhs::Register(vy) => self.emit(0x8F00 | vy << 4)?,
+                    Rhs::Byte(nn) => self.emit(0x6F00 | nn)?,
+                }
+                // vF := vx - vF or vF - vx leaves the no borrow flag in vF
+                let (opcode, true_flag) = match comparison {
+                    Comparison::Less => (0x8F07, 0),
+                    Comparison::GreaterOrEqual => (0x8F07, 1),
+                    Comparison::Greater => (0x8F05, 0),
+                    _ => (0x8F05, 1),
+                };
+                self.emit(opcode | vx << 4)?;
+                self.emit(0x3F00 | (1 - true_flag))
+            }
+        }
+    }
+
+    fn register_instruction(&mut self, opcode: u16) -> Result<(), String> {
+        let vx = self.register()?;
+        if self.peek() == Some("-") {
+            return Err("unsupported Octo source, register ranges are XO-CHIP".to_string());
+        }
+        self.emit(opcode | vx << 8)
+    }
+
+    fn as_register(&self, word: &str) -> Option<u16> {
+        if let Some(vx) = self.aliases.get(word) {
+            return Some(*vx);
+        }
+        let digit = word.strip_prefix(['v', 'V'])?;
+        (digit.len() == 1)
+            .then(|| u16::from_str_radix(digit, 16).ok())
+            .flatten()
+    }
+
+    fn register(&mut self) -> Result<u16, String> {
+        let word = self.word()?;
+        self.as_register(word)
+            .ok_or_else(|| format!("expected a register, found '{}'", word))
+    }
+
+    fn name(&mut self) -> Result<&'a str, String> {
+        let word = self.word()?;
+        if KEYWORDS.contains(&word)
+            || self.as_register(word).is_some()
+            || parse_number(word).is_some()
+        {
+            return Err(format!("invalid name '{}'", word));
+        }
+        Ok(word)
+    }
+
+    fn value_of(&self, word: &str) -> Result<i64, String> {
+        parse_number(word)
+            .or_else(|| self.constants.get(word).copied())
+            .or_else(|| self.labels.get(word).map(|address| *address as i64))
+            .ok_or_else(|| format!("undefined name '{}'", word))
+    }
+
+    fn known_value(&mut self) -> Result<i64, String> {
+        let word = self.word()?;
+        self.value_of(word)
+    }
+
+    fn byte_value(&mut self) -> Result<u16, String> {
+        let value = self.known_value()?;
+        byte(value)
+    }
+
+    fn nibble(&mut self) -> Result<u16, String> {
+        match self.known_value()? {
+            value @ 0..=0xF => Ok(value as u16),
+            value => Err(format!("invalid nibble {}, expected 0 to 15", value)),
+        }
+    }
+
+    /// Returns an address, a label defined further on being set once known
+    fn operand(&mut self) -> Result<Operand<'a>, String> {
+        let word = self.word()?;
+        self.name_operand(word)
+    }
+
+    fn name_operand(&self, word: &'a str) -> Result<Operand<'a>, String> {
+        match self.value_of(word) {
+            Ok(value) => Ok(Operand::Value(value)),
+            Err(_) if !KEYWORDS.contains(&word) && self.as_register(word).is_none() => {
+                Ok(Operand::Label(word))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the value of an operand, 0 for a label recorded to be set at the current address
+    fn resolve_operand(&mut self, operand: Operand<'a>, kind: FixupKind) -> u16 {
+        match operand {
+            Operand::Value(value) => value as u16 & 0xFFF,
+            Operand::Label(label) => {
+                self.fixups.push(Fixup {
+                    address: self.here,
+                    kind,
+                    label,
+                    line: self.line,
+                });
+                0
+            }
+        }
+    }
+
+    fn emit_jump(&mut self, opcode: u16, target: Operand<'a>) -> Result<(), String> {
+        if let Operand::Value(address) = target {
+            if !(0..MEMORY_SIZE as i64).contains(&address) {
+                return Err(format!("invalid address {:#X}", address));
+            }
+        }
+        let address = self.resolve_operand(target, FixupKind::Address);
+        self.emit(opcode | address)
+    }
+
+    fn define_label(&mut self, name: &'a str, address: usize) -> Result<(), String> {
+        if self.labels.insert(name, address).is_some() {
+            return Err(format!("label '{}' defined twice", name));
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, opcode: u16) -> Result<(), String> {
+        if let Some(label) = self.next_label.take() {
+            self.define_label(label, self.here + 1)?;
+        }
+        let [high, low] = opcode.to_be_bytes();
+        self.emit_byte(high)?;
+        self.emit_byte(low)
+    }
+
+    fn emit_byte(&mut self, value: u8) -> Result<(), String> {
+        if self.here >= MEMORY_SIZE {
+            return Err("program larger than the memory".to_string());
+        }
+        let index = self.here - RESERVED_MEMORY_SIZE;
+        if self.rom.len() <= index {
+            self.rom.resize(index + 1, 0);
+        }
+        self.rom[index] = value;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn set_jump(&mut self, jump: usize, address: usize) {
+        let index = jump - RESERVED_MEMORY_SIZE;
+        let opcode = u16::from_be_bytes([self.rom[index], self.rom[index + 1]]);
+        let opcode = opcode & 0xF000 | address as u16;
+        self.rom[index..index + 2].copy_from_slice(&opcode.to_be_bytes());
+    }
+
+    fn resolve_fixups(&mut self) -> Result<(), String> {
+        for fixup in &self.fixups {
+            let address = *self
+                .labels
+                .get(fixup.label)
+                .ok_or_else(|| format!("line {}: undefined name '{}'", fixup.line, fixup.label))?;
+            let index = fixup.address - RESERVED_MEMORY_SIZE;
+            match fixup.kind {
+                FixupKind::Address => {
+                    self.rom[index] |= (address >> 8) as u8;
+                    self.rom[index + 1] = address as u8;
+                }
+                FixupKind::UnpackHigh(nibble) => {
+                    self.rom[index + 1] = nibble << 4 | (address >> 8) as u8
+                }
+                FixupKind::UnpackLow => self.rom[index + 1] = address as u8,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn negate(condition: Condition) -> Condition {
+    match condition {
+        Condition::Key { vx, pressed } => Condition::Key {
+            vx,
+            pressed: !pressed,
+        },
+        Condition::Compare {
+            vx,
+            comparison,
+            rhs,
+        } => Condition::Compare {
+            vx,
+            comparison: match comparison {
+                Comparison::Equal => Comparison::NotEqual,
+                Comparison::NotEqual => Comparison::Equal,
+                Comparison::Less => Comparison::GreaterOrEqual,
+                Comparison::GreaterOrEqual => Comparison::Less,
+                Comparison::Greater => Comparison::LessOrEqual,
+                Comparison::LessOrEqual => Comparison::Greater,
+            },
+            rhs,
+        },
+    }
+}
+
+/// Parses a number in decimal, 0x hex or 0b binary, negative with a leading '-'
+fn parse_number(word: &str) -> Option<i64> {
+    let (negative, digits) = match word.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, word),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Returns a byte of a value from -128 to 255, negative values in two's complement
+fn byte(value: i64) -> Result<u16, String> {
+    match value {
+        -128..=255 => Ok(value as u8 as u16),
+        _ => Err(format!("invalid byte {}, expected -128 to 255", value)),
+    }
+}
+
+#[cfg(test)]
+#[path = "./octo_assembler_test.rs"]
+mod octo_assembler_test;
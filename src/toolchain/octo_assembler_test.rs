@@ -0,0 +1,73 @@
+use super::*;
+
+#[test]
+fn test_assemble_instructions() {
+    let source = "\
+        : main\n\
+        \tv0 := 5\n\
+        \ti := hex v0\n\
+        \tsprite v1 v2 5\n\
+        \tloop\n\
+        \t\tv3 += 1\n\
+        \t\tif v3 == 10 then v3 := 0   # wraps around\n\
+        \tagain\n";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [0x60, 0x05, 0xF0, 0x29, 0xD1, 0x25, 0x73, 0x01, 0x43, 0x0A, 0x63, 0x00, 0x12, 0x06]
+    );
+}
+
+#[test]
+fn test_assemble_labels() {
+    // main not coming first, the program starts with a jump to it
+    let source = "\
+        : draw i := dot sprite v0 v0 1 return\n\
+        : main draw loop again\n\
+        : dot 0b10000000\n";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [0x12, 0x08, 0xA2, 0x0C, 0xD0, 0x01, 0x00, 0xEE, 0x22, 0x02, 0x12, 0x0A, 0x80]
+    );
+
+    let source = ":const start 3\n:alias x v4\n: main x := start :unpack 0xA main";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [0x12, 0x02, 0x64, 0x03, 0x60, 0xA2, 0x61, 0x02]
+    );
+}
+
+#[test]
+fn test_assemble_control_structures() {
+    let source = "\
+        : main\n\
+        loop\n\
+        \twhile v0 != 3\n\
+        \tv0 += 1\n\
+        again\n\
+        if v1 key begin v2 := 1 else v2 := 2 end\n\
+        if v0 < v1 then v3 := 1\n";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [
+            0x40, 0x03, 0x12, 0x08, 0x70, 0x01, 0x12, 0x00, // loop
+            0xE1, 0x9E, 0x12, 0x10, 0x62, 0x01, 0x12, 0x12, 0x62, 0x02, // if begin else end
+            0x8F, 0x10, 0x8F, 0x07, 0x3F, 0x01, 0x63, 0x01, // comparison through vF
+        ]
+    );
+}
+
+#[test]
+fn test_assemble_reports_the_line() {
+    assert_eq!(
+        assemble(": main\n  v0 := 5\n  :macro blink { }").unwrap_err(),
+        "line 3: unsupported Octo source ':macro'"
+    );
+    assert_eq!(
+        assemble(": main\n  jump nowhere").unwrap_err(),
+        "line 2: undefined name 'nowhere'"
+    );
+    assert_eq!(
+        assemble(": main\n  loop\n  v0 += 1").unwrap_err(),
+        "line 3: missing end or again"
+    );
+}
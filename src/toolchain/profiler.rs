@@ -0,0 +1,328 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::constants::MEMORY_SIZE;
+use crate::motherboard::memory::Memory;
+
+use super::decoder::DecodedOpcode;
+
+const HOT_SPOT_LINES: usize = 20; // Addresses listed in the text report, the JSON report has them all
+
+/// Subroutine running on the CALL/RET stack
+#[derive(Clone, Copy)]
+struct ActiveCall {
+    entry: usize,
+
+    /// Instructions executed when the subroutine was called
+    start_cycle: u64,
+
+    /// Frames completed when the subroutine was called
+    start_frame: u64,
+}
+
+/// Cost of the calls to a subroutine
+#[derive(Clone, Copy, Default)]
+struct SubroutineCost {
+    calls: u64,
+
+    /// Instructions executed in the subroutine itself
+    self_cycles: u64,
+
+    /// Instructions executed from the call to the return, with the nested calls
+    total_cycles: u64,
+
+    /// Frames the calls spanned, and the most a single call spanned
+    frames: u64,
+    max_frames: u64,
+}
+
+/// Counts the instructions executed per address and per opcode,
+/// and attributes them to the subroutines following the CALL/RET stack
+pub(crate) struct Profiler {
+    executions: Vec<u64>,
+    opcodes: BTreeMap<&'static str, u64>,
+    subroutines: BTreeMap<usize, SubroutineCost>,
+
+    /// Calls not returned yet, the program entry point at the bottom
+    calls: Vec<ActiveCall>,
+
+    cycles: u64,
+    frames: u64,
+}
+
+impl Profiler {
+    pub(crate) fn new(entry: usize) -> Self {
+        let mut subroutines = BTreeMap::new();
+        subroutines.insert(
+            entry,
+            SubroutineCost {
+                calls: 1,
+                ..SubroutineCost::default()
+            },
+        );
+        Profiler {
+            executions: vec![0; MEMORY_SIZE],
+            opcodes: BTreeMap::new(),
+            subroutines,
+            calls: vec![ActiveCall {
+                entry,
+                start_cycle: 0,
+                start_frame: 0,
+            }],
+            cycles: 0,
+            frames: 0,
+        }
+    }
+
+    /// Counts an instruction about to be executed
+    pub(crate) fn record(&mut self, address: usize, decoded: &DecodedOpcode) {
+        self.executions[address] += 1;
+        *self.opcodes.entry(decoded.variant()).or_default() += 1;
+        self.cycles += 1;
+
+        // The CALL counts in the caller and the RET in the subroutine
+        if let Some(call) = self.calls.last() {
+            self.subroutines.entry(call.entry).or_default().self_cycles += 1;
+        }
+        match decoded {
+            DecodedOpcode::CallNnn { nnn } => {
+                self.subroutines.entry(*nnn).or_default().calls += 1;
+                self.calls.push(ActiveCall {
+                    entry: *nnn,
+                    start_cycle: self.cycles,
+                    start_frame: self.frames,
+                });
+            }
+            // A RET from the program entry point underflows the stack, it is not a return
+            DecodedOpcode::Ret if self.calls.len() > 1 => {
+                let call = self.calls.pop().unwrap();
+                // The call spans the frame it returns in
+                let spanned = self.frames - call.start_frame + 1;
+                close(
+                    &mut self.subroutines,
+                    &self.calls,
+                    call,
+                    self.cycles - call.start_cycle,
+                    spanned,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Counts a completed 60Hz frame
+    pub(crate) fn count_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    /// Returns the costs ranked from the highest, the calls not returned yet counted up to now
+    pub(crate) fn report(&self, memory: &Memory) -> ProfileReport {
+        let mut subroutines = self.subroutines.clone();
+        for (depth, call) in self.calls.iter().enumerate().rev() {
+            // The frame following the last completed one has not started yet
+            let spanned = (self.frames - call.start_frame).max(1);
+            close(
+                &mut subroutines,
+                &self.calls[..depth],
+                *call,
+                self.cycles - call.start_cycle,
+                spanned,
+            );
+        }
+
+        let instruction_at = |address: usize| {
+            // A CALL to the last address has no second byte to decode
+            let low = if address + 1 < MEMORY_SIZE {
+                memory.load(address + 1)
+            } else {
+                0
+            };
+            let opcode = (memory.load(address) as u16) << 8 | low as u16;
+            (opcode, DecodedOpcode::new(opcode).to_string())
+        };
+
+        let mut hot_spots: Vec<HotSpot> = self
+            .executions
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(address, count)| {
+                let (opcode, instruction) = instruction_at(address);
+                HotSpot {
+                    address,
+                    opcode: format!("{:04X}", opcode),
+                    instruction,
+                    count: *count,
+                }
+            })
+            .collect();
+        hot_spots.sort_by(|a, b| b.count.cmp(&a.count).then(a.address.cmp(&b.address)));
+
+        let mut opcodes: Vec<OpcodeCount> = self
+            .opcodes
+            .iter()
+            .map(|(variant, count)| OpcodeCount {
+                variant,
+                count: *count,
+            })
+            .collect();
+        opcodes.sort_by_key(|opcode| Reverse(opcode.count));
+
+        let mut subroutines: Vec<SubroutineReport> = subroutines
+            .into_iter()
+            .map(|(entry, cost)| SubroutineReport {
+                entry,
+                instruction: instruction_at(entry).1,
+                calls: cost.calls,
+                self_cycles: cost.self_cycles,
+                total_cycles: cost.total_cycles,
+                frames: cost.frames,
+                max_frames: cost.max_frames,
+            })
+            .collect();
+        subroutines.sort_by(|a, b| {
+            b.total_cycles
+                .cmp(&a.total_cycles)
+                .then(a.entry.cmp(&b.entry))
+        });
+
+        ProfileReport {
+            instructions: self.cycles,
+            frames: self.frames,
+            hot_spots,
+            opcodes,
+            subroutines,
+        }
+    }
+}
+
+/// Adds the cycles and frames of a call to the costs of its subroutine. The cycles of a recursive
+/// call are already counted by the outer call of the same subroutine, still on the stack.
+fn close(
+    subroutines: &mut BTreeMap<usize, SubroutineCost>,
+    callers: &[ActiveCall],
+    call: ActiveCall,
+    cycles: u64,
+    frames: u64,
+) {
+    let cost = subroutines.entry(call.entry).or_default();
+    cost.max_frames = cost.max_frames.max(frames);
+    if !callers.iter().any(|caller| caller.entry == call.entry) {
+        cost.total_cycles += cycles;
+        cost.frames += frames;
+    }
+}
+
+/// Instructions executed at an address
+#[derive(Serialize)]
+pub(crate) struct HotSpot {
+    pub(crate) address: usize,
+    pub(crate) opcode: String,
+    pub(crate) instruction: String,
+    pub(crate) count: u64,
+}
+
+/// Instructions executed of a kind of opcode
+#[derive(Serialize)]
+pub(crate) struct OpcodeCount {
+    pub(crate) variant: &'static str,
+    pub(crate) count: u64,
+}
+
+/// Cost of a subroutine, the program entry point counting as one called once
+#[derive(Serialize)]
+pub(crate) struct SubroutineReport {
+    pub(crate) entry: usize,
+    pub(crate) instruction: String,
+    pub(crate) calls: u64,
+    pub(crate) self_cycles: u64,
+    pub(crate) total_cycles: u64,
+    pub(crate) frames: u64,
+    pub(crate) max_frames: u64,
+}
+
+/// Profile of a run, every list ranked by cost
+#[derive(Serialize)]
+pub(crate) struct ProfileReport {
+    pub(crate) instructions: u64,
+    pub(crate) frames: u64,
+    pub(crate) hot_spots: Vec<HotSpot>,
+    pub(crate) opcodes: Vec<OpcodeCount>,
+    pub(crate) subroutines: Vec<SubroutineReport>,
+}
+
+impl ProfileReport {
+    /// Returns the report as tables, with the most executed addresses only
+    pub(crate) fn to_text(&self) -> String {
+        let share = |count: u64| 100.0 * count as f64 / self.instructions.max(1) as f64;
+        let mut text = format!(
+            "Profile: {} instructions over {} frames\n",
+            self.instructions, self.frames
+        );
+
+        text.push_str("\nHot spots\n");
+        text.push_str("  Address  Opcode  Instruction            Count   Share\n");
+        for hot_spot in self.hot_spots.iter().take(HOT_SPOT_LINES) {
+            text.push_str(&format!(
+                "  {:#06X}   {}    {:20} {:>7}  {:5.1}%\n",
+                hot_spot.address,
+                hot_spot.opcode,
+                hot_spot.instruction,
+                hot_spot.count,
+                share(hot_spot.count)
+            ));
+        }
+
+        text.push_str("\nOpcodes\n");
+        text.push_str("  Variant        Count   Share\n");
+        for opcode in &self.opcodes {
+            text.push_str(&format!(
+                "  {:12} {:>7}  {:5.1}%\n",
+                opcode.variant,
+                opcode.count,
+                share(opcode.count)
+            ));
+        }
+
+        text.push_str("\nSubroutines\n");
+        text.push_str(
+            "  Entry   Instruction           Calls     Self    Total   Share  Frames  Max frames\n",
+        );
+        for subroutine in &self.subroutines {
+            text.push_str(&format!(
+                "  {:#06X}  {:20} {:>6} {:>8} {:>8}  {:5.1}%  {:>6}  {:>10}\n",
+                subroutine.entry,
+                subroutine.instruction,
+                subroutine.calls,
+                subroutine.self_cycles,
+                subroutine.total_cycles,
+                share(subroutine.total_cycles),
+                subroutine.frames,
+                subroutine.max_frames
+            ));
+        }
+        text
+    }
+
+    /// Writes the report to a file, as JSON if its extension is .json and as text otherwise
+    pub(crate) fn save(&self, path: &Path) -> Result<(), String> {
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let contents = if is_json {
+            serde_json::to_string_pretty(self).map_err(|e| e.to_string())?
+        } else {
+            self.to_text()
+        };
+        fs::write(path, contents)
+            .map_err(|e| format!("Error: profile file {} not writable: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+#[path = "./profiler_test.rs"]
+mod profiler_test;
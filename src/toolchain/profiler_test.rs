@@ -0,0 +1,101 @@
+use super::*;
+use crate::constants::RESERVED_MEMORY_SIZE;
+
+// CALL 0x206 / JP 0x202 / (padding) / ADD V0, 0x01 / RET
+const ROM: [u8; 10] = [0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x70, 0x01, 0x00, 0xEE];
+
+fn build_memory() -> Memory {
+    let mut memory = Memory::new();
+    memory.reset(&ROM, ROM.len(), RESERVED_MEMORY_SIZE);
+    memory
+}
+
+fn record(profiler: &mut Profiler, memory: &Memory, address: usize) {
+    let opcode = (memory.load(address) as u16) << 8 | memory.load(address + 1) as u16;
+    profiler.record(address, &DecodedOpcode::new(opcode));
+}
+
+#[test]
+fn test_costs_follow_calls_and_returns() {
+    let memory = build_memory();
+    let mut profiler = Profiler::new(0x200);
+    record(&mut profiler, &memory, 0x200);
+    profiler.count_frame();
+    record(&mut profiler, &memory, 0x206);
+    record(&mut profiler, &memory, 0x208);
+    record(&mut profiler, &memory, 0x202);
+    record(&mut profiler, &memory, 0x202);
+
+    let report = profiler.report(&memory);
+    assert_eq!((report.instructions, report.frames), (5, 1));
+
+    let hot_spot = &report.hot_spots[0];
+    assert_eq!(
+        (hot_spot.address, hot_spot.opcode.as_str(), hot_spot.count),
+        (0x202, "1202", 2)
+    );
+    assert_eq!(hot_spot.instruction, "JP 0x0202");
+    assert_eq!(
+        (report.opcodes[0].variant, report.opcodes[0].count),
+        ("JpNnn", 2)
+    );
+
+    // The program entry point is still running and includes the subroutine
+    let costs: Vec<(usize, u64, u64, u64, u64)> = report
+        .subroutines
+        .iter()
+        .map(|s| {
+            (
+                s.entry,
+                s.calls,
+                s.self_cycles,
+                s.total_cycles,
+                s.max_frames,
+            )
+        })
+        .collect();
+    assert_eq!(costs, [(0x200, 1, 3, 5, 1), (0x206, 1, 2, 2, 2)]);
+    assert_eq!(report.subroutines[1].instruction, "ADD V0, 0x01");
+}
+
+#[test]
+fn test_recursive_calls_are_counted_once() {
+    // CALL 0x202 / CALL 0x202 / RET, the subroutine calling itself once before both return
+    let mut memory = Memory::new();
+    memory.reset(
+        &[0x22, 0x02, 0x22, 0x02, 0x00, 0xEE],
+        6,
+        RESERVED_MEMORY_SIZE,
+    );
+    let mut profiler = Profiler::new(0x200);
+    for address in [0x200, 0x202, 0x204, 0x204] {
+        record(&mut profiler, &memory, address);
+    }
+
+    let report = profiler.report(&memory);
+    let subroutine = report
+        .subroutines
+        .iter()
+        .find(|s| s.entry == 0x202)
+        .unwrap();
+    assert_eq!(
+        (
+            subroutine.calls,
+            subroutine.self_cycles,
+            subroutine.total_cycles
+        ),
+        (2, 3, 3)
+    );
+}
+
+#[test]
+fn test_text_report() {
+    let memory = build_memory();
+    let mut profiler = Profiler::new(0x200);
+    record(&mut profiler, &memory, 0x200);
+
+    let text = profiler.report(&memory).to_text();
+    assert!(text.starts_with("Profile: 1 instructions over 0 frames\n"));
+    assert!(text.contains("  0x0200   2206    CALL 0x0206                1  100.0%\n"));
+    assert!(text.contains("  CallNnn            1  100.0%\n"));
+}
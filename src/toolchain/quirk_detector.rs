@@ -0,0 +1,267 @@
+use crate::constants::OPCODE_SIZE;
+use crate::motherboard::layout::ProgramLayout;
+use crate::motherboard::processor::Processor;
+use crate::motherboard::quirks::QuirkPreset;
+use crate::peripherals::cartridge_driver::CartridgeDriver;
+use crate::settings::Settings;
+
+use super::debugger::Debugger;
+use super::decoder::DecodedOpcode;
+
+const PRESETS: [QuirkPreset; 3] = [QuirkPreset::Modern, QuirkPreset::Cosmac, QuirkPreset::Schip]; // Ties go to the first
+const TRIAL_FRAMES: usize = 600; // Frames of the trial run under each preset, 10 seconds
+const TRIAL_SEED: u64 = 0; // Seed of RND in the trial runs, for the same ROM to always give the same result
+const REUSE_WINDOW: usize = 8; // Instructions looked at after FX55 and FX65 for a use of I
+
+// Weights of the clues, a SUPER-CHIP opcode being proof where the others are only hints
+const SCHIP_OPCODE_WEIGHT: f32 = 3.0;
+const SHIFT_WEIGHT: f32 = 1.0;
+const IN_PLACE_SHIFT_WEIGHT: f32 = 0.5;
+const I_REUSE_WEIGHT: f32 = 1.0;
+const CRASH_WEIGHT: f32 = -3.0;
+const ERROR_WEIGHT: f32 = -1.0;
+
+/// Hint that a ROM was written for a quirk preset, or against it with a negative weight
+#[derive(Debug, PartialEq)]
+pub(crate) struct Clue {
+    pub(crate) preset: QuirkPreset,
+    pub(crate) weight: f32,
+    pub(crate) reason: String,
+}
+
+/// Outcome of a trial run under a quirk preset
+#[derive(Debug, Default)]
+pub(crate) struct Trial {
+    pub(crate) frames: usize,
+    pub(crate) errors: usize,
+
+    /// The interpreter would have crashed, on a stack overflow or underflow
+    /// or on an address outside the memory
+    pub(crate) crashed: bool,
+}
+
+/// Quirk preset inferred for a ROM, with the clues it was inferred from
+pub(crate) struct Detection {
+    pub(crate) preset: QuirkPreset,
+
+    /// Share of the preset in the softmax of the preset scores, from 1/3 with no clue to 1
+    pub(crate) confidence: f32,
+
+    pub(crate) clues: Vec<Clue>,
+}
+
+impl Detection {
+    pub(crate) fn summary(&self) -> String {
+        format!(
+            "{} ({:.0}% confidence)",
+            self.preset.name(),
+            100.0 * self.confidence
+        )
+    }
+}
+
+/// Returns the opcode at an address of the ROM, loaded at the load address of the layout
+fn opcode_at(rom: &[u8], rom_size: usize, layout: ProgramLayout, address: usize) -> Option<u16> {
+    let offset = address.checked_sub(layout.load_address)?;
+    if offset + OPCODE_SIZE > rom_size {
+        return None;
+    }
+    Some((rom[offset] as u16) << 8 | rom[offset + 1] as u16)
+}
+
+/// Returns true for the SUPER-CHIP opcodes: scrolls, exit, screen modes, big sprites and fonts,
+/// and the flag registers
+fn is_schip_opcode(opcode: u16) -> bool {
+    matches!(opcode, 0x00C0..=0x00CF | 0x00FB..=0x00FF)
+        || opcode & 0xF00F == 0xD000
+        || matches!(opcode & 0xF0FF, 0xF030 | 0xF075 | 0xF085)
+}
+
+/// Returns true if I is used again after the FX55 or FX65 at an address without being set,
+/// expecting it to have moved past the registers as on the COSMAC VIP
+fn reuses_i(rom: &[u8], rom_size: usize, layout: ProgramLayout, address: usize) -> bool {
+    for step in 1..=REUSE_WINDOW {
+        let Some(opcode) = opcode_at(rom, rom_size, layout, address + step * OPCODE_SIZE) else {
+            return false;
+        };
+        match DecodedOpcode::new(opcode) {
+            DecodedOpcode::LdAtIVx { .. }
+            | DecodedOpcode::LdVxAtI { .. }
+            | DecodedOpcode::DrwVxVyN { .. }
+            | DecodedOpcode::BcdVx { .. } => return true,
+            // Setting I, or leaving the straight line, ends the search
+            DecodedOpcode::LdINnn { .. }
+            | DecodedOpcode::LdFVx { .. }
+            | DecodedOpcode::AddIVx { .. }
+            | DecodedOpcode::JpNnn { .. }
+            | DecodedOpcode::JpV0Nnn { .. }
+            | DecodedOpcode::CallNnn { .. }
+            | DecodedOpcode::Ret => return false,
+            decoded if decoded.is_skip() => return false,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Returns the clues found in the instructions reachable by the disassembler
+pub(crate) fn code_clues(rom: &[u8], rom_size: usize, layout: ProgramLayout) -> Vec<Clue> {
+    let mut debugger = Debugger::new();
+    debugger.reset(rom, rom_size, layout);
+
+    let mut schip_opcodes = Vec::new();
+    let mut shifts = Vec::new();
+    let mut in_place_shifts = Vec::new();
+    let mut i_reuses = Vec::new();
+    for address in debugger.instruction_addresses() {
+        let Some(opcode) = opcode_at(rom, rom_size, layout, address) else {
+            continue;
+        };
+        if is_schip_opcode(opcode) {
+            schip_opcodes.push((address, opcode));
+        }
+        match DecodedOpcode::new(opcode) {
+            DecodedOpcode::ShrVx { vx, vy } | DecodedOpcode::ShlVx { vx, vy } => {
+                if vx != vy {
+                    shifts.push((address, opcode));
+                } else {
+                    in_place_shifts.push((address, opcode));
+                }
+            }
+            DecodedOpcode::LdAtIVx { .. } | DecodedOpcode::LdVxAtI { .. }
+                if reuses_i(rom, rom_size, layout, address) =>
+            {
+                i_reuses.push((address, opcode));
+            }
+            _ => {}
+        }
+    }
+
+    let clue = |preset: QuirkPreset, weight: f32, found: &[(usize, u16)], what: &str| {
+        let &(address, opcode) = found.first()?;
+        Some(Clue {
+            preset,
+            weight,
+            reason: format!(
+                "{} {}, first {:04X} at {:#06X}",
+                found.len(),
+                what,
+                opcode,
+                address
+            ),
+        })
+    };
+    [
+        clue(
+            QuirkPreset::Schip,
+            SCHIP_OPCODE_WEIGHT,
+            &schip_opcodes,
+            "SUPER-CHIP opcodes",
+        ),
+        clue(
+            QuirkPreset::Cosmac,
+            SHIFT_WEIGHT,
+            &shifts,
+            "shifts of VY into VX",
+        ),
+        clue(
+            QuirkPreset::Modern,
+            IN_PLACE_SHIFT_WEIGHT,
+            &in_place_shifts,
+            "shifts of VX in place",
+        ),
+        clue(
+            QuirkPreset::Cosmac,
+            I_REUSE_WEIGHT,
+            &i_reuses,
+            "uses of I after LD [I], Vx or LD Vx, [I] without setting it again",
+        ),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Runs a ROM headless under a preset with no keys pressed, a crash ending the run
+pub(crate) fn run_trial(
+    cartridge_driver: &CartridgeDriver,
+    frame_size: usize,
+    layout: ProgramLayout,
+    preset: QuirkPreset,
+) -> Trial {
+    let settings = Settings {
+        frame_size,
+        quirks: preset.quirks(),
+        program_layout: layout,
+        seed: Some(TRIAL_SEED),
+        ..Settings::default()
+    };
+    let mut processor = Processor::with_settings(&settings);
+    let mut trial = Trial::default();
+    processor.run_trial(cartridge_driver, TRIAL_FRAMES, &mut trial);
+    trial
+}
+
+/// Returns the clues against a preset given by its trial run
+fn trial_clues(preset: QuirkPreset, trial: &Trial) -> Vec<Clue> {
+    let mut clues = Vec::new();
+    if trial.crashed {
+        clues.push(Clue {
+            preset,
+            weight: CRASH_WEIGHT,
+            reason: format!("crashed after {} frames of its trial run", trial.frames),
+        });
+    }
+    if trial.errors > 0 {
+        clues.push(Clue {
+            preset,
+            weight: ERROR_WEIGHT,
+            reason: format!("{} errors in its trial run", trial.errors),
+        });
+    }
+    clues
+}
+
+/// Returns the preset with the highest score, the sum of the weights of its clues
+pub(crate) fn choose(clues: Vec<Clue>) -> Detection {
+    let scores = PRESETS.map(|preset| {
+        clues
+            .iter()
+            .filter(|clue| clue.preset == preset)
+            .map(|clue| clue.weight)
+            .sum::<f32>()
+    });
+    let mut best = 0;
+    for (index, score) in scores.iter().enumerate() {
+        if *score > scores[best] {
+            best = index;
+        }
+    }
+    let total: f32 = scores
+        .iter()
+        .map(|score| (score - scores[best]).exp())
+        .sum();
+    Detection {
+        preset: PRESETS[best],
+        confidence: 1.0 / total,
+        clues,
+    }
+}
+
+/// Infers the quirk preset of a ROM from its code and from a trial run under each preset
+pub(crate) fn detect(
+    cartridge_driver: &CartridgeDriver,
+    frame_size: usize,
+    layout: ProgramLayout,
+) -> Detection {
+    let mut clues = code_clues(&cartridge_driver.rom, cartridge_driver.rom_size, layout);
+    for preset in PRESETS {
+        let trial = run_trial(cartridge_driver, frame_size, layout, preset);
+        clues.extend(trial_clues(preset, &trial));
+    }
+    choose(clues)
+}
+
+#[cfg(test)]
+#[path = "./quirk_detector_test.rs"]
+mod quirk_detector_test;
@@ -0,0 +1,79 @@
+use super::*;
+use crate::constants::MEMORY_SIZE;
+
+fn cartridge(program: &[u8]) -> CartridgeDriver {
+    let mut rom = [0; MEMORY_SIZE];
+    rom[..program.len()].copy_from_slice(program);
+    CartridgeDriver {
+        rom,
+        rom_size: program.len(),
+    }
+}
+
+#[test]
+fn test_code_clues() {
+    // SHR V0, V1 / HIGH / LD [I], V1 / LD V1, [I] / JP 0x0208
+    let rom = [0x80, 0x16, 0x00, 0xFF, 0xF1, 0x55, 0xF1, 0x65, 0x12, 0x08];
+    let reasons: Vec<(QuirkPreset, String)> = code_clues(&rom, rom.len(), ProgramLayout::default())
+        .into_iter()
+        .map(|clue| (clue.preset, clue.reason))
+        .collect();
+    assert_eq!(
+        reasons,
+        [
+            (
+                QuirkPreset::Schip,
+                "1 SUPER-CHIP opcodes, first 00FF at 0x0202".to_string()
+            ),
+            (
+                QuirkPreset::Cosmac,
+                "1 shifts of VY into VX, first 8016 at 0x0200".to_string()
+            ),
+            (
+                QuirkPreset::Cosmac,
+                "1 uses of I after LD [I], Vx or LD Vx, [I] without setting it again, first F155 at 0x0204"
+                    .to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_choose_without_clues() {
+    let detection = choose(Vec::new());
+    assert_eq!(detection.preset, QuirkPreset::Modern);
+    assert!((detection.confidence - 1.0 / 3.0).abs() < 1e-6);
+    assert_eq!(detection.summary(), "modern (33% confidence)");
+}
+
+#[test]
+fn test_detect_from_code_and_trials() {
+    // SHR V0, V1 / LD [I], V1 / LD V1, [I] / JP 0x0206
+    let detection = detect(
+        &cartridge(&[0x80, 0x16, 0xF1, 0x55, 0xF1, 0x65, 0x12, 0x06]),
+        10,
+        ProgramLayout::default(),
+    );
+    assert_eq!(detection.preset, QuirkPreset::Cosmac);
+    assert!(detection.confidence > 0.5);
+
+    // CALL 0x0200 overflows the 16 stack entries in the second frame
+    let trial = run_trial(
+        &cartridge(&[0x22, 0x00]),
+        10,
+        ProgramLayout::default(),
+        QuirkPreset::Modern,
+    );
+    assert!(trial.crashed);
+    assert_eq!(trial.frames, 1);
+
+    // LD I, 0xFFF then LD B, V0 writes past the end of the memory in the first frame
+    let trial = run_trial(
+        &cartridge(&[0xAF, 0xFF, 0xF0, 0x33]),
+        10,
+        ProgramLayout::default(),
+        QuirkPreset::Modern,
+    );
+    assert!(trial.crashed);
+    assert_eq!((trial.frames, trial.errors), (0, 0));
+}
@@ -0,0 +1,393 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cli::CaptureOptions;
+use crate::constants::{FRAME_FREQUENCY, SCREEN_HEIGHT, SCREEN_SIZE, SCREEN_WIDTH};
+use crate::peripherals::palette::{Palette, PALETTE_SIZE};
+use crate::settings::Settings;
+
+use super::screenshot::timestamped_file_name;
+
+// Shortest delay between two GIF frames, most viewers slow down shorter ones
+const MIN_GIF_DELAY: usize = 2;
+
+/// Video formats of the recordings
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RecordingFormat {
+    /// Animated GIF, identical frames merged into longer ones (default)
+    Gif,
+
+    /// Uncompressed YUV4MPEG2 video at 60 frames per second, for ffmpeg
+    Y4m,
+}
+
+impl RecordingFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            RecordingFormat::Gif => "gif",
+            RecordingFormat::Y4m => "y4m",
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        Self::value_variants()
+            .iter()
+            .copied()
+            .find(|format| format.extension() == extension)
+    }
+}
+
+/// Records the display at every 60Hz frame, between the frames given on the command line
+/// and between two presses of the recording hotkey
+pub(crate) struct Recorder {
+    directory: PathBuf,
+    format: RecordingFormat,
+    scale: usize,
+    palette: Palette,
+
+    /// Recording scheduled from the command line: file (timestamped if not set),
+    /// first frame and frame it stops at
+    scheduled: Option<(Option<PathBuf>, usize, Option<usize>)>,
+
+    /// Number of frames captured so far, recorded or not
+    frame: usize,
+
+    /// Current recording and its file
+    writer: Option<(PathBuf, Box<dyn FrameWriter>)>,
+
+    /// File of the last finished recording
+    last_path: Option<PathBuf>,
+
+    /// First error met, recording stops after it
+    error: Option<String>,
+}
+
+impl Recorder {
+    /// Returns an error if the recording scheduled from the command line would be empty
+    pub(crate) fn new(
+        settings: &Settings,
+        options: Option<&CaptureOptions>,
+    ) -> Result<Self, String> {
+        let scheduled = options.and_then(|options| {
+            let path = options.record.as_ref()?;
+            Some((
+                path.as_ref().map(PathBuf::from),
+                options.record_start.unwrap_or(0),
+                options.record_stop,
+            ))
+        });
+        if let Some((_, start, Some(stop))) = scheduled {
+            if stop <= start {
+                return Err(format!(
+                    "Error: --record-stop {} must come after --record-start {}",
+                    stop, start
+                ));
+            }
+        }
+        Ok(Recorder {
+            directory: PathBuf::from(&settings.capture_directory),
+            format: settings.record_format,
+            scale: settings.record_scale,
+            palette: settings.palette,
+            scheduled,
+            frame: 0,
+            writer: None,
+            last_path: None,
+            error: None,
+        })
+    }
+
+    /// Records the display if a recording is running, to be called once per 60Hz frame
+    pub(crate) fn capture_frame(&mut self, pixels: &[bool]) {
+        if let Some((path, start, stop)) = self.scheduled.clone() {
+            if self.frame == start {
+                self.start(path);
+            }
+            if stop == Some(self.frame) {
+                self.stop();
+            }
+        }
+
+        if let Some((_, writer)) = &mut self.writer {
+            if let Err(e) = writer.write_frame(pixels) {
+                self.fail(e);
+            }
+        }
+        self.frame += 1;
+    }
+
+    /// Starts a recording in the given palette, or stops the current one
+    pub(crate) fn toggle(&mut self, palette: &Palette) {
+        if self.writer.is_some() {
+            self.stop();
+        } else {
+            self.palette = *palette;
+            self.start(None);
+        }
+    }
+
+    pub(crate) fn is_recording(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// Stops the current recording, returning the file of the last recording
+    /// or the first error met
+    pub(crate) fn finish(&mut self) -> Result<Option<PathBuf>, String> {
+        self.stop();
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(self.last_path.clone()),
+        }
+    }
+
+    fn start(&mut self, path: Option<PathBuf>) {
+        if self.error.is_some() {
+            return;
+        }
+        let path = path.unwrap_or_else(|| {
+            self.directory
+                .join(timestamped_file_name(self.format.extension()))
+        });
+        let format = RecordingFormat::from_path(&path).unwrap_or(self.format);
+
+        let writer = create_file(&path).and_then(|file| -> Result<Box<dyn FrameWriter>, _> {
+            match format {
+                RecordingFormat::Gif => {
+                    Ok(Box::new(GifWriter::new(file, self.scale, &self.palette)?))
+                }
+                RecordingFormat::Y4m => {
+                    Ok(Box::new(Y4mWriter::new(file, self.scale, &self.palette)?))
+                }
+            }
+        });
+        match writer {
+            Ok(writer) => {
+                log::info!("Recording to {}", path.display());
+                self.writer = Some((path, writer));
+            }
+            Err(e) => self.fail(format!(
+                "Error: recording {} not writable: {}",
+                path.display(),
+                e
+            )),
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some((path, writer)) = self.writer.take() {
+            match writer.finish() {
+                Ok(()) => {
+                    log::info!("Recording saved to {}", path.display());
+                    self.last_path = Some(path);
+                }
+                Err(e) => self.fail(format!(
+                    "Error: recording {} not writable: {}",
+                    path.display(),
+                    e
+                )),
+            }
+        }
+    }
+
+    fn fail(&mut self, message: String) {
+        log::error!("{}", message);
+        self.writer = None;
+        self.error.get_or_insert(message);
+    }
+}
+
+fn create_file(path: &Path) -> Result<BufWriter<File>, String> {
+    if let Some(directory) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+    }
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|e| e.to_string())
+}
+
+/// Encoder of the frames of a recording
+trait FrameWriter {
+    fn write_frame(&mut self, pixels: &[bool]) -> Result<(), String>;
+
+    fn finish(self: Box<Self>) -> Result<(), String>;
+}
+
+// Time elapsed at the start of a 60Hz frame, in hundredths of a second, as used by GIF delays
+fn centiseconds(frame: usize) -> usize {
+    (frame as f64 * 100.0 / FRAME_FREQUENCY).round() as usize
+}
+
+// Delays of the GIF frames showing one image for `delay` centiseconds, as one frame can only
+// last up to u16::MAX centiseconds
+fn gif_delays(delay: usize) -> Vec<u16> {
+    let mut delays = vec![u16::MAX; delay / u16::MAX as usize];
+    match delay % u16::MAX as usize {
+        0 if !delays.is_empty() => {}
+        remainder => delays.push(remainder.max(1) as u16),
+    }
+    delays
+}
+
+// Palette index of every pixel of the display upscaled by `scale`
+fn scale_indices(pixels: &[u8], scale: usize) -> Vec<u8> {
+    let mut indices = Vec::with_capacity(SCREEN_SIZE * scale * scale);
+    for row in pixels.chunks_exact(SCREEN_WIDTH) {
+        let scaled_row: Vec<u8> = row
+            .iter()
+            .flat_map(|index| std::iter::repeat_n(*index, scale))
+            .collect();
+        for _ in 0..scale {
+            indices.extend_from_slice(&scaled_row);
+        }
+    }
+    indices
+}
+
+/// Animated GIF, with the display colours as global palette.
+/// A frame identical to the previous one only makes it last longer, and a frame replaced
+/// before the shortest GIF delay is dropped, the next one taking its place.
+struct GifWriter {
+    encoder: gif::Encoder<BufWriter<File>>,
+    scale: usize,
+
+    /// Palette indices of the frame waiting for its delay, and the frame it appeared at
+    pending: Option<(Vec<u8>, usize)>,
+
+    /// Number of frames written so far, including the merged ones
+    frame: usize,
+}
+
+impl GifWriter {
+    fn new(file: BufWriter<File>, scale: usize, palette: &Palette) -> Result<Self, String> {
+        let size = |length: usize| u16::try_from(length * scale).map_err(|e| e.to_string());
+        let colors: Vec<u8> = palette.colors.iter().flatten().copied().collect();
+        let mut encoder =
+            gif::Encoder::new(file, size(SCREEN_WIDTH)?, size(SCREEN_HEIGHT)?, &colors)
+                .map_err(|e| e.to_string())?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|e| e.to_string())?;
+        Ok(GifWriter {
+            encoder,
+            scale,
+            pending: None,
+            frame: 0,
+        })
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        if let Some((pixels, start)) = self.pending.take() {
+            let delay = centiseconds(self.frame) - centiseconds(start);
+            let mut frame = gif::Frame {
+                width: (SCREEN_WIDTH * self.scale) as u16,
+                height: (SCREEN_HEIGHT * self.scale) as u16,
+                buffer: Cow::Owned(scale_indices(&pixels, self.scale)),
+                ..gif::Frame::default()
+            };
+            for delay in gif_delays(delay) {
+                frame.delay = delay;
+                self.encoder
+                    .write_frame(&frame)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FrameWriter for GifWriter {
+    fn write_frame(&mut self, pixels: &[bool]) -> Result<(), String> {
+        let indices: Vec<u8> = pixels.iter().map(|pixel| *pixel as u8).collect();
+        match &mut self.pending {
+            Some((pending, _)) if *pending == indices => {}
+            Some((pending, start))
+                if centiseconds(self.frame) - centiseconds(*start) < MIN_GIF_DELAY =>
+            {
+                *pending = indices;
+            }
+            _ => {
+                self.flush()?;
+                self.pending = Some((indices, self.frame));
+            }
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        self.flush()?;
+        let mut file = self.encoder.into_inner().map_err(|e| e.to_string())?;
+        file.flush().map_err(|e| e.to_string())
+    }
+}
+
+/// YUV4MPEG2 stream with 4:4:4 sampling, every frame written
+struct Y4mWriter {
+    file: BufWriter<File>,
+    scale: usize,
+
+    /// Y, Cb and Cr of every palette colour
+    colors: [[u8; 3]; PALETTE_SIZE],
+}
+
+impl Y4mWriter {
+    fn new(mut file: BufWriter<File>, scale: usize, palette: &Palette) -> Result<Self, String> {
+        writeln!(
+            file,
+            "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+            SCREEN_WIDTH * scale,
+            SCREEN_HEIGHT * scale,
+            FRAME_FREQUENCY as u32
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(Y4mWriter {
+            file,
+            scale,
+            colors: palette.colors.map(to_ycbcr),
+        })
+    }
+}
+
+impl FrameWriter for Y4mWriter {
+    fn write_frame(&mut self, pixels: &[bool]) -> Result<(), String> {
+        let indices: Vec<u8> = pixels.iter().map(|pixel| *pixel as u8).collect();
+        let indices = scale_indices(&indices, self.scale);
+
+        let mut frame = Vec::with_capacity(6 + indices.len() * 3);
+        frame.extend_from_slice(b"FRAME\n");
+        for plane in 0..3 {
+            frame.extend(
+                indices
+                    .iter()
+                    .map(|index| self.colors[*index as usize][plane]),
+            );
+        }
+        self.file.write_all(&frame).map_err(|e| e.to_string())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        self.file.flush().map_err(|e| e.to_string())
+    }
+}
+
+// BT.601 studio range, the default of YUV4MPEG2 readers
+fn to_ycbcr([r, g, b]: [u8; 3]) -> [u8; 3] {
+    let (r, g, b) = (r as f64, g as f64, b as f64);
+    let y = 16.0 + (65.481 * r + 128.553 * g + 24.966 * b) / 255.0;
+    let cb = 128.0 + (-37.797 * r - 74.203 * g + 112.0 * b) / 255.0;
+    let cr = 128.0 + (112.0 * r - 93.786 * g - 18.214 * b) / 255.0;
+    [y, cb, cr].map(|value| value.round() as u8)
+}
+
+#[cfg(test)]
+#[path = "./recorder_test.rs"]
+mod recorder_test;
@@ -0,0 +1,128 @@
+use super::*;
+use std::io::BufReader;
+
+use crate::peripherals::palette::PalettePreset;
+
+fn recorder(path: &Path, scale: usize, start: usize, stop: usize) -> Result<Recorder, String> {
+    let options = CaptureOptions {
+        screenshot: false,
+        capture_dir: None,
+        screenshot_size: None,
+        record: Some(Some(path.to_string_lossy().to_string())),
+        record_format: None,
+        record_start: Some(start),
+        record_stop: Some(stop),
+        record_scale: None,
+        wav: None,
+        heat_map: None,
+        profile: None,
+        coverage: None,
+    };
+    let settings = Settings {
+        record_scale: scale,
+        ..Settings::default()
+    };
+    Recorder::new(&settings, Some(&options))
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("chip8-{}-{}", std::process::id(), name))
+}
+
+fn frame(lit: usize) -> [bool; SCREEN_SIZE] {
+    let mut pixels = [false; SCREEN_SIZE];
+    pixels[lit] = true;
+    pixels
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(
+        RecordingFormat::from_path(Path::new("run.GIF")),
+        Some(RecordingFormat::Gif)
+    );
+    assert_eq!(
+        RecordingFormat::from_path(Path::new("dir/run.y4m")),
+        Some(RecordingFormat::Y4m)
+    );
+    assert_eq!(RecordingFormat::from_path(Path::new("run.mp4")), None);
+}
+
+#[test]
+fn test_stop_before_start() {
+    let path = temp_path("empty.gif");
+    assert_eq!(
+        recorder(&path, 1, 10, 10).err(),
+        Some("Error: --record-stop 10 must come after --record-start 10".to_string())
+    );
+    assert!(recorder(&path, 1, 10, 5).is_err());
+    assert!(recorder(&path, 1, 10, 11).is_ok());
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_gif_delays() {
+    assert_eq!(gif_delays(0), [1]);
+    assert_eq!(gif_delays(17), [17]);
+    assert_eq!(gif_delays(65535), [65535]);
+    assert_eq!(gif_delays(150000), [65535, 65535, 18930]);
+}
+
+#[test]
+fn test_gif_merges_identical_and_short_frames() {
+    let path = temp_path("merge.gif");
+    let mut recorder = recorder(&path, 1, 0, 20).unwrap();
+    for index in 0..25 {
+        let lit = match index {
+            0..=9 => 0,
+            10 => 1,
+            _ => 2,
+        };
+        recorder.capture_frame(&frame(lit));
+    }
+    assert_eq!(recorder.finish().unwrap(), Some(path.clone()));
+
+    let file = BufReader::new(File::open(&path).unwrap());
+    let mut decoder = gif::DecodeOptions::new().read_info(file).unwrap();
+    assert_eq!(
+        decoder.global_palette().unwrap(),
+        PalettePreset::Green.palette().colors.as_flattened()
+    );
+
+    // 10 frames of the first image, then the third one replacing the second one too short to show
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        let lit = frame.buffer.iter().position(|index| *index == 1);
+        frames.push((frame.delay, lit));
+    }
+    assert_eq!(frames, [(17, Some(0)), (16, Some(2))]);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_y4m_records_every_scheduled_frame() {
+    let path = temp_path("schedule.y4m");
+    let mut recorder = recorder(&path, 2, 2, 5).unwrap();
+    for _ in 0..10 {
+        recorder.capture_frame(&frame(0));
+    }
+    recorder.finish().unwrap();
+
+    let data = fs::read(&path).unwrap();
+    let header = b"YUV4MPEG2 W128 H64 F60:1 Ip A1:1 C444\n";
+    assert!(data.starts_with(header));
+    assert_eq!(data.len(), header.len() + 3 * (6 + 128 * 64 * 3));
+
+    // Top left pixel lit, luma of the foreground then of the background
+    let plane = &data[header.len() + 6..];
+    let foreground = to_ycbcr(PalettePreset::Green.palette().foreground());
+    let background = to_ycbcr(PalettePreset::Green.palette().background());
+    assert_eq!(plane[..3], [foreground[0], foreground[0], background[0]]);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_ycbcr_range() {
+    assert_eq!(to_ycbcr([0, 0, 0]), [16, 128, 128]);
+    assert_eq!(to_ycbcr([255, 255, 255]), [235, 128, 128]);
+}
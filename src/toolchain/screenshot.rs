@@ -0,0 +1,120 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+use crate::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::peripherals::palette::Palette;
+use crate::peripherals::post_processing::{Image, PostEffect, PostProcessor};
+use crate::settings::Settings;
+
+/// Resolution of the screenshots
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ScreenshotSize {
+    /// 64x32, one image pixel per display pixel
+    Native,
+
+    /// Upscaled by the scale factor, with the post-processing effects (default)
+    Scaled,
+}
+
+/// Writes the display to timestamped PNG files
+pub(crate) struct Screenshot {
+    directory: PathBuf,
+    size: ScreenshotSize,
+    scale: usize,
+    effects: Vec<PostEffect>,
+}
+
+impl Screenshot {
+    pub(crate) fn new(settings: &Settings) -> Self {
+        Screenshot {
+            directory: PathBuf::from(&settings.capture_directory),
+            size: settings.screenshot_size,
+            scale: settings.scale_factor as usize,
+            effects: settings.post_effects.clone(),
+        }
+    }
+
+    /// Returns the image of the display pixels in the given palette
+    pub(crate) fn render(&self, pixels: &[bool], palette: &Palette) -> Image {
+        let frame: Vec<u8> = pixels
+            .iter()
+            .flat_map(|pixel| {
+                if *pixel {
+                    palette.foreground()
+                } else {
+                    palette.background()
+                }
+            })
+            .collect();
+
+        match self.size {
+            ScreenshotSize::Native => Image {
+                width: SCREEN_WIDTH,
+                height: SCREEN_HEIGHT,
+                pixels: frame,
+            },
+            ScreenshotSize::Scaled => PostProcessor::new(&self.effects, self.scale)
+                .process(&frame)
+                .clone(),
+        }
+    }
+
+    /// Writes the display to a new file in the screenshot directory, returning its path
+    pub(crate) fn save(&self, pixels: &[bool], palette: &Palette) -> Result<PathBuf, String> {
+        fs::create_dir_all(&self.directory).map_err(|e| {
+            format!(
+                "Error: screenshot directory {} not writable: {}",
+                self.directory.display(),
+                e
+            )
+        })?;
+        let path = self.directory.join(timestamped_file_name("png"));
+        write_png(&path, &self.render(pixels, palette))?;
+        log::info!("Screenshot saved to {}", path.display());
+        Ok(path)
+    }
+}
+
+/// Returns a file name made of the current UTC time, such as chip8-20240131-235959-999.png
+pub(crate) fn timestamped_file_name(extension: &str) -> String {
+    let now = OffsetDateTime::now_utc();
+    format!(
+        "chip8-{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}.{}",
+        now.year(),
+        now.month() as u8,
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+        now.millisecond(),
+        extension
+    )
+}
+
+pub(crate) fn write_png(path: &Path, image: &Image) -> Result<(), String> {
+    let error = |e: &dyn std::fmt::Display| {
+        format!("Error: PNG file {} not writable: {}", path.display(), e)
+    };
+
+    let file = File::create(path).map_err(|e| error(&e))?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        image.width as u32,
+        image.height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| error(&e))?;
+    writer
+        .write_image_data(&image.pixels)
+        .map_err(|e| error(&e))
+}
+
+#[cfg(test)]
+#[path = "./screenshot_test.rs"]
+mod screenshot_test;
@@ -0,0 +1,59 @@
+use super::*;
+use std::io::BufReader;
+
+use crate::constants::SCREEN_SIZE;
+use crate::peripherals::palette::PalettePreset;
+
+fn screenshot(size: ScreenshotSize, directory: &Path) -> Screenshot {
+    Screenshot::new(&Settings {
+        scale_factor: 3,
+        screenshot_size: size,
+        capture_directory: directory.to_string_lossy().to_string(),
+        ..Settings::default()
+    })
+}
+
+fn pixels() -> [bool; SCREEN_SIZE] {
+    let mut pixels = [false; SCREEN_SIZE];
+    pixels[1] = true;
+    pixels
+}
+
+#[test]
+fn test_render_native() {
+    let palette = PalettePreset::Amber.palette();
+    let image = screenshot(ScreenshotSize::Native, Path::new(".")).render(&pixels(), &palette);
+    assert_eq!((image.width, image.height), (SCREEN_WIDTH, SCREEN_HEIGHT));
+    assert_eq!(image.pixels[0..3], palette.background());
+    assert_eq!(image.pixels[3..6], palette.foreground());
+}
+
+#[test]
+fn test_render_scaled() {
+    let palette = PalettePreset::Gameboy.palette();
+    let image = screenshot(ScreenshotSize::Scaled, Path::new(".")).render(&pixels(), &palette);
+    assert_eq!(
+        (image.width, image.height),
+        (SCREEN_WIDTH * 3, SCREEN_HEIGHT * 3)
+    );
+    assert_eq!(image.pixels[6..9], palette.background());
+    assert_eq!(image.pixels[9..12], palette.foreground());
+}
+
+#[test]
+fn test_save() {
+    let directory = std::env::temp_dir().join(format!("chip8-screenshot-{}", std::process::id()));
+    let palette = PalettePreset::White.palette();
+    let path = screenshot(ScreenshotSize::Native, &directory)
+        .save(&pixels(), &palette)
+        .unwrap();
+
+    let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+    assert!(file_name.starts_with("chip8-") && file_name.ends_with(".png"));
+
+    let file = BufReader::new(File::open(&path).unwrap());
+    let reader = png::Decoder::new(file).read_info().unwrap();
+    assert_eq!(reader.info().width as usize, SCREEN_WIDTH);
+    assert_eq!(reader.info().height as usize, SCREEN_HEIGHT);
+    fs::remove_dir_all(directory).unwrap();
+}